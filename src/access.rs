@@ -0,0 +1,411 @@
+use crate::{
+    config::{AuthPolicy, ServerConfig},
+    types::AppResult,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fs::File,
+    io::{BufReader, BufWriter},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+const CONNECTION_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rejection {
+    Banned,
+    TooManyConnections,
+    ServerFull,
+}
+
+/// IP addresses and key fingerprints that are not allowed to connect.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BanList {
+    pub ips: BTreeSet<IpAddr>,
+    pub keys: BTreeSet<String>,
+}
+
+impl BanList {
+    /// Loads the ban list, or returns an empty one if the file does not exist.
+    pub fn load(path: &Path) -> AppResult<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn save(&self, path: &Path) -> AppResult<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Adds an IP address or a key fingerprint. Returns false if it was already banned.
+    pub fn add(&mut self, entry: &str) -> bool {
+        match entry.parse::<IpAddr>() {
+            Ok(ip) => self.ips.insert(ip),
+            Err(_) => self
+                .keys
+                .insert(entry.trim_start_matches("SHA256:").to_string()),
+        }
+    }
+
+    /// Removes an IP address or a key fingerprint. Returns false if it was not banned.
+    pub fn remove(&mut self, entry: &str) -> bool {
+        match entry.parse::<IpAddr>() {
+            Ok(ip) => self.ips.remove(&ip),
+            Err(_) => self.keys.remove(entry.trim_start_matches("SHA256:")),
+        }
+    }
+
+    pub fn is_ip_banned(&self, ip: &IpAddr) -> bool {
+        self.ips.contains(ip)
+    }
+
+    pub fn is_key_banned(&self, fingerprint: &str) -> bool {
+        self.keys.contains(fingerprint)
+    }
+}
+
+/// Fingerprints of the keys listed in an `authorized_keys` file.
+#[derive(Debug, Default, Clone)]
+pub struct Allowlist {
+    keys: HashSet<String>,
+}
+
+impl Allowlist {
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let mut keys = HashSet::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // Lines look like `[options] key-type base64-key [comment]`,
+            // so the key is the first field that parses.
+            match line
+                .split_whitespace()
+                .find_map(|field| russh_keys::parse_public_key_base64(field).ok())
+            {
+                Some(key) => {
+                    keys.insert(key.fingerprint());
+                }
+                None => log::warn!("Skipping invalid line in allowlist: {line}"),
+            }
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn contains(&self, fingerprint: &str) -> bool {
+        self.keys.contains(fingerprint)
+    }
+}
+
+/// Keeps track of the open sessions to enforce the connection limits and the ban list.
+#[derive(Debug)]
+pub struct AccessControl {
+    ban_list: BanList,
+    ban_list_path: PathBuf,
+    // Used to pick up changes made to the ban list from the command line.
+    ban_list_modified: Option<SystemTime>,
+    allowlist: Allowlist,
+    allowlist_path: Option<PathBuf>,
+    allowlist_modified: Option<SystemTime>,
+    max_clients: usize,
+    max_sessions_per_ip: usize,
+    max_connections_per_minute: usize,
+    connection_attempts: HashMap<IpAddr, VecDeque<Instant>>,
+    sessions: HashMap<IpAddr, usize>,
+    total_sessions: usize,
+}
+
+impl AccessControl {
+    pub fn new(config: &ServerConfig) -> Self {
+        let mut access_control = Self {
+            ban_list: BanList::default(),
+            ban_list_path: config.ban_list.clone(),
+            ban_list_modified: None,
+            allowlist: Allowlist::default(),
+            allowlist_path: match &config.auth {
+                AuthPolicy::Allowlist { path } => Some(path.clone()),
+                _ => None,
+            },
+            allowlist_modified: None,
+            max_clients: config.max_clients,
+            max_sessions_per_ip: config.max_sessions_per_ip,
+            max_connections_per_minute: config.max_connections_per_minute,
+            connection_attempts: HashMap::new(),
+            sessions: HashMap::new(),
+            total_sessions: 0,
+        };
+        access_control.reload_ban_list();
+        access_control.reload_allowlist();
+        access_control
+    }
+
+    fn reload_allowlist(&mut self) {
+        let Some(path) = &self.allowlist_path else {
+            return;
+        };
+        let modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_some() && modified == self.allowlist_modified {
+            return;
+        }
+        match Allowlist::load(path) {
+            Ok(allowlist) => {
+                self.allowlist = allowlist;
+                self.allowlist_modified = modified;
+            }
+            Err(e) => log::error!("Failed to load allowlist: {e}"),
+        }
+    }
+
+    fn reload_ban_list(&mut self) {
+        let modified = std::fs::metadata(&self.ban_list_path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_some() && modified == self.ban_list_modified {
+            return;
+        }
+        match BanList::load(&self.ban_list_path) {
+            Ok(ban_list) => {
+                self.ban_list = ban_list;
+                self.ban_list_modified = modified;
+            }
+            Err(e) => log::error!("Failed to load ban list: {e}"),
+        }
+    }
+
+    /// Registers a new session and checks if it should be allowed.
+    /// The session is counted even when rejected, and must be closed with `close_session`.
+    pub fn open_session(&mut self, ip: Option<IpAddr>) -> Option<Rejection> {
+        self.reload_ban_list();
+        self.total_sessions += 1;
+
+        let Some(ip) = ip else {
+            return (self.total_sessions > self.max_clients).then_some(Rejection::ServerFull);
+        };
+
+        *self.sessions.entry(ip).or_insert(0) += 1;
+
+        let now = Instant::now();
+        let attempts = self.connection_attempts.entry(ip).or_default();
+        while attempts
+            .front()
+            .is_some_and(|time| now.duration_since(*time) > CONNECTION_RATE_WINDOW)
+        {
+            attempts.pop_front();
+        }
+        attempts.push_back(now);
+        let recent_attempts = attempts.len();
+        // Forget about hosts that stopped connecting.
+        self.connection_attempts.retain(|_, attempts| {
+            attempts
+                .back()
+                .is_some_and(|time| now.duration_since(*time) <= CONNECTION_RATE_WINDOW)
+        });
+
+        if self.ban_list.is_ip_banned(&ip) {
+            return Some(Rejection::Banned);
+        }
+        if recent_attempts > self.max_connections_per_minute
+            || self.sessions[&ip] > self.max_sessions_per_ip
+        {
+            return Some(Rejection::TooManyConnections);
+        }
+        if self.total_sessions > self.max_clients {
+            return Some(Rejection::ServerFull);
+        }
+        None
+    }
+
+    pub fn close_session(&mut self, ip: Option<IpAddr>) {
+        self.total_sessions = self.total_sessions.saturating_sub(1);
+        if let Some(ip) = ip {
+            if let Some(sessions) = self.sessions.get_mut(&ip) {
+                *sessions -= 1;
+                if *sessions == 0 {
+                    self.sessions.remove(&ip);
+                }
+            }
+        }
+    }
+
+    pub fn is_key_allowed(&mut self, fingerprint: &str) -> bool {
+        self.reload_allowlist();
+        self.allowlist.contains(fingerprint)
+    }
+
+    pub fn is_key_banned(&mut self, fingerprint: &str) -> bool {
+        self.reload_ban_list();
+        self.ban_list.is_key_banned(fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ban_list_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "sshattrick_bans_{name}_{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn access_control(name: &str, max_clients: usize) -> AccessControl {
+        AccessControl::new(&ServerConfig {
+            ban_list: ban_list_path(name),
+            max_clients,
+            max_sessions_per_ip: 2,
+            max_connections_per_minute: 4,
+            ..Default::default()
+        })
+    }
+
+    fn ip(address: &str) -> Option<IpAddr> {
+        Some(address.parse().unwrap())
+    }
+
+    #[test]
+    fn test_sessions_per_ip() {
+        let mut access_control = access_control("sessions", 10);
+        assert_eq!(access_control.open_session(ip("10.0.0.1")), None);
+        assert_eq!(access_control.open_session(ip("10.0.0.1")), None);
+        assert_eq!(
+            access_control.open_session(ip("10.0.0.1")),
+            Some(Rejection::TooManyConnections)
+        );
+        // Other hosts have their own limit.
+        assert_eq!(access_control.open_session(ip("10.0.0.2")), None);
+
+        // Rejected sessions are counted until closed too.
+        access_control.close_session(ip("10.0.0.1"));
+        access_control.close_session(ip("10.0.0.1"));
+        assert_eq!(access_control.sessions[&ip("10.0.0.1").unwrap()], 1);
+        assert_eq!(access_control.total_sessions, 2);
+        access_control.close_session(ip("10.0.0.1"));
+        assert!(!access_control
+            .sessions
+            .contains_key(&ip("10.0.0.1").unwrap()));
+
+        // Closing more sessions than were opened does not underflow.
+        access_control.close_session(ip("10.0.0.2"));
+        access_control.close_session(ip("10.0.0.2"));
+        access_control.close_session(None);
+        assert_eq!(access_control.total_sessions, 0);
+        assert!(access_control.sessions.is_empty());
+    }
+
+    #[test]
+    fn test_connection_rate() {
+        let mut access_control = access_control("rate", 10);
+        for _ in 0..4 {
+            assert_eq!(access_control.open_session(ip("10.0.0.1")), None);
+            access_control.close_session(ip("10.0.0.1"));
+        }
+        assert_eq!(
+            access_control.open_session(ip("10.0.0.1")),
+            Some(Rejection::TooManyConnections)
+        );
+        access_control.close_session(ip("10.0.0.1"));
+        assert_eq!(access_control.open_session(ip("10.0.0.2")), None);
+        access_control.close_session(ip("10.0.0.2"));
+
+        // Attempts older than the window no longer count.
+        let attempts = access_control
+            .connection_attempts
+            .get_mut(&ip("10.0.0.1").unwrap())
+            .unwrap();
+        for time in attempts.iter_mut() {
+            *time -= CONNECTION_RATE_WINDOW + Duration::from_secs(1);
+        }
+        assert_eq!(access_control.open_session(ip("10.0.0.1")), None);
+        assert_eq!(
+            access_control.connection_attempts[&ip("10.0.0.1").unwrap()].len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_max_clients() {
+        let mut access_control = access_control("clients", 3);
+        assert_eq!(access_control.open_session(ip("10.0.0.1")), None);
+        assert_eq!(access_control.open_session(ip("10.0.0.2")), None);
+        assert_eq!(access_control.open_session(None), None);
+        assert_eq!(
+            access_control.open_session(ip("10.0.0.3")),
+            Some(Rejection::ServerFull)
+        );
+        assert_eq!(
+            access_control.open_session(None),
+            Some(Rejection::ServerFull)
+        );
+
+        access_control.close_session(None);
+        access_control.close_session(ip("10.0.0.3"));
+        assert_eq!(
+            access_control.open_session(None),
+            Some(Rejection::ServerFull)
+        );
+        access_control.close_session(None);
+        access_control.close_session(ip("10.0.0.1"));
+        assert_eq!(access_control.open_session(ip("10.0.0.3")), None);
+    }
+
+    #[test]
+    fn test_bans() -> AppResult<()> {
+        let path = ban_list_path("bans");
+        let mut ban_list = BanList::default();
+        assert!(ban_list.add("10.0.0.1"));
+        assert!(ban_list.add("::1"));
+        assert!(ban_list.add("SHA256:abc"));
+        assert!(!ban_list.add("abc"));
+        ban_list.save(&path)?;
+
+        let mut ban_list = BanList::load(&path)?;
+        assert!(ban_list.is_ip_banned(&"10.0.0.1".parse()?));
+        assert!(ban_list.is_ip_banned(&"::1".parse()?));
+        assert!(!ban_list.is_ip_banned(&"10.0.0.2".parse()?));
+        assert!(ban_list.is_key_banned("abc"));
+        // Fingerprints are not mistaken for addresses, and the other way around.
+        assert!(!ban_list.is_key_banned("10.0.0.1"));
+        assert!(!ban_list.remove("10.0.0.2"));
+        assert!(ban_list.remove("10.0.0.1"));
+        assert!(ban_list.remove("SHA256:abc"));
+        assert!(!ban_list.remove("abc"));
+        assert!(!ban_list.is_ip_banned(&"10.0.0.1".parse()?));
+        assert!(!ban_list.is_key_banned("abc"));
+        assert!(BanList::load(&ban_list_path("missing"))?.ips.is_empty());
+
+        let mut access_control = AccessControl::new(&ServerConfig {
+            ban_list: path.clone(),
+            ..Default::default()
+        });
+        assert_eq!(
+            access_control.open_session(ip("10.0.0.1")),
+            Some(Rejection::Banned)
+        );
+        assert_eq!(access_control.open_session(ip("10.0.0.2")), None);
+        assert!(access_control.is_key_banned("abc"));
+        assert!(!access_control.is_key_banned("def"));
+
+        // Changes to the file are picked up without restarting.
+        ban_list.add("def");
+        ban_list.save(&path)?;
+        assert!(access_control.is_key_banned("def"));
+        assert_eq!(access_control.open_session(ip("10.0.0.1")), None);
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+}
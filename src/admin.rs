@@ -0,0 +1,369 @@
+use crate::{
+    game::AI_CLIENT_ID,
+    tournament::{TournamentFormat, MAX_TOURNAMENT_PLAYERS, MIN_TOURNAMENT_PLAYERS},
+    types::{AppResult, SshTerminal, TerminalHandle},
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Row, Table, TableState},
+};
+use std::time::Instant;
+
+const REDRAW_INTERVAL_MILLISECONDS: u128 = 200;
+
+#[derive(Debug, Clone)]
+pub struct ClientSummary {
+    pub id: usize,
+    pub identity: Option<String>,
+    pub game_id: Option<uuid::Uuid>,
+    pub bytes_flushed: u64,
+    pub is_muted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameSummary {
+    pub id: uuid::Uuid,
+    pub client_ids: (usize, usize),
+    pub names: (String, String),
+    pub connections_state: (bool, bool),
+    pub score: (u8, u8),
+    pub remaining_seconds: u128,
+    pub fps: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct BotSummary {
+    pub id: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ServerSnapshot {
+    pub clients: Vec<ClientSummary>,
+    pub pending_client: Option<(usize, u64)>,
+    pub games: Vec<GameSummary>,
+    // Bots waiting for an opponent.
+    pub bots: Vec<BotSummary>,
+    pub ai_scripts: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminAction {
+    Kick(usize),
+    Mute(usize),
+    EndGame(uuid::Uuid),
+    CreateTournament {
+        format: TournamentFormat,
+        size: usize,
+    },
+    Broadcast(String),
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Prompt {
+    Broadcast,
+    Tournament(TournamentFormat),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Panel {
+    Clients,
+    Games,
+}
+
+pub struct AdminConsole {
+    terminal: SshTerminal,
+    // Kept to close the channel, since the terminal backend does not expose it.
+    terminal_handle: TerminalHandle,
+    snapshot: ServerSnapshot,
+    panel: Panel,
+    clients_state: TableState,
+    games_state: TableState,
+    // Some while the admin is typing a broadcast message or the size of a tournament.
+    input: Option<(Prompt, String)>,
+    status: String,
+    last_draw: Instant,
+}
+
+impl AdminConsole {
+    pub fn new(terminal: SshTerminal, terminal_handle: TerminalHandle) -> Self {
+        Self {
+            terminal,
+            terminal_handle,
+            snapshot: ServerSnapshot::default(),
+            panel: Panel::Clients,
+            clients_state: TableState::default().with_selected(Some(0)),
+            games_state: TableState::default().with_selected(Some(0)),
+            input: None,
+            status: String::new(),
+            last_draw: Instant::now(),
+        }
+    }
+
+    pub fn terminal_handle(&self) -> &TerminalHandle {
+        &self.terminal_handle
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        self.last_draw.elapsed().as_millis() >= REDRAW_INTERVAL_MILLISECONDS
+    }
+
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = status.into();
+    }
+
+    pub fn handle_input(&mut self, key_code: KeyCode) -> Option<AdminAction> {
+        if let Some((prompt, input)) = self.input.as_mut() {
+            match key_code {
+                KeyCode::Esc => self.input = None,
+                KeyCode::Tab => {
+                    if let Prompt::Tournament(format) = prompt {
+                        *format = format.next();
+                    }
+                }
+                KeyCode::Enter => {
+                    let (prompt, text) = self
+                        .input
+                        .take()
+                        .unwrap_or((Prompt::Broadcast, String::new()));
+                    match prompt {
+                        Prompt::Broadcast if !text.trim().is_empty() => {
+                            return Some(AdminAction::Broadcast(text.trim().to_string()));
+                        }
+                        Prompt::Broadcast => {}
+                        Prompt::Tournament(format) => match text.trim().parse::<usize>() {
+                            Ok(size)
+                                if (MIN_TOURNAMENT_PLAYERS..=MAX_TOURNAMENT_PLAYERS)
+                                    .contains(&size) =>
+                            {
+                                return Some(AdminAction::CreateTournament { format, size });
+                            }
+                            _ => {
+                                self.status = format!(
+                                    "Tournaments have {MIN_TOURNAMENT_PLAYERS} to {MAX_TOURNAMENT_PLAYERS} players"
+                                )
+                            }
+                        },
+                    }
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) if !c.is_control() => input.push(c),
+                _ => {}
+            }
+            return None;
+        }
+
+        match key_code {
+            KeyCode::Esc => return Some(AdminAction::Quit),
+            KeyCode::Tab => {
+                self.panel = match self.panel {
+                    Panel::Clients => Panel::Games,
+                    Panel::Games => Panel::Clients,
+                };
+            }
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::Char('k') => {
+                if let Some(client) = self
+                    .clients_state
+                    .selected()
+                    .and_then(|idx| self.snapshot.clients.get(idx))
+                {
+                    return Some(AdminAction::Kick(client.id));
+                }
+            }
+            KeyCode::Char('m') => {
+                if let Some(client) = self
+                    .clients_state
+                    .selected()
+                    .and_then(|idx| self.snapshot.clients.get(idx))
+                {
+                    return Some(AdminAction::Mute(client.id));
+                }
+            }
+            KeyCode::Char('e') => {
+                if let Some(game) = self
+                    .games_state
+                    .selected()
+                    .and_then(|idx| self.snapshot.games.get(idx))
+                {
+                    return Some(AdminAction::EndGame(game.id));
+                }
+            }
+            KeyCode::Char('b') => self.input = Some((Prompt::Broadcast, String::new())),
+            KeyCode::Char('t') => {
+                self.input = Some((
+                    Prompt::Tournament(TournamentFormat::SingleElimination),
+                    String::new(),
+                ))
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (state, length) = match self.panel {
+            Panel::Clients => (&mut self.clients_state, self.snapshot.clients.len()),
+            Panel::Games => (&mut self.games_state, self.snapshot.games.len()),
+        };
+        if length == 0 {
+            state.select(Some(0));
+            return;
+        }
+        let selected = state.selected().unwrap_or(0) as isize + delta;
+        state.select(Some(selected.clamp(0, length as isize - 1) as usize));
+    }
+
+    pub fn draw(&mut self, snapshot: ServerSnapshot) -> AppResult<()> {
+        self.snapshot = snapshot;
+        self.last_draw = Instant::now();
+
+        let panel = self.panel;
+        let snapshot = &self.snapshot;
+        let clients_state = &mut self.clients_state;
+        let games_state = &mut self.games_state;
+        let input = &self.input;
+        let status = &self.status;
+
+        self.terminal.draw(|frame| {
+            let split = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Min(5),
+                Constraint::Length(3),
+            ])
+            .split(frame.size());
+
+            let pending = match snapshot.pending_client {
+                Some((id, seconds)) => format!("client {id} waiting for {seconds}s"),
+                None => "empty".to_string(),
+            };
+            frame.render_widget(
+                Paragraph::new(format!(
+                    "ssHattrick admin console - {} clients, {} games, queue: {}",
+                    snapshot.clients.len(),
+                    snapshot.games.len(),
+                    pending
+                ))
+                .bold(),
+                split[0],
+            );
+
+            let panels = Layout::horizontal([Constraint::Percentage(40), Constraint::Min(1)])
+                .split(split[1]);
+
+            let highlight = |selected: bool| {
+                if selected {
+                    Style::default().bg(Color::DarkGray)
+                } else {
+                    Style::default()
+                }
+            };
+
+            let client_rows = snapshot.clients.iter().map(|client| {
+                Row::new(vec![
+                    client.id.to_string(),
+                    client
+                        .identity
+                        .as_ref()
+                        .map(|identity| identity.chars().take(16).collect())
+                        .unwrap_or_else(|| "anonymous".to_string()),
+                    client
+                        .game_id
+                        .map(|id| id.to_string()[..8].to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    format!("{}kB", client.bytes_flushed / 1024),
+                    if client.is_muted { "muted" } else { "" }.to_string(),
+                ])
+            });
+            let clients_table = Table::new(
+                client_rows,
+                [
+                    Constraint::Length(6),
+                    Constraint::Length(18),
+                    Constraint::Length(10),
+                    Constraint::Length(8),
+                    Constraint::Min(5),
+                ],
+            )
+            .header(Row::new(vec!["Id", "Identity", "Game", "Sent", "Chat"]).bold())
+            .highlight_style(highlight(panel == Panel::Clients))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Clients")
+                    .border_style(highlight(panel == Panel::Clients)),
+            );
+            frame.render_stateful_widget(clients_table, panels[0], clients_state);
+
+            let game_rows = snapshot.games.iter().map(|game| {
+                let player = |id: usize, connected: bool| {
+                    if id == AI_CLIENT_ID {
+                        "AI".to_string()
+                    } else if connected {
+                        id.to_string()
+                    } else {
+                        format!("{id} (off)")
+                    }
+                };
+                Row::new(vec![
+                    game.id.to_string()[..8].to_string(),
+                    player(game.client_ids.0, game.connections_state.0),
+                    player(game.client_ids.1, game.connections_state.1),
+                    format!("{} - {}", game.score.0, game.score.1),
+                    format!(
+                        "{}:{:02}",
+                        game.remaining_seconds / 60,
+                        game.remaining_seconds % 60
+                    ),
+                    format!("{}", game.fps as u32),
+                ])
+            });
+            let games_table = Table::new(
+                game_rows,
+                [
+                    Constraint::Length(10),
+                    Constraint::Length(12),
+                    Constraint::Length(12),
+                    Constraint::Length(9),
+                    Constraint::Length(7),
+                    Constraint::Min(4),
+                ],
+            )
+            .header(Row::new(vec!["Game", "Red", "Blue", "Score", "Time", "FPS"]).bold())
+            .highlight_style(highlight(panel == Panel::Games))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Games")
+                    .border_style(highlight(panel == Panel::Games)),
+            );
+            frame.render_stateful_widget(games_table, panels[1], games_state);
+
+            let help = match input {
+                Some((Prompt::Broadcast, text)) => {
+                    Line::from(format!("Broadcast: {text}_  (Enter: send, Esc: cancel)"))
+                }
+                Some((Prompt::Tournament(format), text)) => Line::from(format!(
+                    "{} tournament, number of players: {text}_  (Tab: change format, Enter: create, Esc: cancel)",
+                    format.name()
+                )),
+                None => Line::from(
+                    "Tab: switch panel  ↑ ↓: select  k: kick client  m: mute/unmute  e: end game  b: broadcast  t: new tournament  Esc: quit",
+                ),
+            };
+            frame.render_widget(
+                Paragraph::new(vec![help, Line::from(status.as_str()).yellow()])
+                    .block(Block::default().borders(Borders::TOP)),
+                split[2],
+            );
+        })?;
+        Ok(())
+    }
+}
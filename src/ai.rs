@@ -0,0 +1,302 @@
+use crate::types::GameSide;
+use crossterm::event::KeyCode;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// The AI presses keys at about the rate of a held down key, so it is not faster than a player.
+pub const INPUT_INTERVAL_MILLISECONDS: f32 = 40.0;
+// The time between two inputs varies by up to this much, like the reactions of a player.
+const INPUT_JITTER_MILLISECONDS: f32 = 15.0;
+// After this many inputs without moving, the AI is stuck against the opponent and skates
+// in a random direction for a few inputs.
+const STUCK_INPUTS: u8 = 3;
+const ESCAPE_INPUTS: u8 = 4;
+const SHOOTING_DISTANCE: f32 = 60.0;
+const SHOOTING_ALIGNMENT: f32 = 12.0;
+const MAX_AIM_CORRECTIONS: u8 = 2;
+// Above this speed the AI brakes before turning towards its target.
+const BRAKING_SPEED: f32 = 0.3;
+
+/// What the AI can see of the game, in pitch coordinates.
+#[derive(Debug, Clone, Copy)]
+pub struct AiView {
+    pub side: GameSide,
+    pub catcher_position: (f32, f32),
+    pub velocity: (f32, f32),
+    pub puck_position: (f32, f32),
+    pub has_puck: bool,
+    pub is_shooting: bool,
+    // Where to skate to before shooting, in front of the opponent goal.
+    pub shooting_position: (f32, f32),
+    pub opponent_goalie_position: (f32, f32),
+}
+
+/// A simple opponent that chases the puck and shoots on the side the goalie is not covering.
+#[derive(Debug, Clone)]
+pub struct Ai {
+    elapsed: f32,
+    input_interval: f32,
+    aim_corrections: u8,
+    last_position: Option<(f32, f32)>,
+    stuck_inputs: u8,
+    escape: Option<(KeyCode, u8)>,
+    rng: StdRng,
+}
+
+impl Default for Ai {
+    fn default() -> Self {
+        Self::with_seed(rand::random())
+    }
+}
+
+impl Ai {
+    /// An AI whose reactions are drawn from the given seed, so that its matches can be replayed.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            elapsed: 0.0,
+            input_interval: INPUT_INTERVAL_MILLISECONDS,
+            aim_corrections: 0,
+            last_position: None,
+            stuck_inputs: 0,
+            escape: None,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn next_input(&mut self, view: &AiView, deltatime: f32) -> Option<KeyCode> {
+        self.elapsed += deltatime;
+        if self.elapsed < self.input_interval {
+            return None;
+        }
+        self.elapsed = 0.0;
+        self.input_interval = INPUT_INTERVAL_MILLISECONDS
+            + self
+                .rng
+                .gen_range(-INPUT_JITTER_MILLISECONDS..=INPUT_JITTER_MILLISECONDS);
+
+        if !view.is_shooting {
+            if self.last_position == Some(view.catcher_position) {
+                self.stuck_inputs += 1;
+            } else {
+                self.stuck_inputs = 0;
+            }
+            self.last_position = Some(view.catcher_position);
+            if self.stuck_inputs >= STUCK_INPUTS && self.escape.is_none() {
+                let directions = [KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right];
+                let direction = directions[self.rng.gen_range(0..directions.len())];
+                self.escape = Some((direction, ESCAPE_INPUTS));
+                self.stuck_inputs = 0;
+            }
+            if let Some((direction, inputs)) = self.escape {
+                self.escape = (inputs > 1).then_some((direction, inputs - 1));
+                return Some(direction);
+            }
+        }
+
+        if view.is_shooting {
+            if self.aim_corrections >= MAX_AIM_CORRECTIONS {
+                return None;
+            }
+            self.aim_corrections += 1;
+            return if view.opponent_goalie_position.1 > view.shooting_position.1 {
+                Some(KeyCode::Up)
+            } else {
+                Some(KeyCode::Down)
+            };
+        }
+        self.aim_corrections = 0;
+
+        if view.has_puck {
+            let (dx, dy) = (
+                view.shooting_position.0 - view.catcher_position.0,
+                view.shooting_position.1 - view.catcher_position.1,
+            );
+            let is_facing_goal = match view.side {
+                GameSide::Red => view.velocity.0 > 0.0,
+                GameSide::Blue => view.velocity.0 < 0.0,
+            };
+            if dx.abs() < SHOOTING_DISTANCE && dy.abs() < SHOOTING_ALIGNMENT && is_facing_goal {
+                return Some(KeyCode::Char(' '));
+            }
+            return Some(steer_towards(
+                view.catcher_position,
+                view.velocity,
+                view.shooting_position,
+            ));
+        }
+
+        Some(steer_towards(
+            view.catcher_position,
+            view.velocity,
+            view.puck_position,
+        ))
+    }
+}
+
+fn steer_towards(position: (f32, f32), velocity: (f32, f32), target: (f32, f32)) -> KeyCode {
+    let (dx, dy) = (target.0 - position.0, target.1 - position.1);
+    let horizontal = if dx > 0.0 {
+        KeyCode::Right
+    } else {
+        KeyCode::Left
+    };
+    let vertical = if dy > 0.0 { KeyCode::Down } else { KeyCode::Up };
+
+    if dx * velocity.0 < 0.0 && velocity.0.abs() > BRAKING_SPEED {
+        return horizontal;
+    }
+    if dy * velocity.1 < 0.0 && velocity.1.abs() > BRAKING_SPEED {
+        return vertical;
+    }
+    if dx.abs() > dy.abs() {
+        horizontal
+    } else {
+        vertical
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Longer than any input interval, so that every call gives an input.
+    const DELTATIME: f32 = INPUT_INTERVAL_MILLISECONDS + INPUT_JITTER_MILLISECONDS + 1.0;
+
+    fn view() -> AiView {
+        AiView {
+            side: GameSide::Red,
+            catcher_position: (40.0, 43.0),
+            velocity: (0.0, 0.0),
+            puck_position: (100.0, 43.0),
+            has_puck: false,
+            is_shooting: false,
+            shooting_position: (120.0, 43.0),
+            opponent_goalie_position: (150.0, 40.0),
+        }
+    }
+
+    #[test]
+    fn test_input_interval() {
+        // Returns the frames, one millisecond apart, at which the AI gave an input.
+        let inputs = |seed: u64| {
+            let mut ai = Ai::with_seed(seed);
+            let mut view = view();
+            (0..500)
+                .filter(|&frame| {
+                    // Keep moving, so that the AI does not think it is stuck.
+                    view.catcher_position.1 = frame as f32;
+                    ai.next_input(&view, 1.0).is_some()
+                })
+                .collect::<Vec<u32>>()
+        };
+        let frames = inputs(0);
+        assert_eq!(frames[0] + 1, INPUT_INTERVAL_MILLISECONDS as u32);
+        let intervals = frames
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]) as f32)
+            .collect::<Vec<f32>>();
+        assert!(intervals.iter().all(|interval| {
+            (interval - INPUT_INTERVAL_MILLISECONDS).abs() <= INPUT_JITTER_MILLISECONDS + 1.0
+        }));
+        // Reactions vary, but are the same for the same seed.
+        assert!(intervals.iter().any(|&interval| interval != intervals[0]));
+        assert_eq!(frames, inputs(0));
+        assert_ne!(frames, inputs(1));
+    }
+
+    #[test]
+    fn test_chase_and_shoot() {
+        let mut ai = Ai::with_seed(0);
+        // Skates towards the puck along the longest distance.
+        assert_eq!(ai.next_input(&view(), DELTATIME), Some(KeyCode::Right));
+        let view = AiView {
+            catcher_position: (100.0, 80.0),
+            ..view()
+        };
+        assert_eq!(ai.next_input(&view, DELTATIME), Some(KeyCode::Up));
+        // Brakes first when moving fast away from the target.
+        let view = AiView {
+            catcher_position: (40.0, 40.0),
+            velocity: (0.0, -1.0),
+            ..view
+        };
+        assert_eq!(ai.next_input(&view, DELTATIME), Some(KeyCode::Down));
+
+        // With the puck, it skates to the shooting position and shoots when facing the goal.
+        let view = AiView {
+            has_puck: true,
+            catcher_position: (90.0, 43.0),
+            velocity: (-0.1, 0.0),
+            ..view
+        };
+        assert_eq!(ai.next_input(&view, DELTATIME), Some(KeyCode::Right));
+        let view = AiView {
+            velocity: (0.1, 0.0),
+            ..view
+        };
+        assert_eq!(ai.next_input(&view, DELTATIME), Some(KeyCode::Char(' ')));
+        let blue_view = AiView {
+            side: GameSide::Blue,
+            catcher_position: (70.0, 43.0),
+            shooting_position: (40.0, 43.0),
+            velocity: (-0.1, 0.0),
+            ..view
+        };
+        assert_eq!(
+            ai.next_input(&blue_view, DELTATIME),
+            Some(KeyCode::Char(' '))
+        );
+
+        // During the wind up, it aims away from the goalie a limited number of times.
+        let shooting_view = AiView {
+            is_shooting: true,
+            ..view
+        };
+        assert_eq!(
+            ai.next_input(&shooting_view, DELTATIME),
+            Some(KeyCode::Down)
+        );
+        assert_eq!(
+            ai.next_input(&shooting_view, DELTATIME),
+            Some(KeyCode::Down)
+        );
+        assert_eq!(ai.next_input(&shooting_view, DELTATIME), None);
+        assert_eq!(ai.next_input(&view, DELTATIME), Some(KeyCode::Char(' ')));
+        let shooting_view = AiView {
+            opponent_goalie_position: (150.0, 46.0),
+            ..shooting_view
+        };
+        assert_eq!(ai.next_input(&shooting_view, DELTATIME), Some(KeyCode::Up));
+    }
+
+    #[test]
+    fn test_escape_when_stuck() {
+        let mut ai = Ai::with_seed(0);
+        let view = view();
+        let inputs = (0..STUCK_INPUTS + ESCAPE_INPUTS)
+            .map(|_| ai.next_input(&view, DELTATIME).unwrap())
+            .collect::<Vec<KeyCode>>();
+        let (chasing, escaping) = inputs.split_at(STUCK_INPUTS as usize);
+        assert!(chasing.iter().all(|&key| key == KeyCode::Right));
+        assert!(escaping.iter().all(|&key| key == escaping[0]));
+        assert!([KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right].contains(&escaping[0]));
+
+        // Once it moves again, it goes back to chasing the puck.
+        let view = AiView {
+            catcher_position: (41.0, 43.0),
+            ..view
+        };
+        assert_eq!(ai.next_input(&view, DELTATIME), Some(KeyCode::Right));
+
+        // The escape does not interrupt the wind up of a shot.
+        let mut ai = Ai::with_seed(0);
+        let shooting_view = AiView {
+            is_shooting: true,
+            ..view
+        };
+        let inputs = (0..2 * STUCK_INPUTS)
+            .map(|_| ai.next_input(&shooting_view, DELTATIME))
+            .collect::<Vec<Option<KeyCode>>>();
+        assert_eq!(inputs[..2], [Some(KeyCode::Down), Some(KeyCode::Down)]);
+        assert!(inputs[2..].iter().all(Option::is_none));
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{
+    ai::Ai,
+    game::{MatchStats, Restitution, Simulation, MINIMUM_DELTATIME_MILLISECONDS},
+    script::{AiScript, ScriptedAi},
+    types::GameSide,
+};
+use crossterm::event::KeyCode;
+use std::{
+    fmt, thread,
+    time::{Duration, Instant},
+};
+
+pub const BUILT_IN_AI_NAME: &str = "ai";
+
+/// An opponent that can play headless matches: the built-in AI or one of the scripts.
+#[derive(Debug, Clone)]
+pub enum Contender {
+    Ai,
+    Script(AiScript),
+}
+
+impl Contender {
+    pub fn find(name: &str, scripts: &[AiScript]) -> Option<Self> {
+        if name == BUILT_IN_AI_NAME {
+            return Some(Contender::Ai);
+        }
+        scripts
+            .iter()
+            .find(|script| script.name == name)
+            .map(|script| Contender::Script(script.clone()))
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Contender::Ai => BUILT_IN_AI_NAME,
+            Contender::Script(script) => &script.name,
+        }
+    }
+
+    fn player(&self, seed: u64) -> ComputerPlayer {
+        match self {
+            Contender::Ai => ComputerPlayer::Ai(Box::new(Ai::with_seed(seed))),
+            Contender::Script(script) => ComputerPlayer::Script(ScriptedAi::new(script.clone())),
+        }
+    }
+}
+
+enum ComputerPlayer {
+    // Boxed, as the random generator of the AI is much larger than a script.
+    Ai(Box<Ai>),
+    Script(ScriptedAi),
+}
+
+impl ComputerPlayer {
+    fn next_input(&mut self, simulation: &Simulation, side: GameSide) -> Option<KeyCode> {
+        match self {
+            ComputerPlayer::Ai(ai) => {
+                ai.next_input(&simulation.ai_view(side), MINIMUM_DELTATIME_MILLISECONDS)
+            }
+            ComputerPlayer::Script(script) => {
+                script.next_input(side, &simulation.snapshot(), MINIMUM_DELTATIME_MILLISECONDS)
+            }
+        }
+    }
+}
+
+/// Totals of one contender over all the matches of a batch.
+#[derive(Debug, Clone, Copy, Default)]
+struct ContenderTotals {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+    goals: u32,
+    goals_against: u32,
+    shots: u32,
+    shots_on_target: u32,
+    saves: u32,
+    possession_milliseconds: f64,
+    // Sum of the fastest puck of each match, in pixels per second.
+    max_puck_speeds: f64,
+}
+
+impl ContenderTotals {
+    fn add_match(&mut self, score: (u8, u8), stats: &MatchStats) {
+        match score.0.cmp(&score.1) {
+            std::cmp::Ordering::Greater => self.wins += 1,
+            std::cmp::Ordering::Equal => self.draws += 1,
+            std::cmp::Ordering::Less => self.losses += 1,
+        }
+        self.goals += score.0 as u32;
+        self.goals_against += score.1 as u32;
+        self.shots += stats.shots;
+        self.shots_on_target += stats.shots_on_target;
+        self.saves += stats.saves;
+        self.possession_milliseconds += stats.possession_milliseconds as f64;
+        self.max_puck_speeds += stats.max_puck_speed as f64;
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+        self.goals += other.goals;
+        self.goals_against += other.goals_against;
+        self.shots += other.shots;
+        self.shots_on_target += other.shots_on_target;
+        self.saves += other.saves;
+        self.possession_milliseconds += other.possession_milliseconds;
+        self.max_puck_speeds += other.max_puck_speeds;
+    }
+}
+
+/// Results of a batch of matches between two contenders, which swap sides after every match.
+#[derive(Debug, Clone, Default)]
+pub struct BatchReport {
+    names: (String, String),
+    matches: u32,
+    totals: (ContenderTotals, ContenderTotals),
+    red_wins: u32,
+    blue_wins: u32,
+    elapsed: Duration,
+    seed: u64,
+}
+
+impl BatchReport {
+    fn add_match(
+        &mut self,
+        first_side: GameSide,
+        score: (u8, u8),
+        stats: (MatchStats, MatchStats),
+    ) {
+        self.matches += 1;
+        match score.0.cmp(&score.1) {
+            std::cmp::Ordering::Greater => self.red_wins += 1,
+            std::cmp::Ordering::Less => self.blue_wins += 1,
+            std::cmp::Ordering::Equal => {}
+        }
+        let ((first_score, first_stats), (second_score, second_stats)) = match first_side {
+            GameSide::Red => ((score, stats.0), ((score.1, score.0), stats.1)),
+            GameSide::Blue => (((score.1, score.0), stats.1), (score, stats.0)),
+        };
+        self.totals.0.add_match(first_score, &first_stats);
+        self.totals.1.add_match(second_score, &second_stats);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.matches += other.matches;
+        self.totals.0.merge(&other.totals.0);
+        self.totals.1.merge(&other.totals.1);
+        self.red_wins += other.red_wins;
+        self.blue_wins += other.blue_wins;
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let matches = self.matches.max(1) as f64;
+        let percentage = |count: u32| 100.0 * count as f64 / matches;
+        let total_possession =
+            self.totals.0.possession_milliseconds + self.totals.1.possession_milliseconds;
+
+        writeln!(
+            f,
+            "Played {} matches in {:.1}s with seed {}\n",
+            self.matches,
+            self.elapsed.as_secs_f32(),
+            self.seed
+        )?;
+        writeln!(
+            f,
+            "{:<16} {:>6} {:>6} {:>6} {:>6} {:>8} {:>6} {:>9} {:>6} {:>10} {:>9}",
+            "",
+            "wins",
+            "draws",
+            "losses",
+            "goals",
+            "against",
+            "shots",
+            "on target",
+            "saves",
+            "possession",
+            "top speed"
+        )?;
+        for (name, totals) in [
+            (&self.names.0, &self.totals.0),
+            (&self.names.1, &self.totals.1),
+        ] {
+            let on_target = if totals.shots > 0 {
+                100.0 * totals.shots_on_target as f64 / totals.shots as f64
+            } else {
+                0.0
+            };
+            let possession = if total_possession > 0.0 {
+                100.0 * totals.possession_milliseconds / total_possession
+            } else {
+                0.0
+            };
+            writeln!(
+                f,
+                "{:<16} {:>5.1}% {:>5.1}% {:>5.1}% {:>6.2} {:>8.2} {:>6.2} {:>8.1}% {:>6.2} {:>9.1}% {:>9.0}",
+                name,
+                percentage(totals.wins),
+                percentage(totals.draws),
+                percentage(totals.losses),
+                totals.goals as f64 / matches,
+                totals.goals_against as f64 / matches,
+                totals.shots as f64 / matches,
+                on_target,
+                totals.saves as f64 / matches,
+                possession,
+                totals.max_puck_speeds / matches,
+            )?;
+        }
+        write!(
+            f,
+            "\nRed won {:.1}% of the matches and blue {:.1}%. Goals, shots and saves are per match, top speed in pixels per second.",
+            percentage(self.red_wins),
+            percentage(self.blue_wins)
+        )
+    }
+}
+
+/// Plays a match as fast as possible, until the final whistle.
+/// The same seed replays the same match.
+fn play_match(
+    red: &Contender,
+    blue: &Contender,
+    restitution: Restitution,
+    seed: u64,
+) -> Simulation {
+    let mut simulation = Simulation::with_seed(restitution, seed);
+    // Each AI draws its reactions from its own stream.
+    let mut red_player = red.player(seed.wrapping_mul(3).wrapping_add(1));
+    let mut blue_player = blue.player(seed.wrapping_mul(3).wrapping_add(2));
+    while !simulation.is_over() {
+        let mut inputs = vec![];
+        if simulation.is_running() {
+            for (side, player) in [
+                (GameSide::Red, &mut red_player),
+                (GameSide::Blue, &mut blue_player),
+            ] {
+                if let Some(key_code) = player.next_input(&simulation, side) {
+                    inputs.push((side, key_code));
+                }
+            }
+        }
+        simulation.step(&inputs);
+    }
+    simulation
+}
+
+/// Plays the matches between the two contenders over all the available cores.
+/// Match `i` is seeded with `seed + i`, so a batch can be replayed.
+pub fn run_batch(
+    first: &Contender,
+    second: &Contender,
+    matches: u32,
+    restitution: Restitution,
+    seed: u64,
+) -> BatchReport {
+    let start = Instant::now();
+    let threads = thread::available_parallelism()
+        .map(|threads| threads.get() as u32)
+        .unwrap_or(1)
+        .clamp(1, matches.max(1));
+
+    let mut report = BatchReport {
+        names: (first.name().to_string(), second.name().to_string()),
+        seed,
+        ..Default::default()
+    };
+    thread::scope(|scope| {
+        let handles = (0..threads)
+            .map(|thread_index| {
+                scope.spawn(move || {
+                    let mut partial = BatchReport::default();
+                    for index in (thread_index..matches).step_by(threads as usize) {
+                        let first_side = if index % 2 == 0 {
+                            GameSide::Red
+                        } else {
+                            GameSide::Blue
+                        };
+                        let seed = seed.wrapping_add(index as u64);
+                        let simulation = match first_side {
+                            GameSide::Red => play_match(first, second, restitution, seed),
+                            GameSide::Blue => play_match(second, first, restitution, seed),
+                        };
+                        partial.add_match(first_side, simulation.score(), simulation.stats());
+                    }
+                    partial
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            match handle.join() {
+                Ok(partial) => report.merge(&partial),
+                Err(_) => log::error!("A batch thread panicked"),
+            }
+        }
+    });
+    report.elapsed = start.elapsed();
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_add_match_swaps_sides() {
+        let mut report = BatchReport::default();
+        let red_stats = MatchStats {
+            shots: 3,
+            ..Default::default()
+        };
+        let blue_stats = MatchStats {
+            shots: 5,
+            ..Default::default()
+        };
+
+        // The first contender played red and won.
+        report.add_match(GameSide::Red, (2, 1), (red_stats, blue_stats));
+        assert_eq!((report.totals.0.wins, report.totals.1.losses), (1, 1));
+        assert_eq!((report.totals.0.shots, report.totals.1.shots), (3, 5));
+
+        // The first contender played blue and won again.
+        report.add_match(GameSide::Blue, (0, 4), (red_stats, blue_stats));
+        assert_eq!((report.totals.0.wins, report.totals.1.losses), (2, 2));
+        assert_eq!(
+            (report.totals.0.goals, report.totals.0.goals_against),
+            (6, 1)
+        );
+        assert_eq!(
+            (report.totals.1.goals, report.totals.1.goals_against),
+            (1, 6)
+        );
+        assert_eq!((report.totals.0.shots, report.totals.1.shots), (8, 8));
+        assert_eq!((report.red_wins, report.blue_wins), (1, 1));
+    }
+
+    #[test]
+    fn test_play_match_replays_seed() {
+        let restitution = Restitution::default();
+        let first = play_match(&Contender::Ai, &Contender::Ai, restitution, 7);
+        let second = play_match(&Contender::Ai, &Contender::Ai, restitution, 7);
+        assert_eq!(first.score(), second.score());
+        assert_eq!(first.stats().0.shots, second.stats().0.shots);
+    }
+
+    #[test]
+    fn test_run_batch() {
+        let matches = 8;
+        let report = run_batch(
+            &Contender::Ai,
+            &Contender::Ai,
+            matches,
+            Restitution::default(),
+            1,
+        );
+        assert_eq!(report.matches, matches);
+        let (first, second) = report.totals;
+        assert!(first.goals + second.goals > 0);
+        // The matches are not replays of each other.
+        let scores = (1..=matches as u64)
+            .map(|seed| {
+                play_match(&Contender::Ai, &Contender::Ai, Restitution::default(), seed).score()
+            })
+            .collect::<Vec<_>>();
+        assert!(scores.iter().any(|score| *score != scores[0]));
+    }
+}
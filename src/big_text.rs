@@ -238,6 +238,21 @@ pub fn blue_scored(color_1: Color, color_2: Color) -> Paragraph<'static> {
     )
 }
 
+pub fn post(color_1: Color, color_2: Color) -> Paragraph<'static> {
+    big_text(
+        vec![
+            "██████╗  ██████╗ ███████╗████████╗██╗",
+            "██╔══██╗██╔═══██╗██╔════╝╚══██╔══╝██║",
+            "██████╔╝██║   ██║███████╗   ██║   ██║",
+            "██╔═══╝ ██║   ██║╚════██║   ██║   ╚═╝",
+            "██║     ╚██████╔╝███████║   ██║   ██╗",
+            "╚═╝      ╚═════╝ ╚══════╝   ╚═╝   ╚═╝",
+        ],
+        color_1,
+        color_2,
+    )
+}
+
 pub fn red_won(color_1: Color, color_2: Color) -> Paragraph<'static> {
     big_text(
         vec![
@@ -283,6 +298,21 @@ pub fn draw(color_1: Color, color_2: Color) -> Paragraph<'static> {
     )
 }
 
+pub fn title(color_1: Color, color_2: Color) -> Paragraph<'static> {
+    big_text(
+        vec![
+            "███████╗███████╗██╗  ██╗ █████╗ ████████╗████████╗██████╗ ██╗ ██████╗██╗  ██╗",
+            "██╔════╝██╔════╝██║  ██║██╔══██╗╚══██╔══╝╚══██╔══╝██╔══██╗██║██╔════╝██║ ██╔╝",
+            "███████╗███████╗███████║███████║   ██║      ██║   ██████╔╝██║██║     █████╔╝ ",
+            "╚════██║╚════██║██╔══██║██╔══██║   ██║      ██║   ██╔══██╗██║██║     ██╔═██╗ ",
+            "███████║███████║██║  ██║██║  ██║   ██║      ██║   ██║  ██║██║╚██████╗██║  ██╗",
+            "╚══════╝╚══════╝╚═╝  ╚═╝╚═╝  ╚═╝   ╚═╝      ╚═╝   ╚═╝  ╚═╝╚═╝ ╚═════╝╚═╝  ╚═╝",
+        ],
+        color_1,
+        color_2,
+    )
+}
+
 pub fn disconnection(color_1: Color, color_2: Color) -> Paragraph<'static> {
     big_text(vec![
 "██████╗ ██╗███████╗ ██████╗ ██████╗ ███╗   ██╗███╗   ██╗███████╗ ██████╗████████╗██╗ ██████╗ ███╗   ██╗",
@@ -0,0 +1,203 @@
+use crate::{game::Snapshot, types::GameSide};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    sync::mpsc::{self, error::TrySendError},
+};
+
+// Bots get client ids far from the ones of the ssh clients.
+pub const FIRST_BOT_CLIENT_ID: usize = usize::MAX / 2;
+const MAX_NAME_LENGTH: usize = 16;
+// About a second of ticks. Messages to a bot that does not keep up are dropped rather than queued.
+const OUTBOX_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn key_code(&self) -> KeyCode {
+        match self {
+            Direction::Up => KeyCode::Up,
+            Direction::Down => KeyCode::Down,
+            Direction::Left => KeyCode::Left,
+            Direction::Right => KeyCode::Right,
+        }
+    }
+}
+
+/// What a bot can do during a match, like a player with the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum BotAction {
+    Move { direction: Direction },
+    // The puck leaves after a short wind up. Sent again during the wind up it charges a slapshot,
+    // or shoots a one-timer right after the first one.
+    Shoot,
+    // Bends the shot during the wind up.
+    Aim { direction: Direction },
+}
+
+impl BotAction {
+    /// The key a player would press for the action, if it can be done right now.
+    pub fn key_code(&self, is_shooting: bool) -> Option<KeyCode> {
+        match self {
+            BotAction::Move { direction } if !is_shooting => Some(direction.key_code()),
+            BotAction::Shoot => Some(KeyCode::Char(' ')),
+            BotAction::Aim { direction } if is_shooting => Some(direction.key_code()),
+            _ => None,
+        }
+    }
+}
+
+/// The first line a bot sends after connecting.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Hello {
+    name: String,
+    versus_bots: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage<'a> {
+    // Sent after the hello and after every match, until the next match starts.
+    Waiting,
+    Start {
+        side: GameSide,
+        opponent: &'a str,
+    },
+    Tick {
+        side: GameSide,
+        snapshot: &'a Snapshot,
+    },
+    End {
+        score: (u8, u8),
+        completed: bool,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// A program playing over a local socket, exchanging one JSON object per line.
+#[derive(Debug, Clone)]
+pub struct BotLink {
+    pub id: usize,
+    pub name: String,
+    // Bots that want to be paired with other bots, and not only be challenged by players.
+    pub versus_bots: bool,
+    outbox: mpsc::Sender<String>,
+    // Only the last action received before a tick is played.
+    action: Arc<Mutex<Option<BotAction>>>,
+    is_connected: Arc<AtomicBool>,
+}
+
+impl BotLink {
+    /// Reads the hello of a new bot, then keeps forwarding its actions and the messages sent to it.
+    pub async fn connect(stream: TcpStream, id: usize) -> Result<Self, anyhow::Error> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let line = lines
+            .next_line()
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Bot disconnected before saying hello"))?;
+        let hello = match serde_json::from_str::<Hello>(&line) {
+            Ok(hello) => hello,
+            Err(e) => {
+                let message = BotMessage::Error {
+                    message: format!("Invalid hello: {e}"),
+                };
+                writer
+                    .write_all(format!("{}\n", serde_json::to_string(&message)?).as_bytes())
+                    .await?;
+                return Err(e.into());
+            }
+        };
+        let name = hello
+            .name
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(MAX_NAME_LENGTH)
+            .collect::<String>();
+
+        let (outbox, mut inbox) = mpsc::channel::<String>(OUTBOX_CAPACITY);
+        let bot = Self {
+            id,
+            name: if name.trim().is_empty() {
+                "Bot".to_string()
+            } else {
+                name
+            },
+            versus_bots: hello.versus_bots,
+            outbox,
+            action: Arc::new(Mutex::new(None)),
+            is_connected: Arc::new(AtomicBool::new(true)),
+        };
+
+        let is_connected = bot.is_connected.clone();
+        tokio::spawn(async move {
+            while let Some(message) = inbox.recv().await {
+                if writer.write_all(message.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            is_connected.store(false, Ordering::Relaxed);
+        });
+
+        let reader_bot = bot.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(line)) = lines.next_line().await {
+                match serde_json::from_str::<BotAction>(&line) {
+                    Ok(action) => {
+                        if let Ok(mut last_action) = reader_bot.action.lock() {
+                            *last_action = Some(action);
+                        }
+                    }
+                    Err(e) => reader_bot.send(&BotMessage::Error {
+                        message: format!("Invalid action: {e}"),
+                    }),
+                }
+            }
+            log::info!("Bot {} disconnected", reader_bot.name);
+            reader_bot.is_connected.store(false, Ordering::Relaxed);
+        });
+
+        Ok(bot)
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.is_connected.load(Ordering::Relaxed)
+    }
+
+    pub fn send(&self, message: &BotMessage) {
+        let line = match serde_json::to_string(message) {
+            Ok(json) => format!("{json}\n"),
+            Err(e) => {
+                log::error!("Failed to serialize bot message: {e}");
+                return;
+            }
+        };
+        match self.outbox.try_send(line) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => log::debug!("Dropping message to slow bot {}", self.id),
+            Err(TrySendError::Closed(_)) => self.is_connected.store(false, Ordering::Relaxed),
+        }
+    }
+
+    /// The last action received since the previous call.
+    pub fn take_action(&self) -> Option<BotAction> {
+        self.action.lock().ok().and_then(|mut action| action.take())
+    }
+}
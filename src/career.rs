@@ -0,0 +1,38 @@
+use crate::{game::MatchStats, leaderboard::PlayerRecord};
+use serde::{Deserialize, Serialize};
+
+/// The leaderboard record of a player with the statistics summed over their matches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CareerStats {
+    // Flattened, so that the leaderboards saved before the statistics still load.
+    #[serde(flatten)]
+    pub record: PlayerRecord,
+    pub shots: u32,
+    pub shots_on_target: u32,
+    pub possession_seconds: f64,
+    pub steals: u32,
+    pub saves: u32,
+    // In pixels per second.
+    pub max_puck_speed: f32,
+}
+
+impl CareerStats {
+    /// Percentage of the shots that ended in a goal or a save.
+    pub fn shot_accuracy(&self) -> f32 {
+        if self.shots == 0 {
+            return 0.0;
+        }
+        100.0 * self.shots_on_target as f32 / self.shots as f32
+    }
+
+    pub fn add_match(&mut self, name: &str, goals_for: u8, goals_against: u8, stats: &MatchStats) {
+        self.record.add_result(name, goals_for, goals_against);
+        self.shots += stats.shots;
+        self.shots_on_target += stats.shots_on_target;
+        self.possession_seconds += stats.possession_milliseconds as f64 / 1000.0;
+        self.steals += stats.steals;
+        self.saves += stats.saves;
+        self.max_puck_speed = self.max_puck_speed.max(stats.max_puck_speed);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::config::ServerConfig;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+const CHAT_RATE_WINDOW: Duration = Duration::from_secs(60);
+const MAX_HISTORY_LENGTH: usize = 100;
+pub const MAX_MESSAGE_LENGTH: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChatRejection {
+    Muted,
+    TooManyMessages,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub name: String,
+    pub text: String,
+}
+
+/// The channel shared by the players in the lobby.
+#[derive(Debug)]
+pub struct Chat {
+    messages: VecDeque<ChatMessage>,
+    // Lowercase words that are replaced by asterisks.
+    word_filter: HashSet<String>,
+    max_messages_per_minute: usize,
+    sent_messages: HashMap<usize, VecDeque<Instant>>,
+    muted_clients: HashSet<usize>,
+    // Muted players with a key stay muted when they reconnect.
+    muted_identities: HashSet<String>,
+}
+
+impl Chat {
+    pub fn new(config: &ServerConfig) -> Self {
+        Self {
+            messages: VecDeque::new(),
+            word_filter: config
+                .chat_word_filter
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect(),
+            max_messages_per_minute: config.chat_max_messages_per_minute,
+            sent_messages: HashMap::new(),
+            muted_clients: HashSet::new(),
+            muted_identities: HashSet::new(),
+        }
+    }
+
+    pub fn messages(&self) -> &VecDeque<ChatMessage> {
+        &self.messages
+    }
+
+    pub fn is_muted(&self, client_id: usize, identity: Option<&str>) -> bool {
+        self.muted_clients.contains(&client_id)
+            || identity.is_some_and(|identity| self.muted_identities.contains(identity))
+    }
+
+    /// Mutes the client, or unmutes it if it was muted. Returns whether the client is now muted.
+    pub fn toggle_mute(&mut self, client_id: usize, identity: Option<&str>) -> bool {
+        if self.is_muted(client_id, identity) {
+            self.muted_clients.remove(&client_id);
+            if let Some(identity) = identity {
+                self.muted_identities.remove(identity);
+            }
+            false
+        } else {
+            self.muted_clients.insert(client_id);
+            if let Some(identity) = identity {
+                self.muted_identities.insert(identity.to_string());
+            }
+            true
+        }
+    }
+
+    /// Posts a message and returns its text as shown to the players.
+    pub fn post(
+        &mut self,
+        client_id: usize,
+        identity: Option<&str>,
+        name: &str,
+        text: &str,
+    ) -> Result<String, ChatRejection> {
+        if self.is_muted(client_id, identity) {
+            return Err(ChatRejection::Muted);
+        }
+
+        let now = Instant::now();
+        let sent = self.sent_messages.entry(client_id).or_default();
+        while sent
+            .front()
+            .is_some_and(|time| now.duration_since(*time) > CHAT_RATE_WINDOW)
+        {
+            sent.pop_front();
+        }
+        if sent.len() >= self.max_messages_per_minute {
+            return Err(ChatRejection::TooManyMessages);
+        }
+        sent.push_back(now);
+
+        let text = text
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(MAX_MESSAGE_LENGTH)
+            .collect::<String>();
+        let text = self.filter_words(text.trim());
+        self.messages.push_back(ChatMessage {
+            name: name.to_string(),
+            text: text.clone(),
+        });
+        if self.messages.len() > MAX_HISTORY_LENGTH {
+            self.messages.pop_front();
+        }
+        Ok(text)
+    }
+
+    /// Forgets the rate limit of a client that left. Mutes are kept for the admin to lift.
+    pub fn remove_client(&mut self, client_id: usize) {
+        self.sent_messages.remove(&client_id);
+    }
+
+    fn filter_words(&self, text: &str) -> String {
+        let mut filtered = String::with_capacity(text.len());
+        let mut word = String::new();
+        for c in text.chars() {
+            if c.is_alphanumeric() {
+                word.push(c);
+                continue;
+            }
+            self.push_word(&mut filtered, &word);
+            word.clear();
+            filtered.push(c);
+        }
+        self.push_word(&mut filtered, &word);
+        filtered
+    }
+
+    fn push_word(&self, filtered: &mut String, word: &str) {
+        if self.word_filter.contains(&word.to_lowercase()) {
+            filtered.extend(std::iter::repeat_n('*', word.chars().count()));
+        } else {
+            filtered.push_str(word);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chat(max_messages_per_minute: usize) -> Chat {
+        Chat::new(&ServerConfig {
+            chat_max_messages_per_minute: max_messages_per_minute,
+            chat_word_filter: vec!["Darn".to_string()],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_chat_filter() {
+        let mut chat = chat(10);
+        assert_eq!(
+            chat.post(0, None, "red", "  darn it,\x1b[2J DARN\n darned  "),
+            Ok("**** it,[2J **** darned".to_string())
+        );
+        let long = "a".repeat(2 * MAX_MESSAGE_LENGTH);
+        assert_eq!(
+            chat.post(0, None, "red", &long).map(|text| text.len()),
+            Ok(MAX_MESSAGE_LENGTH)
+        );
+        let messages = chat.messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].name, "red");
+        assert_eq!(messages[0].text, "**** it,[2J **** darned");
+
+        for _ in 0..MAX_HISTORY_LENGTH {
+            let _ = chat.post(1, None, "blue", "hi");
+            chat.remove_client(1);
+        }
+        assert_eq!(chat.messages().len(), MAX_HISTORY_LENGTH);
+        assert!(chat.messages().iter().all(|message| message.name == "blue"));
+    }
+
+    #[test]
+    fn test_chat_rate_window() {
+        let mut chat = chat(2);
+        assert!(chat.post(0, None, "red", "one").is_ok());
+        assert!(chat.post(0, None, "red", "two").is_ok());
+        assert_eq!(
+            chat.post(0, None, "red", "three"),
+            Err(ChatRejection::TooManyMessages)
+        );
+        // Other clients have their own window.
+        assert!(chat.post(1, None, "blue", "one").is_ok());
+
+        // Messages older than the window no longer count.
+        let sent = chat.sent_messages.get_mut(&0).unwrap();
+        sent[0] -= CHAT_RATE_WINDOW + Duration::from_secs(1);
+        assert!(chat.post(0, None, "red", "three").is_ok());
+        assert_eq!(
+            chat.post(0, None, "red", "four"),
+            Err(ChatRejection::TooManyMessages)
+        );
+
+        chat.remove_client(0);
+        assert!(chat.post(0, None, "red", "four").is_ok());
+        assert_eq!(chat.messages().len(), 5);
+    }
+
+    #[test]
+    fn test_chat_mutes() {
+        let mut chat = chat(10);
+        assert!(chat.toggle_mute(0, None));
+        assert_eq!(chat.post(0, None, "red", "hi"), Err(ChatRejection::Muted));
+        assert!(chat.post(1, None, "blue", "hi").is_ok());
+        assert!(!chat.toggle_mute(0, None));
+        assert!(chat.post(0, None, "red", "hi").is_ok());
+
+        // Players with a key stay muted when they reconnect with another id.
+        assert!(chat.toggle_mute(2, Some("key")));
+        chat.remove_client(2);
+        assert!(chat.is_muted(3, Some("key")));
+        assert_eq!(
+            chat.post(3, Some("key"), "green", "hi"),
+            Err(ChatRejection::Muted)
+        );
+        assert!(!chat.is_muted(3, Some("other")));
+        assert!(!chat.is_muted(3, None));
+
+        // Unmuting from the new id lifts the mute of the identity.
+        assert!(!chat.toggle_mute(3, Some("key")));
+        assert!(!chat.is_muted(4, Some("key")));
+        assert!(chat.post(3, Some("key"), "green", "hi").is_ok());
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    game::Restitution,
+    keys::{default_host_key_dir, HostKeyAlgorithm},
+    types::AppResult,
+};
+use serde::Deserialize;
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum AuthPolicy {
+    // Anyone can play.
+    #[default]
+    Open,
+    // Players must enter a shared password.
+    Password {
+        password: String,
+    },
+    // Only the keys listed in an `authorized_keys` file can play.
+    Allowlist {
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeasonConfig {
+    pub name: String,
+    // Dates like 2024-06-01, the end date is excluded.
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub auth: AuthPolicy,
+    // Fingerprints of the public keys allowed to open the admin console,
+    // as printed by `ssh-keygen -lf <key>`.
+    pub admin_keys: Vec<String>,
+    // File where match and session events are appended as JSON lines.
+    pub event_log: Option<PathBuf>,
+    // File with the banned IP addresses and key fingerprints.
+    pub ban_list: PathBuf,
+    pub max_clients: usize,
+    pub max_sessions_per_ip: usize,
+    pub max_connections_per_minute: usize,
+    // Directory where the host keys are stored.
+    pub host_key_dir: PathBuf,
+    // Host keys offered to clients, generated on first start.
+    pub host_key_algorithms: Vec<HostKeyAlgorithm>,
+    // How long players are warned before the server shuts down.
+    pub shutdown_notice_seconds: u64,
+    // If set, running games can finish before shutting down, up to the deadline.
+    pub shutdown_wait_for_games: bool,
+    pub shutdown_deadline_seconds: u64,
+    // File where the results and career statistics of the players with a key are stored.
+    pub leaderboard: PathBuf,
+    // File where the standings of the current ladder season and the past champions are stored.
+    pub ladder: PathBuf,
+    pub seasons: Vec<SeasonConfig>,
+    // Outside the configured seasons, the ladder resets every this many days, starting on a Monday.
+    // Zero disables the ladder outside the configured seasons.
+    pub season_length_days: u64,
+    // Directory with the Rhai scripts of the AI opponents, loaded at startup.
+    pub ai_scripts: Option<PathBuf>,
+    // Limit of operations for each decision of a script, so that no script can stall the server.
+    pub ai_script_max_operations: u64,
+    // How bouncy the collisions between the puck, the players, the goalies and the boards are.
+    pub restitution: Restitution,
+    pub chat_max_messages_per_minute: usize,
+    // Words replaced by asterisks in the lobby chat, case insensitive.
+    pub chat_word_filter: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            auth: AuthPolicy::Open,
+            admin_keys: vec![],
+            event_log: None,
+            ban_list: PathBuf::from("./bans.json"),
+            max_clients: 100,
+            max_sessions_per_ip: 4,
+            max_connections_per_minute: 20,
+            host_key_dir: default_host_key_dir(),
+            host_key_algorithms: vec![HostKeyAlgorithm::Ed25519],
+            shutdown_notice_seconds: 10,
+            shutdown_wait_for_games: false,
+            shutdown_deadline_seconds: 300,
+            leaderboard: PathBuf::from("./leaderboard.json"),
+            ladder: PathBuf::from("./ladder.json"),
+            seasons: vec![],
+            season_length_days: 7,
+            ai_scripts: None,
+            ai_script_max_operations: 10_000,
+            restitution: Restitution::default(),
+            chat_max_messages_per_minute: 10,
+            chat_word_filter: vec![],
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let file = File::open(path)?;
+        let config = serde_json::from_reader(BufReader::new(file))?;
+        Ok(config)
+    }
+
+    pub fn is_admin(&self, fingerprint: &str) -> bool {
+        self.admin_keys
+            .iter()
+            .any(|key| key.trim_start_matches("SHA256:") == fingerprint)
+    }
+}
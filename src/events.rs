@@ -0,0 +1,85 @@
+use crate::types::{AppResult, GameSide, SystemTimeTick, Tick};
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    SessionOpened {
+        client_id: usize,
+        peer: Option<String>,
+        identity: Option<String>,
+    },
+    SessionClosed {
+        client_id: usize,
+        duration_seconds: u64,
+    },
+    GameCreated {
+        game_id: uuid::Uuid,
+        red_client_id: usize,
+        blue_client_id: usize,
+    },
+    GoalScored {
+        game_id: uuid::Uuid,
+        side: GameSide,
+        scorer_client_id: usize,
+        game_time_seconds: u128,
+    },
+    Save {
+        game_id: uuid::Uuid,
+        side: GameSide,
+        goalie_client_id: usize,
+        game_time_seconds: u128,
+    },
+    GameEnded {
+        game_id: uuid::Uuid,
+        red_score: u8,
+        blue_score: u8,
+        completed: bool,
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp: Tick,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Writes events as JSON lines to a file. Events are dropped if no file is configured.
+#[derive(Debug, Default)]
+pub struct EventLog {
+    writer: Option<Mutex<BufWriter<File>>>,
+}
+
+impl EventLog {
+    pub fn open(path: &Path) -> AppResult<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: Some(Mutex::new(BufWriter::new(file))),
+        })
+    }
+
+    pub fn log(&self, event: Event) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        let record = Record {
+            timestamp: Tick::now(),
+            event: &event,
+        };
+        let Ok(mut writer) = writer.lock() else {
+            return;
+        };
+        serde_json::to_writer(&mut *writer, &record)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"))
+            .and_then(|_| writer.flush())
+            .unwrap_or_else(|e| log::error!("Failed to write event: {e}"));
+    }
+}
@@ -1,36 +1,118 @@
 use crate::{
-    big_text::{blue_scored, blue_won, dots, draw, red_scored, red_won, BigNumberFont},
+    ai::{Ai, AiView},
+    big_text::{blue_scored, blue_won, dots, draw, post, red_scored, red_won, BigNumberFont},
+    bot::{BotLink, BotMessage},
+    script::{AiScript, ScriptedAi},
     types::*,
     utils::*,
 };
 use crossterm::event::KeyCode;
 use image::{Rgba, RgbaImage};
 use once_cell::sync::Lazy;
-use rand::Rng;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use ratatui::{
     layout::{Constraint, Layout, Margin, Position, Rect},
-    style::Color,
+    style::{Color, Style, Stylize},
     text::Line,
     widgets::Paragraph,
     Frame,
 };
+use serde::{Deserialize, Serialize};
 use std::time::Instant;
 
-const MINIMUM_DELTATIME_MILLISECONDS: f32 = 18.0;
+pub const MINIMUM_DELTATIME_MILLISECONDS: f32 = 18.0;
 const GAME_DURATION_MILLISECONDS: u128 = 90 * 1000;
 const STARTING_DELAY_MILLISECONDS: u128 = 3000;
 const AFTER_GOAL_DELAY_MILLISECONDS: u128 = 2000;
-const ENDING_DELAY_MILLISECONDS: u128 = 1000;
+// The players go back to the lobby after the final result has been shown this long.
+const ENDING_DELAY_MILLISECONDS: u128 = 4000;
+const RECONNECTION_TIMEOUT_SECONDS: u64 = 30;
+const BANNER_DURATION_MILLISECONDS: u128 = 5000;
+const QUICK_CHAT_DURATION_MILLISECONDS: u128 = 3000;
+// Minimum time between two quick chat messages of the same player.
+const QUICK_CHAT_COOLDOWN_MILLISECONDS: u128 = 2000;
+// Sent with the number keys, starting from 1.
+const QUICK_CHAT_MESSAGES: [&str; 9] = [
+    "GG",
+    "Nice shot!",
+    "Nice save!",
+    "What a pass!",
+    "Good luck!",
+    "Oops!",
+    "Wow!",
+    "Sorry!",
+    "Thanks!",
+];
+
+/// Client id of the computer controlled players, which have no connection.
+pub const AI_CLIENT_ID: usize = usize::MAX;
 
 const MIN_X: f32 = 3.0;
 const MAX_X: f32 = 157.0;
 const MIN_Y: f32 = 3.0;
 const MAX_Y: f32 = 83.0;
 
+// The boards are rounded in the corners of the rink. Every viewer picks their own pitch while
+// the puck moves the same for both players, so the square corners of all the pitch images are
+// painted over to match.
+const RINK_CORNER_RADIUS: f32 = 16.0;
+// Center of the rounding of each corner, with the direction of the corner from it.
+const RINK_CORNERS: [((f32, f32), (f32, f32)); 4] = [
+    (
+        (MIN_X + RINK_CORNER_RADIUS, MIN_Y + RINK_CORNER_RADIUS),
+        (-1.0, -1.0),
+    ),
+    (
+        (MAX_X - RINK_CORNER_RADIUS, MIN_Y + RINK_CORNER_RADIUS),
+        (1.0, -1.0),
+    ),
+    (
+        (MIN_X + RINK_CORNER_RADIUS, MAX_Y - RINK_CORNER_RADIUS),
+        (-1.0, 1.0),
+    ),
+    (
+        (MAX_X - RINK_CORNER_RADIUS, MAX_Y - RINK_CORNER_RADIUS),
+        (1.0, 1.0),
+    ),
+];
+
 const GOALIE_AREA_WIDTH: f32 = 8.0;
 const GOALIE_AREA_HEIGHT: f32 = 26.0;
 const GOALIE_AREA_MIN_Y: f32 = 31.0;
 const GOALIE_AREA_MAX_Y: f32 = 55.0;
+// The nets are drawn behind the goal lines, in the boards.
+const GOAL_NET_DEPTH: f32 = 3.0;
+const GOAL_POST_SIZE: f32 = 2.0;
+// The posts at both ends of each goal line.
+const GOAL_POSTS: [CollisionBox; 4] = [
+    CollisionBox {
+        x: MIN_X,
+        y: GOALIE_AREA_MIN_Y - GOAL_POST_SIZE,
+        width: GOAL_POST_SIZE,
+        height: GOAL_POST_SIZE,
+    },
+    CollisionBox {
+        x: MIN_X,
+        y: GOALIE_AREA_MAX_Y,
+        width: GOAL_POST_SIZE,
+        height: GOAL_POST_SIZE,
+    },
+    CollisionBox {
+        x: MAX_X - GOAL_POST_SIZE,
+        y: GOALIE_AREA_MIN_Y - GOAL_POST_SIZE,
+        width: GOAL_POST_SIZE,
+        height: GOAL_POST_SIZE,
+    },
+    CollisionBox {
+        x: MAX_X - GOAL_POST_SIZE,
+        y: GOALIE_AREA_MAX_Y,
+        width: GOAL_POST_SIZE,
+        height: GOAL_POST_SIZE,
+    },
+];
+// Hits slower than this along the normal, in pixels per frame, do not show the post banner.
+const POST_BANNER_MIN_SPEED: f32 = 0.5;
+const POST_BANNER_MILLISECONDS: f32 = 1000.0;
 
 const RED_INITIAL_POSITION: (f32, f32) = (20.0, 40.0);
 const BLUE_INITIAL_POSITION: (f32, f32) = (132.0, 40.0);
@@ -46,6 +128,7 @@ const GOALIE_MAX_Y: f32 = 48.0;
 const ACCELERATION: f32 = 0.2;
 const DECELERATION: f32 = 0.4;
 const MAX_PLAYER_VELOCITY: f32 = 1.3;
+// Fastest the players can push the puck. Shots leave with their own power, above this.
 const MAX_PUCK_VELOCITY: f32 = 2.2;
 
 const GOALIE_MASS: f32 = 1000.0;
@@ -57,25 +140,54 @@ const PUCK_FRICTION_VELOCITY_LOSS: f32 = 0.99;
 const COEFFICIENT_OF_RESTITUTION: f32 = 0.7;
 const COFFICIENT_OF_WALL_BOUNCING: f32 = 0.25;
 
+// Sweeps of the collision masks move by this many pixels at a time.
+const MASK_SWEEP_STEP: f32 = 0.5;
+// A body is pushed out of a mask by at most this many pixels per frame.
+const MAX_MASK_PENETRATION: f32 = 8.0;
+// Pixels around a contact that give the orientation of the outline.
+const MASK_NORMAL_RADIUS: i32 = 1;
+// Bodies approaching slower than this are resting against each other and do not bounce.
+const RESTING_CONTACT_SPEED: f32 = 0.01;
+// Bounces of the puck computed in a single frame, enough for a corner between the boards and a goalie.
+const MAX_PUCK_CONTACTS_PER_FRAME: usize = 4;
+
 const SKATE_TRACE_LENGTH: usize = 512;
 
-const SHOOTING_COUNTER_MILLISECONDS: f32 = 350.0;
+// Terminals do not send key releases, so the shot leaves some time after the last press of the
+// shoot key. After the first press, it waits longer than the usual delay before a held key
+// starts repeating (500 to 660 ms), so that holding the key can charge a slapshot.
+const KEY_REPEAT_DELAY_MILLISECONDS: f32 = 700.0;
+// After a later press, by tapping the key again.
+const SHOT_RELEASE_MILLISECONDS: f32 = 350.0;
+// Presses closer than this come from the key repeat of a held key, which is let go once they stop.
+const KEY_REPEAT_INTERVAL_MILLISECONDS: f32 = 100.0;
+const HELD_KEY_RELEASE_MILLISECONDS: f32 = 150.0;
+// A second press this soon after the first one shoots a one-timer right away.
+const ONE_TIMER_MILLISECONDS: f32 = 150.0;
+// Wind up giving the full power of a slapshot, the shot leaves when it is reached.
+const SLAPSHOT_CHARGE_MILLISECONDS: f32 = 1000.0;
 const AFTER_SHOOTING_COUNTER_MILLISECONDS: f32 = 50.0;
 const AFTER_GOT_STOLEN_COUNTER_MILLISECONDS: f32 = 50.0;
 const SHOOTING_DIRECTION_MODIFIER: f32 = 0.35;
-const SHOOTING_POWER: f32 = 3.0;
+const WRIST_SHOT_POWER: f32 = 3.0;
+const ONE_TIMER_POWER: f32 = 3.75;
+const SLAPSHOT_POWER: f32 = 4.5;
+// Puck speed filling the shot meter of the stats overlay, in pixels per second.
+const SHOT_METER_FULL_SCALE: f32 = 1.5 * SLAPSHOT_POWER * 1000.0 / MINIMUM_DELTATIME_MILLISECONDS;
+const SHOT_METER_WIDTH: usize = 12;
+// The power bar is drawn this many pixels above a player winding up a shot.
+const POWER_BAR_OFFSET: u32 = 2;
+const POWER_BAR_COLOR: Rgba<u8> = Rgba([255, 215, 0, 255]);
+const POWER_BAR_FULL_COLOR: Rgba<u8> = Rgba([255, 60, 0, 255]);
+const POWER_BAR_EMPTY_COLOR: Rgba<u8> = Rgba([70, 70, 70, 255]);
 
-static PITCH_EMPTY: Lazy<RgbaImage> =
-    Lazy::new(|| read_image("pitch_empty.png").expect("Could not read pitch_empty.png."));
+static PITCH_EMPTY: Lazy<RgbaImage> = Lazy::new(|| pitch_image("pitch_empty.png"));
 
-static PITCH_CLASSIC: Lazy<RgbaImage> =
-    Lazy::new(|| read_image("pitch_classic.png").expect("Could not read pitch_classic.png."));
+static PITCH_CLASSIC: Lazy<RgbaImage> = Lazy::new(|| pitch_image("pitch_classic.png"));
 
-static PITCH_BASKET: Lazy<RgbaImage> =
-    Lazy::new(|| read_image("pitch_basket.png").expect("Could not read pitch_basket.png."));
+static PITCH_BASKET: Lazy<RgbaImage> = Lazy::new(|| pitch_image("pitch_basket.png"));
 
-static PITCH_ALT: Lazy<RgbaImage> =
-    Lazy::new(|| read_image("pitch_alt.png").expect("Could not read pitch_alt.png."));
+static PITCH_ALT: Lazy<RgbaImage> = Lazy::new(|| pitch_image("pitch_alt.png"));
 
 static PUCK_DARK: Lazy<RgbaImage> =
     Lazy::new(|| read_image("puck_white.png").expect("Could not read puck.png."));
@@ -111,9 +223,62 @@ static BLUE_PLAYER: Lazy<Vec<RgbaImage>> = Lazy::new(|| {
     images
 });
 
+static RED_PLAYER_MASKS: Lazy<Vec<CollisionMask>> =
+    Lazy::new(|| RED_PLAYER.iter().map(CollisionMask::new).collect());
+
+static BLUE_PLAYER_MASKS: Lazy<Vec<CollisionMask>> =
+    Lazy::new(|| BLUE_PLAYER.iter().map(CollisionMask::new).collect());
+
+static PUCK_MASK: Lazy<CollisionMask> = Lazy::new(|| CollisionMask::new(&PUCK_DARK));
+
 static BLUE_GOALIE: Lazy<RgbaImage> =
     Lazy::new(|| read_image("blue_goalie.png").expect("Could not read blue_goalie.png."));
 
+/// Reads a pitch image, rounds its corners like the boards of the rink and draws the goal posts.
+fn pitch_image(path: &str) -> RgbaImage {
+    let mut image = read_image(path).unwrap_or_else(|e| panic!("Could not read {path}: {e}"));
+    let (width, height) = (image.width(), image.height());
+    for (_, direction) in RINK_CORNERS {
+        // Work in the frame of the top left corner, where the boards are the first lines and columns.
+        let to_image = |x: u32, y: u32| {
+            (
+                if direction.0 < 0.0 { x } else { width - 1 - x },
+                if direction.1 < 0.0 { y } else { height - 1 - y },
+            )
+        };
+        let board_colors = [2, 1, 0].map(|y| {
+            let (x, y) = to_image((MIN_X + RINK_CORNER_RADIUS) as u32, y);
+            *image.get_pixel(x, y)
+        });
+        let corner_center = (MIN_X + RINK_CORNER_RADIUS, MIN_Y + RINK_CORNER_RADIUS);
+        for y in 0..corner_center.1 as u32 {
+            for x in 0..corner_center.0 as u32 {
+                // Only pixels entirely past the boards are painted, so that a puck touching
+                // them is still drawn on the ice.
+                let distance = (x as f32 + 1.0, y as f32 + 1.0)
+                    .sub(corner_center)
+                    .magnitude();
+                if distance <= RINK_CORNER_RADIUS {
+                    continue;
+                }
+                let layer = ((distance - RINK_CORNER_RADIUS) as usize).min(board_colors.len() - 1);
+                let (x, y) = to_image(x, y);
+                image.put_pixel(x, y, board_colors[layer]);
+            }
+        }
+    }
+    // The posts take the color of the goal line they close.
+    for post in GOAL_POSTS {
+        let line_color = *image.get_pixel(post.x as u32, GOALIE_AREA_MIN_Y as u32 - 1);
+        for y in post.y as u32..(post.y + post.height) as u32 {
+            for x in post.x as u32..(post.x + post.width) as u32 {
+                image.put_pixel(x, y, line_color);
+            }
+        }
+    }
+    image
+}
+
 fn base_image(palette: Palette) -> RgbaImage {
     match palette {
         Palette::Dark => PITCH_EMPTY.clone(),
@@ -123,6 +288,26 @@ fn base_image(palette: Palette) -> RgbaImage {
     }
 }
 
+/// Draws the charge of a shot as a bar above the player, as wide as the sprite.
+fn draw_power_bar(img: &mut RgbaImage, player: &impl Body, charge: f32, palette: Palette) {
+    let width = player.image(palette).width();
+    let (x, y) = (
+        player.position().0 as u32,
+        (player.position().1 as u32).saturating_sub(POWER_BAR_OFFSET),
+    );
+    let filled = (charge * width as f32).round() as u32;
+    for dx in 0..width.min(img.width().saturating_sub(x)) {
+        let color = if dx >= filled {
+            POWER_BAR_EMPTY_COLOR
+        } else if charge >= 1.0 {
+            POWER_BAR_FULL_COLOR
+        } else {
+            POWER_BAR_COLOR
+        };
+        img.put_pixel(x + dx, y, color);
+    }
+}
+
 fn skate_trace_color(palette: Palette) -> Rgba<u8> {
     match palette {
         Palette::Dark => Rgba([55, 55, 85, 255]),
@@ -145,16 +330,401 @@ fn puck_catcher_offset(orientation: Orientation) -> (f32, f32) {
     }
 }
 
-/// Simple collision detection using rectangles.
-/// Returns a boolean indicating if the two sprites are colliding.
-fn are_sprites_colliding(rect1: Rect, rect2: Rect) -> bool {
-    rect1.x < rect2.x + rect2.width
-        && rect1.x + rect1.width > rect2.x
-        && rect1.y < rect2.y + rect2.height
-        && rect1.y + rect1.height > rect2.y
+/// An axis aligned box in pitch coordinates. Unlike a `Rect`, it keeps the fractional part of the position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CollisionBox {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+/// Where a moving box meets another one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Contact {
+    // Fraction of the displacement travelled before touching.
+    time: f32,
+    // Normal of the touched surface, pointing towards the moving box.
+    normal: (f32, f32),
+    // How deep the boxes already overlapped before moving.
+    penetration: f32,
+}
+
+impl From<Rect> for CollisionBox {
+    fn from(rect: Rect) -> Self {
+        Self {
+            x: rect.x as f32,
+            y: rect.y as f32,
+            width: rect.width as f32,
+            height: rect.height as f32,
+        }
+    }
+}
+
+impl CollisionBox {
+    fn rect(&self) -> Rect {
+        Rect {
+            x: self.x as u16,
+            y: self.y as u16,
+            width: self.width as u16,
+            height: self.height as u16,
+        }
+    }
+
+    fn overlaps(&self, other: &CollisionBox) -> bool {
+        self.x < other.x + other.width
+            && self.x + self.width > other.x
+            && self.y < other.y + other.height
+            && self.y + self.height > other.y
+    }
+
+    /// How deep the boxes overlap along the shallowest axis, and the normal pointing out of the
+    /// other box on that side.
+    fn penetration(&self, other: &CollisionBox) -> Option<(f32, (f32, f32))> {
+        if !self.overlaps(other) {
+            return None;
+        }
+        [
+            (self.x + self.width - other.x, (-1.0, 0.0)),
+            (other.x + other.width - self.x, (1.0, 0.0)),
+            (self.y + self.height - other.y, (0.0, -1.0)),
+            (other.y + other.height - self.y, (0.0, 1.0)),
+        ]
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    /// Moves the box along the displacement and returns the first contact with the other box,
+    /// which does not move. Boxes that already overlap are separated along the shallowest axis.
+    fn sweep(&self, displacement: (f32, f32), other: &CollisionBox) -> Option<Contact> {
+        if let Some((penetration, normal)) = self.penetration(other) {
+            return Some(Contact {
+                time: 0.0,
+                normal,
+                penetration,
+            });
+        }
+
+        // Sweep the position of the box against the other box grown by the size of this one.
+        let axes = [
+            (
+                self.x,
+                displacement.0,
+                other.x - self.width,
+                other.x + other.width,
+            ),
+            (
+                self.y,
+                displacement.1,
+                other.y - self.height,
+                other.y + other.height,
+            ),
+        ];
+        let mut entry = [f32::NEG_INFINITY; 2];
+        let mut exit = f32::INFINITY;
+        for (index, (position, delta, min, max)) in axes.into_iter().enumerate() {
+            if delta == 0.0 {
+                if position <= min || position >= max {
+                    return None;
+                }
+                continue;
+            }
+            let (near, far) = if delta > 0.0 {
+                ((min - position) / delta, (max - position) / delta)
+            } else {
+                ((max - position) / delta, (min - position) / delta)
+            };
+            entry[index] = near;
+            exit = exit.min(far);
+        }
+
+        let time = entry[0].max(entry[1]);
+        if !(0.0..=1.0).contains(&time) || time >= exit {
+            return None;
+        }
+        let normal = if entry[0] > entry[1] {
+            (-displacement.0.signum(), 0.0)
+        } else {
+            (0.0, -displacement.1.signum())
+        };
+        Some(Contact {
+            time,
+            normal,
+            penetration: 0.0,
+        })
+    }
+
+    /// The limits of the rink for the box, which can enter the nets through the goal mouths.
+    fn rink_limits(&self) -> ((f32, f32), (f32, f32)) {
+        let is_in_goal_mouth =
+            self.y >= GOALIE_AREA_MIN_Y && self.y + self.height <= GOALIE_AREA_MAX_Y;
+        let is_in_net = self.x < MIN_X || self.x + self.width > MAX_X;
+        (
+            if is_in_goal_mouth {
+                (MIN_X - GOAL_NET_DEPTH, MAX_X + GOAL_NET_DEPTH)
+            } else {
+                (MIN_X, MAX_X)
+            },
+            if is_in_net {
+                (GOALIE_AREA_MIN_Y, GOALIE_AREA_MAX_Y)
+            } else {
+                (MIN_Y, MAX_Y)
+            },
+        )
+    }
+
+    /// The corner of the box facing the direction.
+    fn corner(&self, direction: (f32, f32)) -> (f32, f32) {
+        (
+            if direction.0 < 0.0 {
+                self.x
+            } else {
+                self.x + self.width
+            },
+            if direction.1 < 0.0 {
+                self.y
+            } else {
+                self.y + self.height
+            },
+        )
+    }
+
+    /// The normal and depth of the rounded corner of the rink the box sticks out of, if any.
+    fn rink_corner_penetration(&self) -> Option<((f32, f32), f32)> {
+        RINK_CORNERS.into_iter().find_map(|(center, direction)| {
+            let offset = self.corner(direction).sub(center);
+            let distance = offset.magnitude();
+            if is_in_rink_corner(offset, direction) && distance > RINK_CORNER_RADIUS {
+                Some((offset.mul(-1.0 / distance), distance - RINK_CORNER_RADIUS))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The first contact with the rounded corners of the rink while moving along the displacement.
+    fn sweep_rink_corners(&self, displacement: (f32, f32)) -> Option<Contact> {
+        let a = displacement.dot(&displacement);
+        if a == 0.0 {
+            return None;
+        }
+        RINK_CORNERS
+            .into_iter()
+            .filter_map(|(center, direction)| {
+                let offset = self.corner(direction).sub(center);
+                let distance = offset.magnitude();
+                if distance >= RINK_CORNER_RADIUS {
+                    // Already on or past the boards: only stop the box if it keeps going out.
+                    return (is_in_rink_corner(offset, direction)
+                        && offset.dot(&displacement) > 0.0)
+                        .then(|| Contact {
+                            time: 0.0,
+                            normal: offset.mul(-1.0 / distance),
+                            penetration: distance - RINK_CORNER_RADIUS,
+                        });
+                }
+                // When the corner of the box leaves the circle of the rounding.
+                let b = 2.0 * offset.dot(&displacement);
+                let c = offset.dot(&offset) - RINK_CORNER_RADIUS.powi(2);
+                let time = (-b + (b * b - 4.0 * a * c).sqrt()) / (2.0 * a);
+                let hit = offset.add(displacement.mul(time));
+                ((0.0..=1.0).contains(&time) && is_in_rink_corner(hit, direction)).then(|| {
+                    Contact {
+                        time,
+                        normal: hit.mul(-1.0).normalize(),
+                        penetration: 0.0,
+                    }
+                })
+            })
+            .min_by(|a, b| a.time.total_cmp(&b.time))
+    }
+
+    /// The first contact with the boards, or the back of the nets, while moving along the displacement.
+    fn sweep_walls(&self, displacement: (f32, f32)) -> Option<Contact> {
+        let ((min_x, max_x), (min_y, max_y)) = self.rink_limits();
+        let walls = [
+            (
+                displacement.0 < 0.0,
+                (min_x - self.x) / displacement.0,
+                (1.0, 0.0),
+            ),
+            (
+                displacement.0 > 0.0,
+                (max_x - self.x - self.width) / displacement.0,
+                (-1.0, 0.0),
+            ),
+            (
+                displacement.1 < 0.0,
+                (min_y - self.y) / displacement.1,
+                (0.0, 1.0),
+            ),
+            (
+                displacement.1 > 0.0,
+                (max_y - self.y - self.height) / displacement.1,
+                (0.0, -1.0),
+            ),
+        ];
+        walls
+            .into_iter()
+            .filter(|(is_moving_towards, time, _)| *is_moving_towards && *time <= 1.0)
+            .map(|(_, time, normal)| Contact {
+                time: time.max(0.0),
+                normal,
+                penetration: 0.0,
+            })
+            .chain(self.sweep_rink_corners(displacement))
+            .min_by(|a, b| a.time.total_cmp(&b.time))
+    }
+}
+
+/// Whether a point, given relative to the center of the rounding of a corner, is in that corner.
+fn is_in_rink_corner(offset: (f32, f32), direction: (f32, f32)) -> bool {
+    offset.0 * direction.0 > 0.0 && offset.1 * direction.1 > 0.0
+}
+
+/// The opaque pixels of a sprite, for pixel accurate collisions.
+#[derive(Debug, Clone)]
+struct CollisionMask {
+    width: i32,
+    height: i32,
+    pixels: Vec<bool>,
+}
+
+/// The pixel where a sprite at this position is drawn.
+fn pixel_position(position: (f32, f32)) -> (i32, i32) {
+    (position.0.floor() as i32, position.1.floor() as i32)
+}
+
+impl CollisionMask {
+    fn new(image: &RgbaImage) -> Self {
+        Self {
+            width: image.width() as i32,
+            height: image.height() as i32,
+            pixels: image.pixels().map(|pixel| pixel[3] > 0).collect(),
+        }
+    }
+
+    fn is_solid(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && y >= 0
+            && x < self.width
+            && y < self.height
+            && self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Whether the two masks, drawn at the given pixels, share an opaque pixel.
+    fn overlaps(&self, pixel: (i32, i32), other: &CollisionMask, other_pixel: (i32, i32)) -> bool {
+        let (dx, dy) = (pixel.0 - other_pixel.0, pixel.1 - other_pixel.1);
+        (0..self.height)
+            .any(|y| (0..self.width).any(|x| self.is_solid(x, y) && other.is_solid(x + dx, y + dy)))
+    }
+
+    /// Direction from the opaque pixels of this mask that are close to the other mask towards
+    /// the center of the other mask, which follows the outline of the sprite around the contact.
+    fn normal_towards(
+        &self,
+        pixel: (i32, i32),
+        other: &CollisionMask,
+        other_pixel: (i32, i32),
+    ) -> (f32, f32) {
+        let other_center = (
+            other_pixel.0 as f32 + other.width as f32 / 2.0,
+            other_pixel.1 as f32 + other.height as f32 / 2.0,
+        );
+        let mut normal = (0.0, 0.0);
+        for y in
+            other_pixel.1 - MASK_NORMAL_RADIUS..other_pixel.1 + other.height + MASK_NORMAL_RADIUS
+        {
+            for x in
+                other_pixel.0 - MASK_NORMAL_RADIUS..other_pixel.0 + other.width + MASK_NORMAL_RADIUS
+            {
+                if self.is_solid(x - pixel.0, y - pixel.1) {
+                    normal = normal.add(other_center.sub((x as f32 + 0.5, y as f32 + 0.5)));
+                }
+            }
+        }
+        if normal == (0.0, 0.0) {
+            let center = (
+                pixel.0 as f32 + self.width as f32 / 2.0,
+                pixel.1 as f32 + self.height as f32 / 2.0,
+            );
+            normal = other_center.sub(center);
+        }
+        normal.normalize()
+    }
+
+    /// Moves the mask along the displacement, half a pixel at a time, and returns the first
+    /// contact with the other mask, which does not move. Masks that already overlap are pushed
+    /// apart along the normal.
+    fn sweep(
+        &self,
+        position: (f32, f32),
+        displacement: (f32, f32),
+        other: &CollisionMask,
+        other_position: (f32, f32),
+    ) -> Option<Contact> {
+        let other_pixel = pixel_position(other_position);
+        let overlaps_at =
+            |position: (f32, f32)| self.overlaps(pixel_position(position), other, other_pixel);
+        let normal_at = |position: (f32, f32)| {
+            let normal = other.normal_towards(other_pixel, self, pixel_position(position));
+            // The contact must stop the motion, whatever the shape of the outline.
+            if normal.dot(&displacement) >= 0.0 && displacement != (0.0, 0.0) {
+                displacement.mul(-1.0).normalize()
+            } else {
+                normal
+            }
+        };
+
+        if overlaps_at(position) {
+            let normal = normal_at(position);
+            let mut penetration = 0.0;
+            while penetration < MAX_MASK_PENETRATION
+                && overlaps_at(position.add(normal.mul(penetration)))
+            {
+                penetration += MASK_SWEEP_STEP;
+            }
+            return Some(Contact {
+                time: 0.0,
+                normal,
+                penetration,
+            });
+        }
+
+        let steps = (displacement.magnitude() / MASK_SWEEP_STEP).ceil() as usize;
+        for step in 1..=steps {
+            let next_position = position.add(displacement.mul(step as f32 / steps as f32));
+            if overlaps_at(next_position) {
+                return Some(Contact {
+                    time: (step - 1) as f32 / steps as f32,
+                    normal: normal_at(next_position),
+                    penetration: 0.0,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Pushes two bodies apart along the contact normal, which points towards the first body.
+/// Returns false if they were already moving apart.
+fn apply_impulse(
+    body1: &mut impl Body,
+    body2: &mut impl Body,
+    normal: (f32, f32),
+    restitution: f32,
+) -> bool {
+    let approach_speed = body1.velocity().sub(body2.velocity()).dot(&normal);
+    if approach_speed > -RESTING_CONTACT_SPEED {
+        return false;
+    }
+    let impulse = -(1.0 + restitution) * approach_speed / (1.0 / body1.mass() + 1.0 / body2.mass());
+    body1.set_velocity(body1.velocity().add(normal.mul(impulse / body1.mass())));
+    body2.set_velocity(body2.velocity().sub(normal.mul(impulse / body2.mass())));
+    true
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Orientation {
     Up,
     UpLeft,
@@ -192,21 +762,83 @@ impl From<u8> for Orientation {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+/// Share of the speed kept along the contact normal when two bodies bounce off each other,
+/// from 0 for a dead stop to 1 for a bounce without loss.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Restitution {
+    pub puck_boards: f32,
+    pub puck_goalie: f32,
+    pub puck_player: f32,
+    pub player_player: f32,
+    pub player_goalie: f32,
+}
+
+impl Default for Restitution {
+    fn default() -> Self {
+        Self {
+            puck_boards: 1.0,
+            puck_goalie: COEFFICIENT_OF_RESTITUTION,
+            puck_player: COEFFICIENT_OF_RESTITUTION,
+            player_player: COEFFICIENT_OF_RESTITUTION,
+            player_goalie: COFFICIENT_OF_WALL_BOUNCING,
+        }
+    }
+}
+
+/// Notable things that happened during a game, collected by the server.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    Goal { side: GameSide },
+    Save { side: GameSide },
+}
+
+/// Statistics of one side during a match.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct MatchStats {
+    pub shots: u32,
+    // Shots that ended in a goal or a save.
+    pub shots_on_target: u32,
+    pub possession_milliseconds: f32,
+    pub steals: u32,
+    pub saves: u32,
+    // In pixels per second.
+    pub max_puck_speed: f32,
+    pub last_shot_speed: f32,
+}
+
+/// Converts a velocity in pixels per frame to pixels per second.
+fn puck_speed(velocity: (f32, f32)) -> f32 {
+    velocity.magnitude() * 1000.0 / MINIMUM_DELTATIME_MILLISECONDS
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum GameState {
     // TODO: add character selection with different stats
-    Starting { time: Instant },
+    // The milliseconds spent in the state are counted in simulation time.
+    Starting { elapsed: u128 },
     Running,
-    AfterGoal { time: Instant, scored: GameSide },
-    Ending { time: Instant },
+    AfterGoal { elapsed: u128, scored: GameSide },
+    Ending { elapsed: u128 },
+}
+
+/// What the puck can bounce off, besides the boards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PuckObstacle {
+    Goalie(GameSide),
+    Player(GameSide),
+    Post,
 }
 
 enum CollisionType {
     Minimal,
     Full,
+    // The opaque pixels of the sprite, when both bodies have a mask.
+    Mask,
 }
-#[derive(Clone, Copy, PartialEq)]
-enum Palette {
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Palette {
+    #[default]
     Dark,
     Light,
     Basket,
@@ -222,52 +854,70 @@ impl Palette {
             Palette::Alt => Palette::Dark,
         }
     }
+
+    pub fn previous(&self) -> Self {
+        match self {
+            Palette::Dark => Palette::Alt,
+            Palette::Light => Palette::Dark,
+            Palette::Basket => Palette::Light,
+            Palette::Alt => Palette::Basket,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::Dark => "Dark",
+            Palette::Light => "Classic",
+            Palette::Basket => "Basket",
+            Palette::Alt => "Gold",
+        }
+    }
 }
 
+/// Bounces two overlapping bodies off each other, as spinless rigid bodies, with an impulse along
+/// the normal of their contact. Returns whether they were colliding.
 fn resolve_collision(
     sprite1: &mut impl Body,
     sprite2: &mut impl Body,
     collision_type1: CollisionType,
     collision_type2: CollisionType,
+    restitution: f32,
 ) -> bool {
-    let rect1 = match collision_type1 {
-        CollisionType::Minimal => sprite1.minimal_collision_rect(),
-        CollisionType::Full => sprite1.full_collision_rect(),
+    let normal = match (
+        collision_type1,
+        sprite1.collision_mask(),
+        collision_type2,
+        sprite2.collision_mask(),
+    ) {
+        (CollisionType::Mask, Some(mask1), CollisionType::Mask, Some(mask2)) => {
+            let (pixel1, pixel2) = (
+                pixel_position(sprite1.position()),
+                pixel_position(sprite2.position()),
+            );
+            mask1
+                .overlaps(pixel1, mask2, pixel2)
+                .then(|| mask2.normal_towards(pixel2, mask1, pixel1))
+        }
+        (collision_type1, _, collision_type2, _) => {
+            let rect1 = match collision_type1 {
+                CollisionType::Minimal => sprite1.minimal_collision_rect(),
+                CollisionType::Full | CollisionType::Mask => sprite1.full_collision_rect(),
+            };
+            let rect2 = match collision_type2 {
+                CollisionType::Minimal => sprite2.minimal_collision_rect(),
+                CollisionType::Full | CollisionType::Mask => sprite2.full_collision_rect(),
+            };
+            CollisionBox::from(rect1)
+                .penetration(&CollisionBox::from(rect2))
+                .map(|(_, normal)| normal)
+        }
     };
 
-    let rect2 = match collision_type2 {
-        CollisionType::Minimal => sprite2.minimal_collision_rect(),
-        CollisionType::Full => sprite2.full_collision_rect(),
+    let Some(normal) = normal else {
+        return false;
     };
-
-    // Check collisions between players
-    if are_sprites_colliding(rect1, rect2) {
-        // Calculate new velocities by conservation of momentum
-        // Energy is dissipated in the collision by a factor ENERGY_LOSS
-        let velocity_com = (
-            (sprite1.velocity().0 * sprite1.mass() + sprite2.velocity().0 * sprite2.mass())
-                / (sprite1.mass() + sprite2.mass()),
-            (sprite1.velocity().1 * sprite1.mass() + sprite2.velocity().1 * sprite2.mass())
-                / (sprite1.mass() + sprite2.mass()),
-        );
-
-        sprite1.set_velocity((
-            (1.0 + COEFFICIENT_OF_RESTITUTION) * velocity_com.0
-                - COEFFICIENT_OF_RESTITUTION * sprite1.velocity().0,
-            (1.0 + COEFFICIENT_OF_RESTITUTION) * velocity_com.1
-                - COEFFICIENT_OF_RESTITUTION * sprite1.velocity().1,
-        ));
-
-        sprite2.set_velocity((
-            (1.0 + COEFFICIENT_OF_RESTITUTION) * velocity_com.0
-                - COEFFICIENT_OF_RESTITUTION * sprite2.velocity().0,
-            (1.0 + COEFFICIENT_OF_RESTITUTION) * velocity_com.1
-                - COEFFICIENT_OF_RESTITUTION * sprite2.velocity().1,
-        ));
-        return true;
-    }
-
-    false
+    apply_impulse(sprite1, sprite2, normal, restitution);
+    true
 }
 
 trait Body {
@@ -286,22 +936,28 @@ trait Body {
             height: h as u16,
         }
     }
-    fn minimal_collision_rect(&self) -> Rect {
+    fn collision_box(&self) -> CollisionBox {
         let (x, y) = self.position();
-        let (w, h) = self.size();
-        Rect {
-            x: x as u16,
-            y: y as u16,
-            width: w as u16,
-            height: h as u16,
+        let (width, height) = self.size();
+        CollisionBox {
+            x,
+            y,
+            width,
+            height,
         }
     }
+    fn minimal_collision_rect(&self) -> Rect {
+        self.collision_box().rect()
+    }
+    fn collision_mask(&self) -> Option<&'static CollisionMask> {
+        None
+    }
     fn mass(&self) -> f32;
     fn update(&mut self, _deltatime: f32) {}
     fn image(&self, palette: Palette) -> RgbaImage;
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct Puck {
     position: (f32, f32),
     velocity: (f32, f32),
@@ -310,8 +966,13 @@ pub struct Puck {
 
 impl Puck {
     pub fn new() -> Self {
+        Self::faceoff(&mut rand::thread_rng())
+    }
+
+    /// A puck thrown in from the top or the bottom boards, on the center line.
+    fn faceoff(rng: &mut impl Rng) -> Self {
         // Pick random number from o or 1
-        if rand::thread_rng().gen_range(0..=1) == 0 {
+        if rng.gen_range(0..=1) == 0 {
             Self {
                 position: (79.0, MIN_Y),
                 velocity: (0.0, 1.0),
@@ -326,22 +987,47 @@ impl Puck {
         }
     }
 
+    /// The side scoring once the whole puck is past a goal line.
     pub fn has_scored(&self) -> Option<GameSide> {
-        if self.position.0 <= MIN_X
-            && self.position.1 >= GOALIE_AREA_MIN_Y
-            && self.position.1 <= GOALIE_AREA_MAX_Y - PUCK_HEIGHT
-        {
+        let is_in_goal_mouth = self.position.1 >= GOALIE_AREA_MIN_Y
+            && self.position.1 <= GOALIE_AREA_MAX_Y - PUCK_HEIGHT;
+        if is_in_goal_mouth && self.position.0 <= MIN_X - PUCK_WIDTH {
             return Some(GameSide::Blue);
         }
-        if self.position.0 >= MAX_X - PUCK_WIDTH
-            && self.position.1 >= GOALIE_AREA_MIN_Y
-            && self.position.1 <= GOALIE_AREA_MAX_Y - PUCK_HEIGHT
-        {
+        if is_in_goal_mouth && self.position.0 >= MAX_X {
             return Some(GameSide::Red);
         }
         None
     }
 
+    /// Bounces off something that does not move, like the boards, and returns the speed
+    /// it had towards it.
+    fn bounce(&mut self, normal: (f32, f32), restitution: f32) -> f32 {
+        // Only the speed along the normal changes.
+        let normal_speed = self.velocity.dot(&normal);
+        if normal_speed < 0.0 {
+            self.set_velocity(
+                self.velocity
+                    .sub(normal.mul((1.0 + restitution) * normal_speed)),
+            );
+        }
+        normal_speed
+    }
+
+    fn apply_friction(&mut self) {
+        let (vx, vy) = self.velocity();
+        self.set_velocity((
+            vx * PUCK_FRICTION_VELOCITY_LOSS,
+            vy * PUCK_FRICTION_VELOCITY_LOSS,
+        ));
+    }
+
+    /// Releases the puck with the power of a shot, which can be faster than a push.
+    fn shoot(&mut self, velocity: (f32, f32)) {
+        self.possession = None;
+        self.velocity = velocity;
+    }
+
     pub fn attach_to_player(&mut self, player: &Player) {
         let offset = puck_catcher_offset(player.orientation);
         self.set_position((player.position.0 + offset.0, player.position.1 + offset.1));
@@ -384,23 +1070,38 @@ impl Body for Puck {
     fn set_position(&mut self, position: (f32, f32)) {
         let (w1, h1) = self.size();
         let (mut new_x1, mut new_y1) = position;
-        if new_x1 < MIN_X {
-            new_x1 = MIN_X;
+        let limits = |x, y| {
+            CollisionBox {
+                x,
+                y,
+                width: w1,
+                height: h1,
+            }
+            .rink_limits()
+        };
+        let ((min_x, max_x), _) = limits(new_x1, new_y1);
+        if new_x1 < min_x {
+            new_x1 = min_x;
             self.set_velocity((-self.velocity.0, self.velocity.1));
-        } else if new_x1 + w1 > MAX_X {
-            new_x1 = MAX_X - w1;
+        } else if new_x1 + w1 > max_x {
+            new_x1 = max_x - w1;
             self.set_velocity((-self.velocity.0, self.velocity.1));
         }
 
-        if new_y1 < MIN_Y {
-            new_y1 = MIN_Y;
+        let (_, (min_y, max_y)) = limits(new_x1, new_y1);
+        if new_y1 < min_y {
+            new_y1 = min_y;
             self.set_velocity((self.velocity.0, -self.velocity.1));
-        } else if new_y1 + h1 > MAX_Y {
-            new_y1 = MAX_Y - h1;
+        } else if new_y1 + h1 > max_y {
+            new_y1 = max_y - h1;
             self.set_velocity((self.velocity.0, -self.velocity.1));
         }
 
         self.position = (new_x1, new_y1);
+        // The free puck bounces off the rounded corners in the sweep, this only keeps the carried one in.
+        if let Some((normal, penetration)) = self.collision_box().rink_corner_penetration() {
+            self.position = self.position.add(normal.mul(penetration));
+        }
     }
 
     fn velocity(&self) -> (f32, f32) {
@@ -408,11 +1109,14 @@ impl Body for Puck {
     }
 
     fn set_velocity(&mut self, velocity: (f32, f32)) {
+        // Pushes cannot make the puck faster than the limit, but a shot leaving faster keeps
+        // its speed until friction and bounces slow it down.
+        let max_speed = MAX_PUCK_VELOCITY.max(self.velocity.magnitude());
         let (mut vx, mut vy) = velocity;
         let speed = (vx.powf(2.0) + vy.powf(2.0)).sqrt();
-        if speed > MAX_PUCK_VELOCITY {
-            vx = vx * MAX_PUCK_VELOCITY / speed;
-            vy = vy * MAX_PUCK_VELOCITY / speed;
+        if speed > max_speed {
+            vx = vx * max_speed / speed;
+            vy = vy * max_speed / speed;
         }
         self.velocity = (vx, vy);
     }
@@ -421,30 +1125,30 @@ impl Body for Puck {
         (PUCK_WIDTH, PUCK_HEIGHT)
     }
 
+    fn collision_mask(&self) -> Option<&'static CollisionMask> {
+        Some(&PUCK_MASK)
+    }
+
     fn mass(&self) -> f32 {
         PUCK_MASS
     }
 
     fn full_collision_rect(&self) -> Rect {
-        // A 6x6 rect around the puck
-        let (x, y) = self.position();
+        // A 6x6 rect around the puck, cut at the edge of the pitch when it is in the back of the net.
+        let (x, y) = (self.position().0 as u16, self.position().1 as u16);
         let (w, h) = self.size();
+        let (left, top) = (x.saturating_sub(2), y.saturating_sub(2));
         Rect {
-            x: x as u16 - 2,
-            y: y as u16 - 2,
-            width: w as u16 + 4,
-            height: h as u16 + 4,
+            x: left,
+            y: top,
+            width: x + w as u16 + 2 - left,
+            height: y + h as u16 + 2 - top,
         }
     }
 
     fn update(&mut self, deltatime: f32) {
         let (x, y) = self.position();
-        let (vx, vy) = self.velocity();
-        // Apply friction
-        self.set_velocity((
-            vx * PUCK_FRICTION_VELOCITY_LOSS,
-            vy * PUCK_FRICTION_VELOCITY_LOSS,
-        ));
+        self.apply_friction();
         let (vx, vy) = self.velocity();
         self.set_position((x + vx * deltatime, y + vy * deltatime));
     }
@@ -459,7 +1163,7 @@ impl Body for Puck {
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct Player {
     side: GameSide,
     position: (f32, f32),
@@ -468,6 +1172,10 @@ pub struct Player {
     new_orientation: Option<Orientation>,
     shooting_direction: Option<(f32, f32)>,
     shooting_counter: f32,
+    // Time since the shoot key was first pressed, and when it was last pressed.
+    windup_milliseconds: f32,
+    charge_milliseconds: f32,
+    is_one_timer: bool,
     after_shooting_counter: f32,
     after_got_stolen_counter: f32,
 }
@@ -490,6 +1198,9 @@ impl Player {
             new_orientation: None,
             shooting_direction: None,
             shooting_counter: 0.0,
+            windup_milliseconds: 0.0,
+            charge_milliseconds: 0.0,
+            is_one_timer: false,
             after_shooting_counter: 0.0,
             after_got_stolen_counter: 0.0,
         }
@@ -506,9 +1217,33 @@ impl Player {
             GameSide::Blue => Orientation::Left,
         };
         self.new_orientation = None;
+        self.cancel_shot();
+        self.after_shooting_counter = 0.0;
+    }
+
+    fn cancel_shot(&mut self) {
         self.shooting_direction = None;
         self.shooting_counter = 0.0;
-        self.after_shooting_counter = 0.0;
+        self.windup_milliseconds = 0.0;
+        self.charge_milliseconds = 0.0;
+        self.is_one_timer = false;
+    }
+
+    /// How much of the power of a slapshot the wind up has gathered, from 0 to 1.
+    fn shot_charge(&self) -> f32 {
+        (self.charge_milliseconds / SLAPSHOT_CHARGE_MILLISECONDS).min(1.0)
+    }
+
+    fn winding_up_charge(&self) -> Option<f32> {
+        (self.shooting_counter > 0.0).then(|| self.shot_charge())
+    }
+
+    /// The speed given to the puck: a tap is a wrist shot, holding the key charges a slapshot.
+    fn shot_power(&self) -> f32 {
+        if self.is_one_timer {
+            return ONE_TIMER_POWER;
+        }
+        WRIST_SHOT_POWER + (SLAPSHOT_POWER - WRIST_SHOT_POWER) * self.shot_charge()
     }
 
     pub fn catcher_position(&self) -> (f32, f32) {
@@ -586,6 +1321,22 @@ impl Body for Player {
         }
 
         self.position = (new_x1, new_y1);
+        let full_box = CollisionBox {
+            x: new_x1,
+            y: new_y1,
+            width: w1,
+            height: h1,
+        };
+        if let Some((normal, penetration)) = full_box.rink_corner_penetration() {
+            self.position = self.position.add(normal.mul(penetration));
+            let normal_speed = self.velocity.dot(&normal);
+            if normal_speed < 0.0 {
+                self.set_velocity(
+                    self.velocity
+                        .sub(normal.mul((1.0 + COFFICIENT_OF_WALL_BOUNCING) * normal_speed)),
+                );
+            }
+        }
     }
 
     fn velocity(&self) -> (f32, f32) {
@@ -610,61 +1361,68 @@ impl Body for Player {
         }
     }
 
-    fn minimal_collision_rect(&self) -> Rect {
+    fn collision_box(&self) -> CollisionBox {
         let (x, y) = self.position();
 
         match self.orientation {
-            Orientation::Up => Rect {
-                x: x as u16,
-                y: y as u16,
-                width: 14,
-                height: 8,
+            Orientation::Up => CollisionBox {
+                x,
+                y,
+                width: 14.0,
+                height: 8.0,
             },
-            Orientation::UpLeft => Rect {
-                x: x as u16,
-                y: y as u16 + 5,
-                width: 13,
-                height: 10,
+            Orientation::UpLeft => CollisionBox {
+                x,
+                y: y + 5.0,
+                width: 13.0,
+                height: 10.0,
             },
-            Orientation::Left => Rect {
-                x: x as u16,
-                y: y as u16 + 6,
-                width: 8,
-                height: 14,
+            Orientation::Left => CollisionBox {
+                x,
+                y: y + 6.0,
+                width: 8.0,
+                height: 14.0,
             },
-            Orientation::DownLeft => Rect {
-                x: x as u16 + 5,
-                y: y as u16 + 2,
-                width: 10,
-                height: 13,
+            Orientation::DownLeft => CollisionBox {
+                x: x + 5.0,
+                y: y + 2.0,
+                width: 10.0,
+                height: 13.0,
             },
-            Orientation::Down => Rect {
-                x: x as u16 + 6,
-                y: y as u16,
-                width: 14,
-                height: 8,
+            Orientation::Down => CollisionBox {
+                x: x + 6.0,
+                y,
+                width: 14.0,
+                height: 8.0,
             },
-            Orientation::DownRight => Rect {
-                x: x as u16 + 2,
-                y: y as u16,
-                width: 13,
-                height: 10,
+            Orientation::DownRight => CollisionBox {
+                x: x + 2.0,
+                y,
+                width: 13.0,
+                height: 10.0,
             },
-            Orientation::Right => Rect {
-                x: x as u16,
-                y: y as u16,
-                width: 8,
-                height: 14,
+            Orientation::Right => CollisionBox {
+                x,
+                y,
+                width: 8.0,
+                height: 14.0,
             },
-            Orientation::UpRight => Rect {
-                x: x as u16,
-                y: y as u16,
-                width: 10,
-                height: 13,
+            Orientation::UpRight => CollisionBox {
+                x,
+                y,
+                width: 10.0,
+                height: 13.0,
             },
         }
     }
 
+    fn collision_mask(&self) -> Option<&'static CollisionMask> {
+        match self.side {
+            GameSide::Red => RED_PLAYER_MASKS.get(self.orientation as usize),
+            GameSide::Blue => BLUE_PLAYER_MASKS.get(self.orientation as usize),
+        }
+    }
+
     fn mass(&self) -> f32 {
         PLAYER_MASS
     }
@@ -702,12 +1460,11 @@ impl Body for Player {
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct Goalie {
     side: GameSide,
     position: (f32, f32),
     velocity: (f32, f32),
-    saves: usize,
 }
 
 impl Goalie {
@@ -722,7 +1479,6 @@ impl Goalie {
             side,
             position,
             velocity,
-            saves: 0,
         }
     }
 }
@@ -783,40 +1539,220 @@ impl Body for Goalie {
 #[derive(Clone)]
 pub struct Client {
     id: usize,
-    terminal: SshTerminal,
+    name: String,
+    // None for computer controlled players.
+    terminal: Option<SshTerminal>,
+    ai: Option<Ai>,
+    script: Option<ScriptedAi>,
+    bot: Option<BotLink>,
     is_connected: bool,
+    // Set when the connection dropped. A client that left on purpose cannot reconnect.
+    disconnected_at: Option<Instant>,
+    can_reconnect: bool,
     palette: Palette,
+    // Clearing is left to the next draw, so that input handlers never write to the terminal.
+    needs_clear: bool,
+    // The last quick chat message sent and when.
+    quick_chat: Option<(&'static str, Instant)>,
+    // Shows the match statistics instead of the controls.
+    show_stats: bool,
 }
 
 impl Client {
-    pub fn new(id: usize, terminal: SshTerminal) -> Self {
+    pub fn new(id: usize, name: String, palette: Palette, terminal: SshTerminal) -> Self {
         Self {
             id,
-            terminal,
+            name,
+            terminal: Some(terminal),
+            ai: None,
+            script: None,
+            bot: None,
             is_connected: true,
-            palette: Palette::Dark,
+            disconnected_at: None,
+            can_reconnect: true,
+            palette,
+            needs_clear: true,
+            quick_chat: None,
+            show_stats: false,
         }
     }
 
-    pub fn clear(&mut self) -> AppResult<()> {
+    pub fn ai() -> Self {
+        Self {
+            id: AI_CLIENT_ID,
+            name: "AI".to_string(),
+            terminal: None,
+            ai: Some(Ai::default()),
+            script: None,
+            bot: None,
+            is_connected: true,
+            disconnected_at: None,
+            can_reconnect: false,
+            palette: Palette::default(),
+            needs_clear: false,
+            quick_chat: None,
+            show_stats: false,
+        }
+    }
+
+    pub fn script(script: AiScript) -> Self {
+        Self {
+            id: AI_CLIENT_ID,
+            name: script.name.clone(),
+            terminal: None,
+            ai: None,
+            script: Some(ScriptedAi::new(script)),
+            bot: None,
+            is_connected: true,
+            disconnected_at: None,
+            can_reconnect: false,
+            palette: Palette::default(),
+            needs_clear: false,
+            quick_chat: None,
+            show_stats: false,
+        }
+    }
+
+    pub fn bot(bot: BotLink) -> Self {
+        Self {
+            id: bot.id,
+            name: bot.name.clone(),
+            terminal: None,
+            ai: None,
+            script: None,
+            bot: Some(bot),
+            is_connected: true,
+            disconnected_at: None,
+            can_reconnect: false,
+            palette: Palette::default(),
+            needs_clear: false,
+            quick_chat: None,
+            show_stats: false,
+        }
+    }
+
+    fn is_human(&self) -> bool {
+        !self.is_computer() && self.bot.is_none()
+    }
+
+    /// Played by the built-in AI or a script.
+    fn is_computer(&self) -> bool {
+        self.ai.is_some() || self.script.is_some()
+    }
+
+    fn disconnect(&mut self) {
         if self.is_connected {
-            self.terminal.draw(|f| {
-                let mut lines = vec![];
-                for _ in 0..f.size().height {
-                    lines.push(Line::from(" ".repeat(f.size().width.into())));
-                }
-                let clear = Paragraph::new(lines).style(Color::White);
-                f.render_widget(clear, f.size());
-            })?;
+            self.is_connected = false;
+            self.disconnected_at = Some(Instant::now());
         }
-        Ok(())
     }
-}
 
-#[derive(Clone)]
-pub struct Game {
-    red_client: Client,
-    blue_client: Client,
+    fn send_quick_chat(&mut self, index: usize) {
+        if self
+            .quick_chat
+            .is_some_and(|(_, time)| time.elapsed().as_millis() < QUICK_CHAT_COOLDOWN_MILLISECONDS)
+        {
+            return;
+        }
+        if let Some(message) = QUICK_CHAT_MESSAGES.get(index) {
+            self.quick_chat = Some((message, Instant::now()));
+        }
+    }
+
+    fn visible_quick_chat(&self) -> Option<&'static str> {
+        self.quick_chat
+            .filter(|(_, time)| time.elapsed().as_millis() < QUICK_CHAT_DURATION_MILLISECONDS)
+            .map(|(message, _)| message)
+    }
+
+    fn is_waiting_for_reconnection(&self) -> bool {
+        if self.is_connected || !self.can_reconnect {
+            return false;
+        }
+        if let Some(time) = self.disconnected_at {
+            return time.elapsed().as_secs() < RECONNECTION_TIMEOUT_SECONDS;
+        }
+        false
+    }
+
+    pub fn clear(&mut self) -> AppResult<()> {
+        if !self.is_connected {
+            return Ok(());
+        }
+        if let Some(terminal) = self.terminal.as_mut() {
+            terminal.draw(|f| {
+                let mut lines = vec![];
+                for _ in 0..f.size().height {
+                    lines.push(Line::from(" ".repeat(f.size().width.into())));
+                }
+                let clear = Paragraph::new(lines).style(Color::White);
+                f.render_widget(clear, f.size());
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// State of a player in a snapshot, in pitch coordinates.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PlayerSnapshot {
+    pub position: (f32, f32),
+    pub velocity: (f32, f32),
+    // Where the player holds the puck.
+    pub catcher_position: (f32, f32),
+    // From 0 facing up, counterclockwise to 7 facing up right.
+    pub orientation: u8,
+    pub is_shooting: bool,
+}
+
+impl From<&Player> for PlayerSnapshot {
+    fn from(player: &Player) -> Self {
+        Self {
+            position: player.position,
+            velocity: player.velocity,
+            catcher_position: player.catcher_position(),
+            orientation: player.orientation as u8,
+            is_shooting: player.shooting_counter > 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PuckSnapshot {
+    pub position: (f32, f32),
+    pub velocity: (f32, f32),
+    pub possession: Option<GameSide>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Starting,
+    Running,
+    AfterGoal,
+    Ending,
+}
+
+/// Everything there is to know about a simulation after a step.
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub phase: Phase,
+    pub remaining_seconds: u128,
+    pub score: (u8, u8),
+    pub red_player: PlayerSnapshot,
+    pub blue_player: PlayerSnapshot,
+    pub red_goalie: (f32, f32),
+    pub blue_goalie: (f32, f32),
+    pub puck: PuckSnapshot,
+    pub red_stats: MatchStats,
+    pub blue_stats: MatchStats,
+}
+
+/// The players, goalies, puck, score and state of a match, without any terminal attached.
+/// It only moves forward when stepped, so it can be driven by the game loop, tests or bots alike.
+#[derive(Debug, Clone)]
+pub struct Simulation {
     red_player: Player,
     blue_player: Player,
     red_goalie: Goalie,
@@ -825,114 +1761,223 @@ pub struct Game {
     blue_score: u8,
     puck: Puck,
     skate_traces: Vec<(f32, f32)>,
-    pub id: uuid::Uuid,
     timer: u128,
-    last_tick: Instant,
-    fps: f32,
+    tick: u64,
     state: GameState,
+    events: Vec<GameEvent>,
+    red_stats: MatchStats,
+    blue_stats: MatchStats,
+    // The side of the last shot, until the puck is caught, saved or scored.
+    shot_in_flight: Option<GameSide>,
+    restitution: Restitution,
+    // Time left showing the post banner, after a shot rang off a post.
+    post_banner_milliseconds: f32,
+    // Draws the faceoffs, seeded to replay a match.
+    rng: StdRng,
 }
 
-impl Game {
-    pub fn new(red_client: (usize, SshTerminal), blue_client: (usize, SshTerminal)) -> Self {
-        let mut game = Self {
-            red_client: Client::new(red_client.0, red_client.1),
-            blue_client: Client::new(blue_client.0, blue_client.1),
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Self::with_rng(StdRng::from_entropy())
+    }
+
+    /// A simulation whose faceoffs are drawn from the given seed, so that it can be replayed.
+    pub fn with_seed(restitution: Restitution, seed: u64) -> Self {
+        Self {
+            restitution,
+            ..Self::with_rng(StdRng::seed_from_u64(seed))
+        }
+    }
+
+    fn with_rng(mut rng: StdRng) -> Self {
+        Self {
             red_player: Player::new(GameSide::Red),
             blue_player: Player::new(GameSide::Blue),
             red_goalie: Goalie::new(GameSide::Red),
             blue_goalie: Goalie::new(GameSide::Blue),
             red_score: 0,
             blue_score: 0,
-            puck: Puck::new(),
+            puck: Puck::faceoff(&mut rng),
             skate_traces: vec![],
-            id: uuid::Uuid::new_v4(),
             timer: 0,
-            last_tick: Instant::now(),
-            fps: 0.0,
-            state: GameState::Starting {
-                time: Instant::now(),
-            },
-        };
-
-        game.red_client
-            .clear()
-            .unwrap_or_else(|e| log::error!("Failed to clear red client terminal: {e}"));
-        game.blue_client
-            .clear()
-            .unwrap_or_else(|e| log::error!("Failed to clear red client terminal: {e}"));
-        game
+            tick: 0,
+            state: GameState::Starting { elapsed: 0 },
+            events: vec![],
+            red_stats: MatchStats::default(),
+            blue_stats: MatchStats::default(),
+            shot_in_flight: None,
+            restitution: Restitution::default(),
+            post_banner_milliseconds: 0.0,
+            rng,
+        }
     }
 
-    pub fn clear_client(&mut self, client_id: usize) {
-        if self.red_client.id == client_id {
-            self.red_client
-                .clear()
-                .unwrap_or_else(|e| log::error!("Failed to clear red client terminal: {e}"));
-        } else {
-            self.blue_client
-                .clear()
-                .unwrap_or_else(|e| log::error!("Failed to clear red client terminal: {e}"));
+    /// A simulation with other bounces than the default ones, to try out how the game plays.
+    pub fn with_restitution(restitution: Restitution) -> Self {
+        Self {
+            restitution,
+            ..Self::new()
         }
     }
 
     fn reset(&mut self) {
         self.red_player.reset();
         self.blue_player.reset();
-        self.puck = Puck::new();
-        self.state = GameState::Starting {
-            time: Instant::now(),
-        };
+        self.puck = Puck::faceoff(&mut self.rng);
+        self.state = GameState::Starting { elapsed: 0 };
         self.skate_traces.clear();
+        self.shot_in_flight = None;
+        self.post_banner_milliseconds = 0.0;
     }
 
-    fn close(&mut self) {
-        self.red_client.is_connected = false;
-        self.blue_client.is_connected = false;
+    fn stats_mut(&mut self, side: GameSide) -> &mut MatchStats {
+        match side {
+            GameSide::Red => &mut self.red_stats,
+            GameSide::Blue => &mut self.blue_stats,
+        }
     }
 
-    pub fn disconnect(&mut self, client_id: usize) {
-        if self.red_client.id == client_id {
-            self.red_client.is_connected = false;
-        } else {
-            self.blue_client.is_connected = false;
+    /// Counts the shot in flight as on target, if it was shot by the given side.
+    fn shot_on_target(&mut self, side: GameSide) {
+        if self.shot_in_flight == Some(side) {
+            self.stats_mut(side).shots_on_target += 1;
         }
+        self.shot_in_flight = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state == GameState::Running
     }
 
     pub fn is_over(&self) -> bool {
         matches!(self.state, GameState::Ending { .. })
     }
 
-    pub fn is_running(&self) -> bool {
-        self.red_client.is_connected && self.blue_client.is_connected
+    /// The match is over and the result has been shown long enough.
+    pub fn is_finished(&self) -> bool {
+        match self.state {
+            GameState::Ending { elapsed } => elapsed >= ENDING_DELAY_MILLISECONDS,
+            _ => false,
+        }
     }
 
-    pub fn client_ids(&self) -> (usize, usize) {
-        (self.red_client.id, self.blue_client.id)
+    pub fn score(&self) -> (u8, u8) {
+        (self.red_score, self.blue_score)
     }
 
-    pub fn handle_input(&mut self, client_id: usize, key_code: KeyCode) {
-        if key_code == KeyCode::Esc {
-            self.disconnect(client_id);
-            return;
+    pub fn stats(&self) -> (MatchStats, MatchStats) {
+        (self.red_stats, self.blue_stats)
+    }
+
+    pub fn remaining_seconds(&self) -> u128 {
+        if self.timer > GAME_DURATION_MILLISECONDS {
+            0
+        } else {
+            (GAME_DURATION_MILLISECONDS - self.timer) / 1000
         }
+    }
 
-        if key_code == KeyCode::Char('p') {
-            if self.red_client.id == client_id {
-                self.red_client.palette = self.red_client.palette.next();
-            } else {
-                self.blue_client.palette = self.blue_client.palette.next();
+    /// Elapsed game time, excluding pauses after goals.
+    pub fn game_time_seconds(&self) -> u128 {
+        self.timer.min(GAME_DURATION_MILLISECONDS) / 1000
+    }
+
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        self.events.drain(..).collect()
+    }
+
+    /// Ends the match immediately.
+    pub fn end(&mut self) {
+        self.state = GameState::Ending { elapsed: 0 };
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tick: self.tick,
+            phase: match self.state {
+                GameState::Starting { .. } => Phase::Starting,
+                GameState::Running => Phase::Running,
+                GameState::AfterGoal { .. } => Phase::AfterGoal,
+                GameState::Ending { .. } => Phase::Ending,
+            },
+            remaining_seconds: self.remaining_seconds(),
+            score: self.score(),
+            red_player: (&self.red_player).into(),
+            blue_player: (&self.blue_player).into(),
+            red_goalie: self.red_goalie.position,
+            blue_goalie: self.blue_goalie.position,
+            puck: PuckSnapshot {
+                position: self.puck.position,
+                velocity: self.puck.velocity,
+                possession: self.puck.possession,
+            },
+            red_stats: self.red_stats,
+            blue_stats: self.blue_stats,
+        }
+    }
+
+    /// Applies the keys pressed by each side, then moves the simulation forward by one frame.
+    pub fn step(&mut self, inputs: &[(GameSide, KeyCode)]) {
+        self.advance(inputs, MINIMUM_DELTATIME_MILLISECONDS);
+    }
+
+    fn advance(&mut self, inputs: &[(GameSide, KeyCode)], deltatime: f32) {
+        for &(side, key_code) in inputs {
+            self.handle_input(side, key_code);
+        }
+        self.tick += 1;
+
+        let delta = deltatime as u128;
+        match self.state {
+            GameState::Starting { elapsed } => {
+                self.state = if elapsed + delta >= STARTING_DELAY_MILLISECONDS {
+                    GameState::Running
+                } else {
+                    GameState::Starting {
+                        elapsed: elapsed + delta,
+                    }
+                };
+            }
+            GameState::Running => {
+                self.update_running(deltatime);
+                self.timer += delta;
+                if self.timer > GAME_DURATION_MILLISECONDS {
+                    self.state = GameState::Ending { elapsed: 0 };
+                }
+            }
+            GameState::AfterGoal { elapsed, scored } => {
+                if elapsed + delta >= AFTER_GOAL_DELAY_MILLISECONDS {
+                    self.reset();
+                } else {
+                    self.state = GameState::AfterGoal {
+                        elapsed: elapsed + delta,
+                        scored,
+                    };
+                }
+            }
+            GameState::Ending { elapsed } => {
+                self.state = GameState::Ending {
+                    elapsed: elapsed + delta,
+                };
             }
-            return;
         }
+    }
 
+    /// Moves or shoots with the player of the given side. Keys are ignored outside of play.
+    pub fn handle_input(&mut self, side: GameSide, key_code: KeyCode) {
         if self.state != GameState::Running {
             return;
         }
 
-        let player = if self.red_client.id == client_id {
-            &mut self.red_player
-        } else {
-            &mut self.blue_player
+        let player = match side {
+            GameSide::Red => &mut self.red_player,
+            GameSide::Blue => &mut self.blue_player,
         };
 
         if player.shooting_counter > 0.0 {
@@ -963,6 +2008,22 @@ impl Game {
                         shooting_direction.1,
                     );
                 }
+                KeyCode::Char(' ') => {
+                    if player.charge_milliseconds == 0.0
+                        && player.windup_milliseconds < ONE_TIMER_MILLISECONDS
+                    {
+                        player.is_one_timer = true;
+                    } else {
+                        // The first press was at the start of the wind up.
+                        let interval = player.windup_milliseconds - player.charge_milliseconds;
+                        player.charge_milliseconds = player.windup_milliseconds;
+                        player.shooting_counter = if interval < KEY_REPEAT_INTERVAL_MILLISECONDS {
+                            HELD_KEY_RELEASE_MILLISECONDS
+                        } else {
+                            SHOT_RELEASE_MILLISECONDS
+                        };
+                    }
+                }
                 _ => {}
             }
             player.shooting_direction = Some(shooting_direction);
@@ -971,7 +2032,7 @@ impl Game {
             if key_code == KeyCode::Char(' ') && player.after_shooting_counter == 0.0 {
                 if let Some(side) = self.puck.possession {
                     if side == player.side {
-                        player.shooting_counter = SHOOTING_COUNTER_MILLISECONDS;
+                        player.shooting_counter = KEY_REPEAT_DELAY_MILLISECONDS;
                         player.velocity.0 *= 0.85;
                         player.velocity.1 *= 0.85;
                         self.puck.velocity.0 *= 0.85;
@@ -1045,52 +2106,170 @@ impl Game {
         }
     }
 
-    pub fn update(&mut self) -> AppResult<()> {
-        let now = Instant::now();
-        let deltatime = now.duration_since(self.last_tick).as_millis() as f32;
-        if deltatime < MINIMUM_DELTATIME_MILLISECONDS {
-            return Ok(());
+    /// What the AI controlling the given side can see.
+    pub fn ai_view(&self, side: GameSide) -> AiView {
+        let (player, opponent_goalie) = match side {
+            GameSide::Red => (&self.red_player, &self.blue_goalie),
+            GameSide::Blue => (&self.blue_player, &self.red_goalie),
+        };
+        let goal_center_y = (GOALIE_AREA_MIN_Y + GOALIE_AREA_MAX_Y) / 2.0;
+        let shooting_position = match side {
+            GameSide::Red => (MAX_X - 45.0, goal_center_y),
+            GameSide::Blue => (MIN_X + 45.0, goal_center_y),
+        };
+        AiView {
+            side,
+            catcher_position: player.catcher_position(),
+            velocity: player.velocity,
+            puck_position: self.puck.position,
+            has_puck: self.puck.possession == Some(side),
+            is_shooting: player.shooting_counter > 0.0,
+            shooting_position,
+            opponent_goalie_position: (
+                opponent_goalie.position.0,
+                opponent_goalie.position.1 + GOALIE_HEIGHT / 2.0,
+            ),
         }
+    }
 
-        match self.state {
-            GameState::Starting { time } => {
-                if now.duration_since(time).as_millis() >= STARTING_DELAY_MILLISECONDS {
-                    self.state = GameState::Running;
+    /// Moves the puck by its velocity. A free puck is swept against the boards, the goalies and
+    /// the sprites of the players, bouncing off the first one on its way, so that fast pucks
+    /// cannot pass through them.
+    fn move_puck(&mut self, deltatime: f32) {
+        let is_carried = self.puck.possession.is_some_and(|side| match side {
+            GameSide::Red => self.red_player.shooting_counter == 0.0,
+            GameSide::Blue => self.blue_player.shooting_counter == 0.0,
+        });
+        if is_carried {
+            // The carried puck follows the stick, it is attached to the player below.
+            let position = self.puck.position.add(self.puck.velocity.mul(deltatime));
+            self.puck.set_position(position);
+            return;
+        }
+
+        let mut remaining = deltatime;
+        for _ in 0..MAX_PUCK_CONTACTS_PER_FRAME {
+            let displacement = self.puck.velocity.mul(remaining);
+            let puck_box = self.puck.collision_box();
+            let mut first_contact = puck_box
+                .sweep_walls(displacement)
+                .map(|contact| (contact, None));
+            let goalie_contacts = [
+                (GameSide::Red, &self.red_goalie),
+                (GameSide::Blue, &self.blue_goalie),
+            ]
+            .map(|(side, goalie)| {
+                puck_box
+                    .sweep(displacement, &goalie.collision_box())
+                    .map(|contact| (contact, Some(PuckObstacle::Goalie(side))))
+            });
+            let player_contacts = [
+                (GameSide::Red, &self.red_player),
+                (GameSide::Blue, &self.blue_player),
+            ]
+            .map(|(side, player)| {
+                // The puck leaves the stick of the shooter freely, during the wind up and after the shot.
+                if self.puck.possession == Some(side) || player.after_shooting_counter > 0.0 {
+                    return None;
                 }
-            }
-            GameState::Running => {
-                self.update_running(deltatime)?;
-                self.timer += deltatime as u128;
-                if self.timer > GAME_DURATION_MILLISECONDS {
-                    self.state = GameState::Ending {
-                        time: Instant::now(),
-                    };
+                PUCK_MASK
+                    .sweep(
+                        self.puck.position,
+                        displacement,
+                        player.collision_mask()?,
+                        player.position,
+                    )
+                    .map(|contact| (contact, Some(PuckObstacle::Player(side))))
+            });
+            let post_contacts = GOAL_POSTS.map(|post| {
+                puck_box
+                    .sweep(displacement, &post)
+                    .map(|contact| (contact, Some(PuckObstacle::Post)))
+            });
+            for (contact, obstacle) in goalie_contacts
+                .into_iter()
+                .chain(player_contacts)
+                .chain(post_contacts)
+                .flatten()
+            {
+                if first_contact.is_none_or(|(first, _)| contact.time < first.time) {
+                    first_contact = Some((contact, obstacle));
                 }
             }
-            GameState::AfterGoal { time, scored: _ } => {
-                if now.duration_since(time).as_millis() >= AFTER_GOAL_DELAY_MILLISECONDS {
-                    self.reset();
+
+            let Some((contact, obstacle)) = first_contact else {
+                let position = self.puck.position.add(displacement);
+                self.puck.set_position(position);
+                return;
+            };
+
+            let position = self
+                .puck
+                .position
+                .add(displacement.mul(contact.time))
+                .add(contact.normal.mul(contact.penetration));
+            self.puck.set_position(position);
+            match obstacle {
+                None => {
+                    // The puck stays in the back of the net.
+                    if self.puck.has_scored().is_some() {
+                        return;
+                    }
+                    self.puck
+                        .bounce(contact.normal, self.restitution.puck_boards);
                 }
-            }
-            GameState::Ending { time } => {
-                if now.duration_since(time).as_millis() >= ENDING_DELAY_MILLISECONDS {
-                    self.close();
+                Some(PuckObstacle::Post) => {
+                    let normal_speed = self
+                        .puck
+                        .bounce(contact.normal, self.restitution.puck_boards);
+                    if normal_speed < -POST_BANNER_MIN_SPEED {
+                        self.post_banner_milliseconds = POST_BANNER_MILLISECONDS;
+                    }
+                }
+                Some(PuckObstacle::Goalie(side)) => {
+                    let goalie = match side {
+                        GameSide::Red => &mut self.red_goalie,
+                        GameSide::Blue => &mut self.blue_goalie,
+                    };
+                    // A goalie pushing a dead puck does not make a save.
+                    let is_shot = self.puck.velocity.dot(&contact.normal) < 0.0;
+                    if apply_impulse(
+                        &mut self.puck,
+                        goalie,
+                        contact.normal,
+                        self.restitution.puck_goalie,
+                    ) && is_shot
+                    {
+                        self.stats_mut(side).saves += 1;
+                        self.shot_on_target(side.opponent());
+                        self.events.push(GameEvent::Save { side });
+                    }
+                }
+                Some(PuckObstacle::Player(side)) => {
+                    let player = match side {
+                        GameSide::Red => &mut self.red_player,
+                        GameSide::Blue => &mut self.blue_player,
+                    };
+                    apply_impulse(
+                        &mut self.puck,
+                        player,
+                        contact.normal,
+                        self.restitution.puck_player,
+                    );
                 }
             }
+            remaining *= 1.0 - contact.time;
         }
-        self.fps = 1000.0 / deltatime;
-        self.last_tick = now;
-
-        Ok(())
     }
 
-    fn update_running(&mut self, deltatime: f32) -> AppResult<()> {
+    fn update_running(&mut self, deltatime: f32) {
         let red_previous_position = self.red_player.position;
         let red_previous_orientation = self.red_player.orientation;
         let blue_previous_position = self.blue_player.position;
         let blue_previous_orientation = self.blue_player.orientation;
 
         let normalized_deltatime = deltatime / MINIMUM_DELTATIME_MILLISECONDS;
+        self.post_banner_milliseconds = (self.post_banner_milliseconds - deltatime).max(0.0);
 
         self.red_player.update(normalized_deltatime);
         let red_goalie_head_position_y =
@@ -1105,14 +2284,15 @@ impl Game {
         self.blue_goalie
             .set_position((MAX_X - GOALIE_WIDTH, blue_goalie_head_position_y));
         self.blue_goalie.set_velocity(self.blue_player.velocity);
-        self.puck.update(normalized_deltatime);
+        self.puck.apply_friction();
 
         // Check collisions between players
         if resolve_collision(
             &mut self.red_player,
             &mut self.blue_player,
-            CollisionType::Minimal,
-            CollisionType::Minimal,
+            CollisionType::Mask,
+            CollisionType::Mask,
+            self.restitution.player_player,
         ) {
             self.red_player.rotate(red_previous_orientation);
             self.red_player.set_position(red_previous_position);
@@ -1126,25 +2306,28 @@ impl Game {
             &mut self.red_goalie,
             CollisionType::Minimal,
             CollisionType::Full,
+            self.restitution.player_goalie,
         ) || resolve_collision(
             &mut self.red_player,
             &mut self.red_goalie,
             CollisionType::Full,
             CollisionType::Minimal,
+            self.restitution.player_goalie,
         ) || resolve_collision(
             &mut self.red_player,
             &mut self.blue_goalie,
             CollisionType::Minimal,
             CollisionType::Full,
+            self.restitution.player_goalie,
         ) || resolve_collision(
             &mut self.red_player,
             &mut self.blue_goalie,
             CollisionType::Full,
             CollisionType::Minimal,
+            self.restitution.player_goalie,
         ) {
             self.red_player.rotate(red_previous_orientation);
             self.red_player.set_position(red_previous_position);
-            self.red_player.set_velocity((0.0, 0.0));
         }
 
         // Check collision between blue and goalies
@@ -1153,25 +2336,28 @@ impl Game {
             &mut self.blue_goalie,
             CollisionType::Minimal,
             CollisionType::Full,
+            self.restitution.player_goalie,
         ) || resolve_collision(
             &mut self.blue_player,
             &mut self.blue_goalie,
             CollisionType::Full,
             CollisionType::Minimal,
+            self.restitution.player_goalie,
         ) || resolve_collision(
             &mut self.blue_player,
             &mut self.red_goalie,
             CollisionType::Minimal,
             CollisionType::Full,
+            self.restitution.player_goalie,
         ) || resolve_collision(
             &mut self.blue_player,
             &mut self.red_goalie,
             CollisionType::Full,
             CollisionType::Minimal,
+            self.restitution.player_goalie,
         ) {
             self.blue_player.rotate(blue_previous_orientation);
             self.blue_player.set_position(blue_previous_position);
-            self.blue_player.set_velocity((0.0, 0.0));
         }
 
         if self.red_player.position != red_previous_position {
@@ -1193,59 +2379,33 @@ impl Game {
             self.skate_traces.remove(0);
         }
 
-        let puck_previous_position = self.puck.position;
-        // Check collision between puck and goalies
-        // FIXME: sometimes puck is tucked inside goalie
-        if resolve_collision(
-            &mut self.puck,
-            &mut self.red_goalie,
-            CollisionType::Minimal,
-            CollisionType::Minimal,
-        ) {
-            self.puck.set_position(puck_previous_position);
-            self.red_goalie.saves += 1;
-        } else if resolve_collision(
-            &mut self.puck,
-            &mut self.blue_goalie,
-            CollisionType::Minimal,
-            CollisionType::Minimal,
-        ) {
-            self.puck.set_position(puck_previous_position);
-            self.blue_goalie.saves += 1;
-        }
-
-        // Check collision between puck and players
-        if resolve_collision(
-            &mut self.puck,
-            &mut self.red_player,
-            CollisionType::Minimal,
-            CollisionType::Minimal,
-        ) || resolve_collision(
-            &mut self.puck,
-            &mut self.blue_player,
-            CollisionType::Minimal,
-            CollisionType::Minimal,
-        ) {
-            self.puck.set_position(puck_previous_position);
-        }
+        self.move_puck(normalized_deltatime);
 
         // Check for goals!
         match self.puck.has_scored() {
             Some(GameSide::Red) => {
                 self.red_score += 1;
+                self.shot_on_target(GameSide::Red);
+                self.events.push(GameEvent::Goal {
+                    side: GameSide::Red,
+                });
                 self.state = GameState::AfterGoal {
-                    time: Instant::now(),
+                    elapsed: 0,
                     scored: GameSide::Red,
                 };
-                return Ok(());
+                return;
             }
             Some(GameSide::Blue) => {
                 self.blue_score += 1;
+                self.shot_on_target(GameSide::Blue);
+                self.events.push(GameEvent::Goal {
+                    side: GameSide::Blue,
+                });
                 self.state = GameState::AfterGoal {
-                    time: Instant::now(),
+                    elapsed: 0,
                     scored: GameSide::Blue,
                 };
-                return Ok(());
+                return;
             }
             None => {}
         }
@@ -1255,6 +2415,7 @@ impl Game {
             Some(GameSide::Red) => {
                 if self.puck.can_be_stolen_by_player(&self.blue_player) {
                     self.puck.possession = Some(GameSide::Blue);
+                    self.blue_stats.steals += 1;
                     self.red_player.after_got_stolen_counter =
                         AFTER_GOT_STOLEN_COUNTER_MILLISECONDS;
                 }
@@ -1262,6 +2423,7 @@ impl Game {
             Some(GameSide::Blue) => {
                 if self.puck.can_be_stolen_by_player(&self.red_player) {
                     self.puck.possession = Some(GameSide::Red);
+                    self.red_stats.steals += 1;
                     self.blue_player.after_got_stolen_counter =
                         AFTER_GOT_STOLEN_COUNTER_MILLISECONDS;
                 }
@@ -1298,106 +2460,445 @@ impl Game {
             }
         }
 
-        // Puck positioning logic.
-        // If the puck is in possession, it follows the player unless the player is shooting.
-        if let Some(side) = self.puck.possession {
-            let (player, other) = if side == GameSide::Red {
-                (&mut self.red_player, &mut self.blue_player)
-            } else {
-                (&mut self.blue_player, &mut self.red_player)
-            };
+        // A shot ends when someone catches the puck.
+        if let Some(side) = self.puck.possession {
+            self.shot_in_flight = None;
+            self.stats_mut(side).possession_milliseconds += deltatime;
+        }
+
+        // Puck positioning logic.
+        // If the puck is in possession, it follows the player unless the player is shooting.
+        if let Some(side) = self.puck.possession {
+            let (player, other) = if side == GameSide::Red {
+                (&mut self.red_player, &mut self.blue_player)
+            } else {
+                (&mut self.blue_player, &mut self.red_player)
+            };
+
+            if player.shooting_counter > 0.0 {
+                player.shooting_counter -= deltatime;
+                player.windup_milliseconds += deltatime;
+                // If the player is shooting counter went to 0, the puck follows the shooting direction.
+                if player.shooting_counter <= 0.0
+                    || player.is_one_timer
+                    || player.charge_milliseconds >= SLAPSHOT_CHARGE_MILLISECONDS
+                {
+                    player.after_shooting_counter = AFTER_SHOOTING_COUNTER_MILLISECONDS;
+                    player.new_orientation = Some(((player.orientation as u8 + 1) % 8).into());
+
+                    // FIXME: put shooting direction and counter together in a single variable
+                    // Aiming only bends the shot, its speed comes from the wind up.
+                    self.puck.shoot(
+                        player
+                            .shooting_direction
+                            .unwrap_or(player.velocity)
+                            .normalize()
+                            .mul(player.shot_power()),
+                    );
+
+                    player.cancel_shot();
+
+                    // The speed the puck really leaves with, as shown by the shot meter.
+                    let speed = puck_speed(self.puck.velocity());
+                    let stats = match side {
+                        GameSide::Red => &mut self.red_stats,
+                        GameSide::Blue => &mut self.blue_stats,
+                    };
+                    stats.shots += 1;
+                    stats.max_puck_speed = stats.max_puck_speed.max(speed);
+                    stats.last_shot_speed = speed;
+                    self.shot_in_flight = Some(side);
+                }
+            } else {
+                self.puck.attach_to_player(&player);
+            }
+
+            if other.shooting_counter > 0.0 {
+                other.cancel_shot();
+            }
+        }
+    }
+}
+
+/// What is drawn around the rink besides the simulation, which can differ between viewers.
+#[derive(Clone, Copy)]
+struct Hud<'a> {
+    timer: u128,
+    fps: f32,
+    banner: Option<&'a str>,
+    quick_chats: (Option<&'a str>, Option<&'a str>),
+    // The side of the player looking at the screen, None for spectators.
+    rules_side: Option<GameSide>,
+    show_stats: bool,
+}
+
+#[derive(Clone)]
+pub struct Game {
+    red_client: Client,
+    blue_client: Client,
+    simulation: Simulation,
+    pub id: uuid::Uuid,
+    last_tick: Instant,
+    fps: f32,
+    banner: Option<(String, Instant)>,
+    spectators: Vec<Client>,
+}
+
+impl Game {
+    pub fn new(red_client: Client, blue_client: Client, restitution: Restitution) -> Self {
+        Self {
+            red_client,
+            blue_client,
+            simulation: Simulation::with_restitution(restitution),
+            id: uuid::Uuid::new_v4(),
+            last_tick: Instant::now(),
+            fps: 0.0,
+            banner: None,
+            spectators: vec![],
+        }
+    }
+
+    pub fn clear_client(&mut self, client_id: usize) {
+        if self.red_client.id == client_id {
+            self.red_client.needs_clear = true;
+        } else if self.blue_client.id == client_id {
+            self.blue_client.needs_clear = true;
+        } else if let Some(spectator) = self
+            .spectators
+            .iter_mut()
+            .find(|spectator| spectator.id == client_id)
+        {
+            spectator.needs_clear = true;
+        }
+    }
+
+    pub fn add_spectator(&mut self, spectator: Client) {
+        self.spectators.push(spectator);
+    }
+
+    pub fn remove_spectator(&mut self, client_id: usize) {
+        self.spectators
+            .retain(|spectator| spectator.id != client_id);
+    }
+
+    pub fn spectator_ids(&self) -> Vec<usize> {
+        self.spectators
+            .iter()
+            .map(|spectator| spectator.id)
+            .collect()
+    }
+
+    fn close(&mut self) {
+        self.red_client.is_connected = false;
+        self.blue_client.is_connected = false;
+    }
+
+    pub fn disconnect(&mut self, client_id: usize) {
+        if self.red_client.id == client_id {
+            self.red_client.disconnect();
+        } else if self.blue_client.id == client_id {
+            self.blue_client.disconnect();
+        }
+    }
+
+    /// Disconnects a client that quit on purpose, so that it cannot reconnect.
+    pub fn leave(&mut self, client_id: usize) {
+        self.disconnect(client_id);
+        if self.red_client.id == client_id {
+            self.red_client.can_reconnect = false;
+        } else if self.blue_client.id == client_id {
+            self.blue_client.can_reconnect = false;
+        }
+    }
+
+    pub fn can_reconnect(&self, client_id: usize) -> bool {
+        if self.red_client.id == client_id {
+            self.red_client.is_waiting_for_reconnection()
+        } else if self.blue_client.id == client_id {
+            self.blue_client.is_waiting_for_reconnection()
+        } else {
+            false
+        }
+    }
+
+    /// Rebinds a new terminal to the client that dropped with `old_client_id`.
+    pub fn reconnect(&mut self, old_client_id: usize, new_client_id: usize, terminal: SshTerminal) {
+        let client = if self.red_client.id == old_client_id {
+            &mut self.red_client
+        } else {
+            &mut self.blue_client
+        };
+
+        client.id = new_client_id;
+        client.terminal = Some(terminal);
+        client.is_connected = true;
+        client.disconnected_at = None;
+        client.needs_clear = true;
+    }
+
+    /// Ids of the players connected over ssh, excluding the AI.
+    pub fn human_client_ids(&self) -> Vec<usize> {
+        [&self.red_client, &self.blue_client]
+            .iter()
+            .filter(|client| client.is_human())
+            .map(|client| client.id)
+            .collect()
+    }
+
+    /// Humans or bots are still playing, the AI or a script alone does not keep a game going.
+    pub fn has_connected_players(&self) -> bool {
+        [&self.red_client, &self.blue_client]
+            .iter()
+            .any(|client| !client.is_computer() && client.is_connected)
+    }
+
+    pub fn is_against_ai(&self) -> bool {
+        self.red_client.is_computer() || self.blue_client.is_computer()
+    }
+
+    /// The bots playing the game and their side.
+    pub fn bots(&self) -> Vec<(GameSide, BotLink)> {
+        [
+            (GameSide::Red, &self.red_client),
+            (GameSide::Blue, &self.blue_client),
+        ]
+        .into_iter()
+        .filter_map(|(side, client)| Some((side, client.bot.clone()?)))
+        .collect()
+    }
+
+    pub fn names(&self) -> (&str, &str) {
+        (&self.red_client.name, &self.blue_client.name)
+    }
+
+    pub fn is_waiting_for_reconnection(&self) -> bool {
+        self.red_client.is_waiting_for_reconnection()
+            || self.blue_client.is_waiting_for_reconnection()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.simulation.is_over()
+    }
+
+    /// The game is over and the result has been shown long enough.
+    pub fn is_finished(&self) -> bool {
+        self.simulation.is_finished()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.red_client.is_connected && self.blue_client.is_connected
+    }
+
+    pub fn client_ids(&self) -> (usize, usize) {
+        (self.red_client.id, self.blue_client.id)
+    }
+
+    pub fn score(&self) -> (u8, u8) {
+        self.simulation.score()
+    }
+
+    pub fn stats(&self) -> (MatchStats, MatchStats) {
+        self.simulation.stats()
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    pub fn remaining_seconds(&self) -> u128 {
+        self.simulation.remaining_seconds()
+    }
+
+    /// Elapsed game time, excluding pauses after goals.
+    pub fn game_time_seconds(&self) -> u128 {
+        self.simulation.game_time_seconds()
+    }
+
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        self.simulation.drain_events()
+    }
+
+    /// Shows a message on top of the game to both players for a few seconds.
+    pub fn set_banner(&mut self, text: &str) {
+        self.banner = Some((text.to_string(), Instant::now()));
+    }
+
+    /// Ends the game immediately, showing the result to both players.
+    pub fn end(&mut self) {
+        self.simulation.end();
+    }
+
+    pub fn handle_spectator_input(&mut self, client_id: usize, key_code: KeyCode) {
+        let Some(spectator) = self
+            .spectators
+            .iter_mut()
+            .find(|spectator| spectator.id == client_id)
+        else {
+            return;
+        };
+        match key_code {
+            KeyCode::Char('p') => spectator.palette = spectator.palette.next(),
+            KeyCode::Char('s') => spectator.show_stats = !spectator.show_stats,
+            _ => {}
+        }
+    }
+
+    pub fn handle_input(&mut self, client_id: usize, key_code: KeyCode) {
+        if key_code == KeyCode::Esc {
+            self.leave(client_id);
+            return;
+        }
 
-            if player.shooting_counter > 0.0 {
-                player.shooting_counter -= deltatime;
-                // If the player is shooting counter went to 0, the puck follows the shooting direction.
-                if player.shooting_counter <= 0.0 {
-                    player.shooting_counter = 0.0;
-                    player.after_shooting_counter = AFTER_SHOOTING_COUNTER_MILLISECONDS;
-                    player.new_orientation = Some(((player.orientation as u8 + 1) % 8).into());
-                    self.puck.possession = None;
+        if key_code == KeyCode::Char('p') {
+            if self.red_client.id == client_id {
+                self.red_client.palette = self.red_client.palette.next();
+            } else {
+                self.blue_client.palette = self.blue_client.palette.next();
+            }
+            return;
+        }
 
-                    // FIXME: put shooting direction and counter together in a single variable
-                    self.puck.velocity = player
-                        .shooting_direction
-                        .unwrap_or(player.velocity)
-                        .mul(SHOOTING_POWER);
+        if key_code == KeyCode::Char('s') {
+            if self.red_client.id == client_id {
+                self.red_client.show_stats = !self.red_client.show_stats;
+            } else {
+                self.blue_client.show_stats = !self.blue_client.show_stats;
+            }
+            return;
+        }
 
-                    player.shooting_direction = None;
-                }
+        if let KeyCode::Char(c @ '1'..='9') = key_code {
+            let index = c as usize - '1' as usize;
+            if self.red_client.id == client_id {
+                self.red_client.send_quick_chat(index);
             } else {
-                self.puck.attach_to_player(&player);
+                self.blue_client.send_quick_chat(index);
             }
+            return;
+        }
 
-            if other.shooting_counter > 0.0 {
-                other.shooting_counter = 0.0;
-                other.shooting_direction = None;
+        let side = if self.red_client.id == client_id {
+            GameSide::Red
+        } else {
+            GameSide::Blue
+        };
+        self.simulation.handle_input(side, key_code);
+    }
+
+    pub fn update(&mut self) -> AppResult<()> {
+        let now = Instant::now();
+        let deltatime = now.duration_since(self.last_tick).as_millis() as f32;
+        if deltatime < MINIMUM_DELTATIME_MILLISECONDS {
+            return Ok(());
+        }
+
+        for client in [&mut self.red_client, &mut self.blue_client] {
+            if client.bot.as_ref().is_some_and(|bot| !bot.is_connected()) {
+                client.disconnect();
             }
         }
 
+        let inputs = self.computer_inputs(deltatime);
+        self.simulation.advance(&inputs, deltatime);
+        if self.simulation.is_finished() {
+            self.close();
+        }
+
+        let snapshot = self.simulation.snapshot();
+        for (side, bot) in self.bots() {
+            bot.send(&BotMessage::Tick {
+                side,
+                snapshot: &snapshot,
+            });
+        }
+        self.fps = 1000.0 / deltatime;
+        self.last_tick = now;
+
         Ok(())
     }
 
+    /// Keys pressed by the AI and the bots.
+    fn computer_inputs(&mut self, deltatime: f32) -> Vec<(GameSide, KeyCode)> {
+        if !self.simulation.is_running() {
+            return vec![];
+        }
+        let snapshot = self.simulation.snapshot();
+        let mut inputs = vec![];
+        for (side, client) in [
+            (GameSide::Red, &mut self.red_client),
+            (GameSide::Blue, &mut self.blue_client),
+        ] {
+            let view = self.simulation.ai_view(side);
+            let key_code = if let Some(ai) = client.ai.as_mut() {
+                ai.next_input(&view, deltatime)
+            } else if let Some(script) = client.script.as_mut() {
+                script.next_input(side, &snapshot, deltatime)
+            } else if let Some(bot) = client.bot.as_ref() {
+                bot.take_action()
+                    .and_then(|action| action.key_code(view.is_shooting))
+            } else {
+                None
+            };
+            if let Some(key_code) = key_code {
+                inputs.push((side, key_code));
+            }
+        }
+        inputs
+    }
+
     pub fn draw(&mut self) -> AppResult<()> {
-        let timer = if self.timer > GAME_DURATION_MILLISECONDS {
-            0
-        } else {
-            (GAME_DURATION_MILLISECONDS - self.timer) / 1000
-        };
+        let timer = self.remaining_seconds();
+        let simulation = &self.simulation;
 
-        if self.red_client.is_connected {
-            if self
-                .red_client
-                .terminal
-                .draw(|f| {
-                    Self::render(
-                        f,
-                        self.red_client.palette,
-                        &self.red_player,
-                        &self.red_goalie,
-                        &self.blue_player,
-                        &self.blue_goalie,
-                        &self.puck,
-                        &self.skate_traces,
-                        self.red_score,
-                        self.blue_score,
-                        self.red_goalie.saves,
-                        self.blue_goalie.saves,
-                        timer,
-                        self.fps,
-                        self.state,
-                        GameSide::Red,
-                    )
-                    .unwrap_or_else(|e| {
-                        log::error!("Failed to draw game: {}", e);
-                    })
-                })
-                .is_err()
-            {
-                self.red_client.is_connected = false;
+        if let Some((_, time)) = &self.banner {
+            if time.elapsed().as_millis() > BANNER_DURATION_MILLISECONDS {
+                self.banner = None;
             }
         }
-        if self.blue_client.is_connected {
-            if self
-                .blue_client
-                .terminal
+        let banner = self.banner.as_ref().map(|(text, _)| text.as_str());
+        let quick_chats = (
+            self.red_client.visible_quick_chat(),
+            self.blue_client.visible_quick_chat(),
+        );
+
+        for (client, rules_side) in [
+            (&mut self.red_client, Some(GameSide::Red)),
+            (&mut self.blue_client, Some(GameSide::Blue)),
+        ]
+        .into_iter()
+        .chain(
+            self.spectators
+                .iter_mut()
+                .map(|spectator| (spectator, None)),
+        ) {
+            if !client.is_connected {
+                continue;
+            }
+            if client.needs_clear {
+                client
+                    .clear()
+                    .unwrap_or_else(|e| log::error!("Failed to clear client terminal: {e}"));
+                client.needs_clear = false;
+            }
+            let palette = client.palette;
+            let show_stats = client.show_stats;
+            let Some(terminal) = client.terminal.as_mut() else {
+                continue;
+            };
+            if terminal
                 .draw(|f| {
                     Self::render(
                         f,
-                        self.blue_client.palette,
-                        &self.red_player,
-                        &self.red_goalie,
-                        &self.blue_player,
-                        &self.blue_goalie,
-                        &self.puck,
-                        &self.skate_traces,
-                        self.red_score,
-                        self.blue_score,
-                        self.red_goalie.saves,
-                        self.blue_goalie.saves,
-                        timer,
-                        self.fps,
-                        self.state,
-                        GameSide::Blue,
+                        palette,
+                        simulation,
+                        &Hud {
+                            timer,
+                            fps: self.fps,
+                            banner,
+                            quick_chats,
+                            rules_side,
+                            show_stats,
+                        },
                     )
                     .unwrap_or_else(|e| {
                         log::error!("Failed to draw game: {}", e);
@@ -1405,9 +2906,10 @@ impl Game {
                 })
                 .is_err()
             {
-                self.blue_client.is_connected = false;
+                client.disconnect();
             }
         }
+        self.spectators.retain(|spectator| spectator.is_connected);
 
         Ok(())
     }
@@ -1415,27 +2917,32 @@ impl Game {
     fn render(
         frame: &mut Frame,
         palette: Palette,
-        red_player: &impl Body,
-        red_goalie: &impl Body,
-        blue_player: &impl Body,
-        blue_goalie: &impl Body,
-        puck: &impl Body,
-        skate_traces: &[(f32, f32)],
-        red_score: u8,
-        blue_score: u8,
-        red_saves: usize,
-        blue_saves: usize,
-        timer: u128,
-        fps: f32,
-        state: GameState,
-        rules_side: GameSide,
+        simulation: &Simulation,
+        hud: &Hud,
     ) -> AppResult<()> {
+        let Hud {
+            timer,
+            fps,
+            banner,
+            quick_chats,
+            rules_side,
+            show_stats,
+        } = *hud;
+        let red_player = &simulation.red_player;
+        let red_goalie = &simulation.red_goalie;
+        let blue_player = &simulation.blue_player;
+        let blue_goalie = &simulation.blue_goalie;
+        let puck = &simulation.puck;
+        let (red_score, blue_score) = (simulation.red_score, simulation.blue_score);
+        let state = simulation.state;
+        let show_post = simulation.post_banner_milliseconds > 0.0;
+
         let split =
             Layout::vertical([Constraint::Length(7), Constraint::Min(1)]).split(frame.size());
 
         let mut img = base_image(palette);
 
-        for (x, y) in skate_traces {
+        for (x, y) in simulation.skate_traces.iter() {
             img.put_pixel(*x as u32, *y as u32, skate_trace_color(palette));
         }
 
@@ -1468,12 +2975,29 @@ impl Game {
             puck.position().1 as u32,
         )?;
 
+        if let Some(charge) = red_player.winding_up_charge() {
+            draw_power_bar(&mut img, red_player, charge, palette);
+        }
+        if let Some(charge) = blue_player.winding_up_charge() {
+            draw_power_bar(&mut img, blue_player, charge, palette);
+        }
+
         let paragraph = Paragraph::new(img_to_lines(&img));
         frame.render_widget(paragraph, split[1]);
 
         let info_rect = Rect::new(frame.size().width - 20, frame.size().height - 1, 10, 1);
         frame.render_widget(Paragraph::new(format!("FPS:{}", fps as u32)), info_rect);
 
+        if let Some(text) = banner {
+            let banner_rect = Rect::new(0, 0, frame.size().width, 1);
+            frame.render_widget(
+                Paragraph::new(text.to_string())
+                    .style(Style::default().fg(Color::Yellow).bold())
+                    .centered(),
+                banner_rect,
+            );
+        }
+
         let top_split = Layout::horizontal([
             Constraint::Length(20),
             Constraint::Length(43),
@@ -1497,40 +3021,88 @@ impl Game {
         });
         frame.render_widget(red_score_paragraph, area);
 
+        let (red_stats, blue_stats) = simulation.stats();
+        let total_possession =
+            red_stats.possession_milliseconds + blue_stats.possession_milliseconds;
+        let possession = |stats: &MatchStats| {
+            if total_possession > 0.0 {
+                100.0 * stats.possession_milliseconds / total_possession
+            } else {
+                50.0
+            }
+        };
+        let stats_lines = |stats: &MatchStats| {
+            let filled = ((stats.last_shot_speed / SHOT_METER_FULL_SCALE).min(1.0)
+                * SHOT_METER_WIDTH as f32)
+                .round() as usize;
+            vec![
+                Line::from(format!("Saves {}  Steals {}", stats.saves, stats.steals)),
+                Line::from(format!(
+                    "Shots {}  On target {}",
+                    stats.shots, stats.shots_on_target
+                )),
+                Line::from(format!("Possession {:.0}%", possession(stats))),
+                Line::from(format!(
+                    "Last shot {}{} {:.0} px/s",
+                    "█".repeat(filled),
+                    "░".repeat(SHOT_METER_WIDTH - filled),
+                    stats.last_shot_speed
+                )),
+            ]
+        };
+        let saves =
+            |stats: &MatchStats| Paragraph::new(format!("Saves {}", stats.saves)).centered();
+        let player_help = |stats: &MatchStats| {
+            if show_stats {
+                let mut lines = stats_lines(stats);
+                lines.push(Line::from("s: show controls"));
+                return Paragraph::new(lines).centered();
+            }
+            Paragraph::new(vec![
+                Line::from(format!("Saves {}", stats.saves)),
+                Line::from("← ↑ → ↓: move"),
+                Line::from("space: shoot, hold: slapshot"),
+                Line::from("p: change palette  s: stats"),
+                Line::from("1-9: quick chat"),
+                Line::from("Esc: back to lobby"),
+            ])
+            .centered()
+        };
+        let opponent_panel = |stats: &MatchStats| {
+            if show_stats {
+                Paragraph::new(stats_lines(stats)).centered()
+            } else {
+                saves(stats)
+            }
+        };
+
         match rules_side {
-            GameSide::Red => {
-                frame.render_widget(
-                    Paragraph::new(vec![
-                        Line::from(format!("Saves {}", red_saves)),
-                        Line::from("← ↑ → ↓: move"),
-                        Line::from("space: shoot"),
-                        Line::from("p: change palette"),
-                        Line::from("Esc: close game"),
-                    ])
-                    .centered(),
-                    top_split[1],
-                );
-                frame.render_widget(
-                    Paragraph::new(format!("Saves {}", blue_saves)).centered(),
-                    top_split[3],
-                );
+            Some(GameSide::Red) => {
+                frame.render_widget(player_help(&red_stats), top_split[1]);
+                frame.render_widget(opponent_panel(&blue_stats), top_split[3]);
             }
-            GameSide::Blue => {
-                frame.render_widget(
-                    Paragraph::new(format!("Saves {}", red_saves)).centered(),
-                    top_split[1],
-                );
+            Some(GameSide::Blue) => {
+                frame.render_widget(opponent_panel(&red_stats), top_split[1]);
+                frame.render_widget(player_help(&blue_stats), top_split[3]);
+            }
+            None if show_stats => {
+                let mut lines = stats_lines(&red_stats);
+                lines.push(Line::from("s: hide stats  Esc: lobby"));
+                frame.render_widget(Paragraph::new(lines).centered(), top_split[1]);
+                frame.render_widget(opponent_panel(&blue_stats), top_split[3]);
+            }
+            None => {
                 frame.render_widget(
                     Paragraph::new(vec![
-                        Line::from(format!("Saves {}", blue_saves)),
-                        Line::from("← ↑ → ↓: move"),
-                        Line::from("space: shoot"),
-                        Line::from("p: change palette"),
-                        Line::from("Esc: close game"),
+                        Line::from(format!("Saves {}", red_stats.saves)),
+                        Line::from("Spectating"),
+                        Line::from("p: change palette  s: stats"),
+                        Line::from("Esc: back to lobby"),
                     ])
                     .centered(),
-                    top_split[3],
+                    top_split[1],
                 );
+                frame.render_widget(saves(&blue_stats), top_split[3]);
             }
         }
 
@@ -1542,6 +3114,26 @@ impl Game {
         });
         frame.render_widget(blue_score_paragraph, area);
 
+        // Quick chat messages are shown on the rink border, below the score of the sender.
+        for (message, side) in [
+            (quick_chats.0, GameSide::Red),
+            (quick_chats.1, GameSide::Blue),
+        ] {
+            let Some(message) = message else {
+                continue;
+            };
+            let width = message.chars().count() as u16 + 2;
+            let (x, color) = match side {
+                GameSide::Red => (top_split[0].x + 2, Color::Red),
+                GameSide::Blue => (top_split[4].right().saturating_sub(width + 2), Color::Blue),
+            };
+            frame.render_widget(
+                Paragraph::new(format!(" {message} "))
+                    .style(Style::default().fg(Color::White).bg(color).bold()),
+                Rect::new(x, top_split[0].bottom(), width, 1),
+            );
+        }
+
         let timer_split = Layout::horizontal([
             Constraint::Length(10),
             Constraint::Length(4),
@@ -1567,14 +3159,13 @@ impl Game {
         frame.render_widget(seconds_units_paragraph, timer_split[3]);
 
         match state {
-            GameState::Starting { time } => {
+            GameState::Starting { elapsed } => {
                 let rect = Rect::new(
                     (MIN_X + MAX_X) as u16 / 2 - 5,
                     (MIN_Y + MAX_Y) as u16 / 4 + 5,
                     10,
                     10,
                 );
-                let elapsed = time.elapsed().as_millis();
                 let countdown_paragraph = if STARTING_DELAY_MILLISECONDS > elapsed {
                     (((STARTING_DELAY_MILLISECONDS - elapsed) / 1000) as u8 + 1)
                         .big_font_styled(color_1, color_2)
@@ -1584,7 +3175,7 @@ impl Game {
 
                 frame.render_widget(countdown_paragraph, rect);
             }
-            GameState::AfterGoal { time: _, scored } => {
+            GameState::AfterGoal { elapsed: _, scored } => {
                 let rect = Rect::new(
                     (MIN_X + MAX_X) as u16 / 2 - 44,
                     (MIN_Y + MAX_Y) as u16 / 4 + 5,
@@ -1614,6 +3205,15 @@ impl Game {
                 };
                 frame.render_widget(congrats, rect);
             }
+            GameState::Running if show_post => {
+                let rect = Rect::new(
+                    (MIN_X + MAX_X) as u16 / 2 - 19,
+                    (MIN_Y + MAX_Y) as u16 / 4 + 5,
+                    38,
+                    10,
+                );
+                frame.render_widget(post(color_1, color_2), rect);
+            }
             _ => {}
         }
 
@@ -2087,6 +3687,7 @@ mod test {
                 &mut goalie,
                 CollisionType::Minimal,
                 CollisionType::Minimal,
+                COEFFICIENT_OF_RESTITUTION,
             ) {
                 puck.set_position(puck_previous_position);
             }
@@ -2124,4 +3725,577 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_puck_sweep() {
+        let puck = CollisionBox {
+            x: 50.0,
+            y: 42.0,
+            width: PUCK_WIDTH,
+            height: PUCK_HEIGHT,
+        };
+        let player = CollisionBox {
+            x: 70.0,
+            y: 36.0,
+            width: 8.0,
+            height: 14.0,
+        };
+
+        // A displacement jumping over the player still hits its side.
+        let contact = puck.sweep((40.0, 0.0), &player).unwrap();
+        assert_eq!(contact.time, 0.45);
+        assert_eq!(contact.normal, (-1.0, 0.0));
+
+        // Moving away, along or short of the player is not a contact.
+        assert_eq!(puck.sweep((-40.0, 0.0), &player), None);
+        assert_eq!(puck.sweep((0.0, 40.0), &player), None);
+        assert_eq!(puck.sweep((10.0, 0.0), &player), None);
+
+        // A puck inside the player is pushed out of the closest side.
+        let inside = CollisionBox { x: 71.0, ..puck };
+        let contact = inside.sweep((0.0, 0.0), &player).unwrap();
+        assert_eq!(contact.time, 0.0);
+        assert_eq!(contact.normal, (-1.0, 0.0));
+        assert_eq!(contact.penetration, 3.0);
+    }
+
+    #[test]
+    fn test_player_collision_masks() {
+        for (images, masks) in [
+            (&*RED_PLAYER, &*RED_PLAYER_MASKS),
+            (&*BLUE_PLAYER, &*BLUE_PLAYER_MASKS),
+        ] {
+            assert_eq!(masks.len(), 8);
+            for (image, mask) in images.iter().zip(masks.iter()) {
+                assert_eq!(mask.width, image.width() as i32);
+                assert_eq!(mask.height, image.height() as i32);
+                assert!(mask.pixels.iter().any(|is_solid| *is_solid));
+            }
+        }
+
+        let mut player = Player::new(GameSide::Red);
+        player.orientation = Orientation::DownLeft;
+        player.position = (50.0, 40.0);
+        let mask = player.collision_mask().unwrap();
+
+        // The empty corner of a diagonal sprite does not stop the puck, its body does.
+        let corner = (51.0, 52.0);
+        assert!(
+            CollisionBox::from(player.full_collision_rect()).overlaps(&CollisionBox::from(
+                Rect::new(corner.0 as u16, corner.1 as u16, 2, 2)
+            ))
+        );
+        assert!(!PUCK_MASK.overlaps(pixel_position(corner), mask, (50, 40)));
+        assert!(PUCK_MASK.overlaps((58, 47), mask, (50, 40)));
+
+        // A puck hitting the diagonal edge bounces off along the outline.
+        let contact = PUCK_MASK
+            .sweep((50.0, 50.0), (6.0, 0.0), mask, player.position)
+            .unwrap();
+        assert!(contact.time > 0.0 && contact.time < 1.0);
+        assert!(contact.normal.0 < 0.0 && contact.normal.1 > 0.0);
+        let position = (50.0 + 6.0 * contact.time, 50.0);
+        assert!(!PUCK_MASK.overlaps(pixel_position(position), mask, (50, 40)));
+
+        // Players only collide when their pixels do.
+        let mut other = Player::new(GameSide::Blue);
+        other.orientation = Orientation::UpRight;
+        other.position = (40.0, 50.0);
+        assert!(CollisionBox::from(player.full_collision_rect())
+            .overlaps(&CollisionBox::from(other.full_collision_rect())));
+        assert!(!resolve_collision(
+            &mut player,
+            &mut other,
+            CollisionType::Mask,
+            CollisionType::Mask,
+            COEFFICIENT_OF_RESTITUTION
+        ));
+        other.position = (50.0, 45.0);
+        assert!(resolve_collision(
+            &mut player,
+            &mut other,
+            CollisionType::Mask,
+            CollisionType::Mask,
+            COEFFICIENT_OF_RESTITUTION
+        ));
+    }
+
+    #[test]
+    fn test_impulse_along_contact_normal() {
+        // A puck hitting a goalie at an angle keeps its speed along the goalie,
+        // and bounces back with the restitution of the pair.
+        let mut puck = Puck::new();
+        let mut goalie = Goalie::new(GameSide::Blue);
+        puck.velocity = (1.0, 1.0);
+        assert!(apply_impulse(&mut puck, &mut goalie, (-1.0, 0.0), 0.5));
+        assert!((puck.velocity.0 + 0.5).abs() < 0.01);
+        assert_eq!(puck.velocity.1, 1.0);
+
+        // Bodies moving apart are left alone.
+        let velocity = puck.velocity;
+        assert!(!apply_impulse(&mut puck, &mut goalie, (-1.0, 0.0), 0.5));
+        assert_eq!(puck.velocity, velocity);
+
+        // Momentum is conserved between players, and no energy is lost with a restitution of 1.
+        let mut red_player = Player::new(GameSide::Red);
+        let mut blue_player = Player::new(GameSide::Blue);
+        red_player.velocity = (1.0, 0.0);
+        blue_player.velocity = (-0.5, 0.5);
+        let normal = (-1.0, -1.0).normalize();
+        assert!(apply_impulse(
+            &mut red_player,
+            &mut blue_player,
+            normal,
+            1.0
+        ));
+        let momentum = red_player
+            .velocity
+            .add(blue_player.velocity)
+            .mul(PLAYER_MASS);
+        assert!((momentum.0 - 0.5 * PLAYER_MASS).abs() < 0.001);
+        assert!((momentum.1 - 0.5 * PLAYER_MASS).abs() < 0.001);
+        let energy =
+            red_player.velocity.magnitude().powi(2) + blue_player.velocity.magnitude().powi(2);
+        assert!((energy - 1.5).abs() < 0.001);
+
+        // Without restitution the puck stops against the goalie.
+        let mut simulation = Simulation::with_restitution(Restitution {
+            puck_goalie: 0.0,
+            ..Restitution::default()
+        });
+        simulation.state = GameState::Running;
+        simulation.blue_player.position = (100.0, MIN_Y);
+        simulation.update_running(MINIMUM_DELTATIME_MILLISECONDS);
+        let goalie_box = simulation.blue_goalie.collision_box();
+        simulation.puck.position = (goalie_box.x - 10.0, goalie_box.y + 2.0);
+        simulation.puck.velocity = (MAX_PUCK_VELOCITY, 0.0);
+        for _ in 0..10 {
+            simulation.update_running(MINIMUM_DELTATIME_MILLISECONDS);
+        }
+        assert!(simulation.puck.velocity.0.abs() < 0.01);
+        assert_eq!(simulation.blue_stats.saves, 1);
+    }
+
+    #[test]
+    fn test_puck_does_not_pass_through() {
+        let mut simulation = Simulation::new();
+        simulation.state = GameState::Running;
+        simulation.blue_player.position = (70.0, 30.0);
+        simulation.puck.position = (50.0, 42.0);
+        simulation.puck.velocity = (MAX_PUCK_VELOCITY, 0.0);
+
+        // A long frame moves the puck further than the width of the player.
+        simulation.update_running(10.0 * MINIMUM_DELTATIME_MILLISECONDS);
+        assert!(simulation.puck.position.0 + PUCK_WIDTH <= 70.0);
+        assert!(simulation.puck.velocity.0 < 0.0);
+
+        // A puck shot on the goalie at full speed bounces back once.
+        let mut simulation = Simulation::new();
+        simulation.state = GameState::Running;
+        simulation.blue_player.position = (100.0, MIN_Y);
+        simulation.update_running(MINIMUM_DELTATIME_MILLISECONDS);
+        let goalie_box = simulation.blue_goalie.collision_box();
+        simulation.puck.position = (goalie_box.x - 30.0, goalie_box.y + 2.0);
+        simulation.puck.velocity = (MAX_PUCK_VELOCITY, 0.0);
+        for _ in 0..10 {
+            simulation.update_running(5.0 * MINIMUM_DELTATIME_MILLISECONDS);
+            assert!(!simulation
+                .puck
+                .collision_box()
+                .overlaps(&simulation.blue_goalie.collision_box()));
+        }
+        assert_eq!(simulation.score(), (0, 0));
+        assert_eq!(simulation.blue_stats.saves, 1);
+        assert!(simulation.puck.velocity.0 < 0.0);
+    }
+
+    #[test]
+    fn test_rink_corners() {
+        // A puck sliding along the boards follows the rounding of the corner.
+        let mut simulation = Simulation::new();
+        simulation.state = GameState::Running;
+        simulation.puck.position = (40.0, MIN_Y);
+        simulation.puck.velocity = (-MAX_PUCK_VELOCITY, 0.0);
+        for _ in 0..25 {
+            simulation.update_running(MINIMUM_DELTATIME_MILLISECONDS);
+            assert!(simulation
+                .puck
+                .collision_box()
+                .rink_corner_penetration()
+                .is_none_or(|(_, depth)| depth < 0.01));
+        }
+        let (x, _) = simulation.puck.position;
+        let (vx, vy) = simulation.puck.velocity;
+        assert!(x < MIN_X + 1.0);
+        assert!(vy > 1.5 && vx.abs() < 0.1);
+
+        // Players cannot skate into the corners either.
+        let mut player = Player::new(GameSide::Red);
+        player.velocity = (-1.0, -1.0);
+        player.set_position((MIN_X, MIN_Y));
+        let (width, height) = player.size();
+        let full_box = CollisionBox {
+            x: player.position.0,
+            y: player.position.1,
+            width,
+            height,
+        };
+        assert!(full_box
+            .rink_corner_penetration()
+            .is_none_or(|(_, depth)| depth < 0.01));
+        assert!(player.velocity.0 > 0.0 && player.velocity.1 > 0.0);
+    }
+
+    #[test]
+    fn test_pitch_corners() {
+        // The boards painted in the corners of every pitch are where the puck bounces.
+        for (palette, asset) in [
+            (Palette::Dark, "pitch_empty.png"),
+            (Palette::Light, "pitch_classic.png"),
+            (Palette::Basket, "pitch_basket.png"),
+            (Palette::Alt, "pitch_alt.png"),
+        ] {
+            let art = read_image(asset).unwrap();
+            let pitch = base_image(palette);
+            let is_painted_board = |x: u32, y: u32| {
+                let is_in_corner = (x as f32) < MIN_X + RINK_CORNER_RADIUS
+                    || (x as f32) > MAX_X - RINK_CORNER_RADIUS;
+                is_in_corner && pitch.get_pixel(x, y) != art.get_pixel(x, y)
+            };
+            assert!(is_painted_board(MIN_X as u32, MIN_Y as u32), "{asset}");
+
+            for (center, direction) in RINK_CORNERS {
+                let mut simulation = Simulation::new();
+                simulation.state = GameState::Running;
+                simulation.puck.position = center.sub((PUCK_WIDTH / 2.0, PUCK_HEIGHT / 2.0));
+                simulation.puck.velocity = direction.normalize().mul(MAX_PUCK_VELOCITY);
+                for _ in 0..20 {
+                    simulation.update_running(MINIMUM_DELTATIME_MILLISECONDS);
+                    // The puck is drawn over the ice, never over the boards.
+                    let (x, y) = simulation.puck.position;
+                    for (dx, dy, pixel) in simulation.puck.image(palette).enumerate_pixels() {
+                        let (x, y) = (x as u32 + dx, y as u32 + dy);
+                        assert!(
+                            pixel[3] == 0 || !is_painted_board(x, y),
+                            "{asset} {direction:?} {x} {y}"
+                        );
+                    }
+                }
+                assert!(simulation.puck.velocity.dot(&direction) < 0.0, "{asset}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_goal_posts() {
+        // A shot on the post rings off it without scoring.
+        let mut simulation = Simulation::new();
+        simulation.state = GameState::Running;
+        simulation.blue_player.position = (100.0, MIN_Y);
+        simulation.puck.position = (140.0, GOALIE_AREA_MIN_Y - 3.0);
+        simulation.puck.velocity = (MAX_PUCK_VELOCITY, 0.0);
+        for _ in 0..10 {
+            simulation.update_running(MINIMUM_DELTATIME_MILLISECONDS);
+        }
+        assert!(simulation.puck.velocity.0 < 0.0);
+        assert!(simulation.post_banner_milliseconds > 0.0);
+        assert_eq!(simulation.score(), (0, 0));
+        assert_eq!(simulation.blue_stats.saves, 0);
+
+        // The banner goes away after a while.
+        for _ in 0..(POST_BANNER_MILLISECONDS / MINIMUM_DELTATIME_MILLISECONDS) as usize {
+            simulation.update_running(MINIMUM_DELTATIME_MILLISECONDS);
+        }
+        assert_eq!(simulation.post_banner_milliseconds, 0.0);
+
+        // A goal needs the whole puck past the goal line.
+        let mut puck = Puck::new();
+        puck.position = (MAX_X - 1.0, 40.0);
+        assert_eq!(puck.has_scored(), None);
+        puck.position = (MAX_X, 40.0);
+        assert_eq!(puck.has_scored(), Some(GameSide::Red));
+        puck.position = (MIN_X - 1.0, 40.0);
+        assert_eq!(puck.has_scored(), None);
+        puck.position = (MIN_X - PUCK_WIDTH, 40.0);
+        assert_eq!(puck.has_scored(), Some(GameSide::Blue));
+
+        // Next to the goal mouth the boards are still there.
+        puck.set_position((MAX_X, GOALIE_AREA_MIN_Y - 6.0));
+        assert_eq!(puck.position, (MAX_X - PUCK_WIDTH, GOALIE_AREA_MIN_Y - 6.0));
+        puck.set_position((MAX_X + 10.0, 40.0));
+        assert_eq!(puck.position, (MAX_X + GOAL_NET_DEPTH - PUCK_WIDTH, 40.0));
+    }
+
+    #[test]
+    fn test_puck_tucked_inside_goalie() {
+        let mut simulation = Simulation::new();
+        simulation.state = GameState::Running;
+        simulation.update_running(MINIMUM_DELTATIME_MILLISECONDS);
+        let goalie_box = simulation.blue_goalie.collision_box();
+        simulation.puck.position = (goalie_box.x + 1.0, goalie_box.y + 2.0);
+        simulation.puck.velocity = (0.5, 0.0);
+
+        for _ in 0..20 {
+            simulation.update_running(MINIMUM_DELTATIME_MILLISECONDS);
+        }
+        assert!(!simulation
+            .puck
+            .collision_box()
+            .overlaps(&simulation.blue_goalie.collision_box()));
+        assert_eq!(simulation.blue_stats.saves, 1);
+        assert_eq!(simulation.score(), (0, 0));
+    }
+
+    #[test]
+    fn test_simulation_goal() {
+        let mut simulation = Simulation::new();
+        while simulation.snapshot().phase == Phase::Starting {
+            simulation.step(&[]);
+        }
+        assert_eq!(
+            simulation.tick as u128,
+            STARTING_DELAY_MILLISECONDS.div_ceil(MINIMUM_DELTATIME_MILLISECONDS as u128)
+        );
+
+        // Send the puck in the blue goal, on the side the goalie is not covering.
+        let goalie_y = simulation.blue_goalie.position.1;
+        let y = if goalie_y > (GOALIE_AREA_MIN_Y + GOALIE_AREA_MAX_Y) / 2.0 - GOALIE_HEIGHT {
+            GOALIE_AREA_MIN_Y
+        } else {
+            GOALIE_AREA_MAX_Y - PUCK_HEIGHT
+        };
+        simulation.puck.position = (MAX_X - PUCK_WIDTH - 4.0, y);
+        simulation.puck.velocity = (1.0, 0.0);
+        while simulation.snapshot().phase == Phase::Running {
+            simulation.step(&[]);
+        }
+
+        let snapshot = simulation.snapshot();
+        assert_eq!(snapshot.phase, Phase::AfterGoal);
+        assert_eq!(snapshot.score, (1, 0));
+        assert_eq!(
+            simulation.drain_events(),
+            vec![GameEvent::Goal {
+                side: GameSide::Red
+            }]
+        );
+
+        // Play resumes from the starting positions.
+        while simulation.snapshot().phase == Phase::AfterGoal {
+            simulation.step(&[]);
+        }
+        let snapshot = simulation.snapshot();
+        assert_eq!(snapshot.phase, Phase::Starting);
+        assert_eq!(snapshot.red_player.position, RED_INITIAL_POSITION);
+        assert_eq!(snapshot.blue_player.position, BLUE_INITIAL_POSITION);
+    }
+
+    #[test]
+    fn test_simulation_input() {
+        let mut simulation = Simulation::new();
+        // Inputs are ignored before the start.
+        simulation.step(&[(GameSide::Red, KeyCode::Right)]);
+        assert_eq!(simulation.snapshot().red_player.velocity, (0.0, 0.0));
+
+        while simulation.snapshot().phase == Phase::Starting {
+            simulation.step(&[]);
+        }
+        simulation.step(&[(GameSide::Red, KeyCode::Right)]);
+        let snapshot = simulation.snapshot();
+        assert!(snapshot.red_player.velocity.0 > 0.0);
+        assert!(snapshot.red_player.position.0 > RED_INITIAL_POSITION.0);
+        assert_eq!(snapshot.blue_player.position, BLUE_INITIAL_POSITION);
+    }
+
+    #[test]
+    fn test_charged_shots() {
+        // Returns the frame of the shot, the speed recorded for it and the speed of the puck a
+        // few frames later, pressing the shoot key at the given frames.
+        let shoot = |presses: &[usize]| {
+            let mut simulation = Simulation::new();
+            while simulation.state != GameState::Running {
+                simulation.step(&[]);
+            }
+            simulation.puck.possession = Some(GameSide::Red);
+            simulation.puck.attach_to_player(&simulation.red_player);
+            let mut charges = vec![];
+            for frame in 0..200 {
+                if presses.contains(&frame) {
+                    simulation.step(&[(GameSide::Red, KeyCode::Char(' '))]);
+                } else {
+                    simulation.step(&[]);
+                }
+                if simulation.red_stats.shots > 0 {
+                    assert_eq!(simulation.red_player.winding_up_charge(), None);
+                    for _ in 0..3 {
+                        simulation.step(&[]);
+                    }
+                    let flight_speed = puck_speed(simulation.puck.velocity);
+                    return (
+                        frame,
+                        simulation.red_stats.last_shot_speed,
+                        flight_speed,
+                        charges,
+                    );
+                }
+                charges.push(simulation.red_player.winding_up_charge().unwrap());
+            }
+            panic!("The shot never left");
+        };
+        let speed = |power: f32| power * 1000.0 / MINIMUM_DELTATIME_MILLISECONDS;
+        // The puck keeps the power of the shot, only slowed down by friction.
+        let is_flying_at = |flight_speed: f32, power: f32| {
+            flight_speed <= speed(power) && flight_speed > 0.95 * speed(power)
+        };
+
+        // The frame of the shot leaving this long after a press at the given frame.
+        let released = |frame: usize, milliseconds: f32| {
+            frame + (milliseconds / MINIMUM_DELTATIME_MILLISECONDS).ceil() as usize - 1
+        };
+        // A held key repeats after a delay, then every couple of frames.
+        let hold = |last_repeat: usize| {
+            [0].into_iter()
+                .chain((30..=last_repeat).step_by(2))
+                .collect::<Vec<usize>>()
+        };
+
+        // A tap is a wrist shot, leaving once the key could have started repeating.
+        let (frame, wrist_shot_speed, flight_speed, charges) = shoot(&[0]);
+        assert_eq!(frame, released(0, KEY_REPEAT_DELAY_MILLISECONDS));
+        assert!((wrist_shot_speed - speed(WRIST_SHOT_POWER)).abs() < 1.0);
+        assert!(is_flying_at(flight_speed, WRIST_SHOT_POWER));
+        assert!(charges.iter().all(|&charge| charge == 0.0));
+
+        // Holding the key charges a slapshot, which leaves at full power.
+        let (frame, slapshot_speed, flight_speed, charges) = shoot(&hold(200));
+        assert!(frame < 200 && frame * MINIMUM_DELTATIME_MILLISECONDS as usize >= 1000);
+        assert!((slapshot_speed - speed(SLAPSHOT_POWER)).abs() < 1.0);
+        assert!(is_flying_at(flight_speed, SLAPSHOT_POWER));
+        assert!(charges.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        // Letting go early gives a shot between the two, soon after the repeats stop.
+        let (frame, held_speed, _, _) = shoot(&hold(40));
+        assert_eq!(frame, released(40, HELD_KEY_RELEASE_MILLISECONDS));
+        assert!(wrist_shot_speed < held_speed && held_speed < slapshot_speed);
+
+        // Tapping again charges too, and leaves a bit later as taps are further apart.
+        let (frame, tapped_speed, _, _) = shoot(&[0, 15, 30]);
+        assert_eq!(frame, released(30, SHOT_RELEASE_MILLISECONDS));
+        assert!(wrist_shot_speed < tapped_speed && tapped_speed < slapshot_speed);
+
+        // A double tap shoots a one-timer at once.
+        let (frame, one_timer_speed, flight_speed, _) = shoot(&[0, 3]);
+        assert_eq!(frame, 3);
+        assert!((one_timer_speed - speed(ONE_TIMER_POWER)).abs() < 1.0);
+        assert!(is_flying_at(flight_speed, ONE_TIMER_POWER));
+    }
+
+    #[test]
+    fn test_shot_stats() {
+        let mut simulation = Simulation::new();
+        while simulation.state != GameState::Running {
+            simulation.step(&[]);
+        }
+        // A held slapshot, then a wrist shot.
+        let held = [0]
+            .into_iter()
+            .chain((30..200).step_by(2))
+            .collect::<Vec<usize>>();
+        let mut recorded_speeds = vec![];
+        for presses in [held, vec![0]] {
+            simulation.red_player.after_shooting_counter = 0.0;
+            simulation.puck.possession = Some(GameSide::Red);
+            simulation.puck.attach_to_player(&simulation.red_player);
+            let shots = simulation.red_stats.shots;
+            let mut frame = 0;
+            while simulation.red_stats.shots == shots {
+                if presses.contains(&frame) {
+                    simulation.step(&[(GameSide::Red, KeyCode::Char(' '))]);
+                } else {
+                    simulation.step(&[]);
+                }
+                frame += 1;
+            }
+            // The meter shows the speed the puck flies at, only slowed down by friction since.
+            let recorded_speed = simulation.red_stats.last_shot_speed;
+            simulation.step(&[]);
+            let flight_speed = puck_speed(simulation.puck.velocity);
+            assert!(flight_speed <= recorded_speed && flight_speed > 0.98 * recorded_speed);
+            recorded_speeds.push(recorded_speed);
+        }
+        assert!(recorded_speeds[0] > recorded_speeds[1]);
+        assert!(recorded_speeds[0] > puck_speed((MAX_PUCK_VELOCITY, 0.0)));
+        assert_eq!(simulation.red_stats.shots, 2);
+        assert_eq!(simulation.red_stats.max_puck_speed, recorded_speeds[0]);
+        assert_eq!(simulation.red_stats.last_shot_speed, recorded_speeds[1]);
+    }
+
+    #[test]
+    fn test_simulation_ai_match() {
+        // Plays a seeded match between two AIs and returns the goals of each side.
+        let play = |seed: u64| {
+            let mut simulation = Simulation::with_seed(Restitution::default(), seed);
+            let mut red_ai = Ai::with_seed(3 * seed + 1);
+            let mut blue_ai = Ai::with_seed(3 * seed + 2);
+            let mut goals = (0, 0);
+            while !simulation.is_finished() {
+                let mut inputs = vec![];
+                if simulation.state == GameState::Running {
+                    for (side, ai) in [(GameSide::Red, &mut red_ai), (GameSide::Blue, &mut blue_ai)]
+                    {
+                        if let Some(key_code) =
+                            ai.next_input(&simulation.ai_view(side), MINIMUM_DELTATIME_MILLISECONDS)
+                        {
+                            inputs.push((side, key_code));
+                        }
+                    }
+                }
+                simulation.step(&inputs);
+                for event in simulation.drain_events() {
+                    match event {
+                        GameEvent::Goal {
+                            side: GameSide::Red,
+                        } => goals.0 += 1,
+                        GameEvent::Goal {
+                            side: GameSide::Blue,
+                        } => goals.1 += 1,
+                        GameEvent::Save { .. } => {}
+                    }
+                }
+            }
+
+            let snapshot = simulation.snapshot();
+            assert_eq!(snapshot.remaining_seconds, 0);
+            assert_eq!(snapshot.score, goals);
+            assert!(snapshot.red_stats.shots + snapshot.blue_stats.shots > 0);
+            goals
+        };
+
+        // A match can end 0-0, but the AIs score within a few.
+        assert!((0..5).any(|seed| {
+            let goals = play(seed);
+            goals.0 + goals.1 > 0
+        }));
+    }
+
+    #[test]
+    fn test_quick_chat_throttling() {
+        let mut client = Client::ai();
+        client.send_quick_chat(0);
+        assert_eq!(client.visible_quick_chat(), Some("GG"));
+
+        // Messages sent during the cooldown are dropped.
+        client.send_quick_chat(1);
+        assert_eq!(client.visible_quick_chat(), Some("GG"));
+
+        client.quick_chat = Some((
+            "GG",
+            Instant::now() - time::Duration::from_millis(QUICK_CHAT_COOLDOWN_MILLISECONDS as u64),
+        ));
+        client.send_quick_chat(1);
+        assert_eq!(client.visible_quick_chat(), Some("Nice shot!"));
+
+        client.send_quick_chat(QUICK_CHAT_MESSAGES.len());
+        assert_eq!(client.visible_quick_chat(), Some("Nice shot!"));
+    }
 }
@@ -0,0 +1,132 @@
+use crate::types::AppResult;
+use russh_keys::key::{KeyPair, SignatureHash};
+use serde::Deserialize;
+use ssh_key::{
+    private::{Ed25519Keypair, KeypairData, RsaKeypair, RsaPrivateKey},
+    public::RsaPublicKey,
+    LineEnding, Mpint, PrivateKey,
+};
+use std::{
+    fs::File,
+    io::Read,
+    os::unix::fs::{DirBuilderExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+const RSA_KEY_SIZE: usize = 3072;
+// Where older versions stored the raw ed25519 seed.
+const LEGACY_KEY_PATH: &str = "./keys";
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostKeyAlgorithm {
+    Ed25519,
+    // ECDSA host keys are not supported by russh, so they are not offered here.
+    Rsa,
+}
+
+impl HostKeyAlgorithm {
+    fn file_name(&self) -> &'static str {
+        match self {
+            Self::Ed25519 => "ssh_host_ed25519_key",
+            Self::Rsa => "ssh_host_rsa_key",
+        }
+    }
+
+    fn generate(&self) -> AppResult<PrivateKey> {
+        let mut rng = rand::rngs::OsRng;
+        let key_data = match self {
+            Self::Ed25519 => match load_legacy_key() {
+                Ok(seed) => {
+                    log::info!("Importing legacy host key from {LEGACY_KEY_PATH}");
+                    KeypairData::from(Ed25519Keypair::from_seed(&seed))
+                }
+                Err(_) => KeypairData::from(Ed25519Keypair::random(&mut rng)),
+            },
+            Self::Rsa => KeypairData::from(generate_rsa_keypair()?),
+        };
+        Ok(PrivateKey::new(key_data, "sshattrick host key")?)
+    }
+}
+
+// Pure Rust RSA key generation is very slow in debug builds, so let openssl do it.
+fn generate_rsa_keypair() -> AppResult<RsaKeypair> {
+    let Some(KeyPair::RSA { key, .. }) =
+        KeyPair::generate_rsa(RSA_KEY_SIZE, SignatureHash::SHA2_512)
+    else {
+        return Err("Failed generating RSA key".into());
+    };
+    let mpint = |bytes: Vec<u8>| Mpint::from_positive_bytes(&bytes);
+    Ok(RsaKeypair {
+        public: RsaPublicKey {
+            e: mpint(key.e().to_vec())?,
+            n: mpint(key.n().to_vec())?,
+        },
+        private: RsaPrivateKey {
+            d: mpint(key.d().to_vec())?,
+            iqmp: mpint(key.iqmp().ok_or("Missing RSA parameters")?.to_vec())?,
+            p: mpint(key.p().ok_or("Missing RSA parameters")?.to_vec())?,
+            q: mpint(key.q().ok_or("Missing RSA parameters")?.to_vec())?,
+        },
+    })
+}
+
+/// The data directory of the platform, e.g. `~/.local/share/sshattrick` on Linux.
+pub fn default_host_key_dir() -> PathBuf {
+    directories::ProjectDirs::from("org", "frittura", "sshattrick")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn load_legacy_key() -> AppResult<[u8; 32]> {
+    let mut file = File::open(LEGACY_KEY_PATH)?;
+    let mut seed = [0; 32];
+    file.read_exact(&mut seed)?;
+    Ok(seed)
+}
+
+fn load_host_key(path: &Path) -> AppResult<KeyPair> {
+    let mode = std::fs::metadata(path)?.permissions().mode();
+    if mode & 0o077 != 0 {
+        log::warn!(
+            "Host key {} is accessible by other users (mode {:o})",
+            path.display(),
+            mode & 0o777
+        );
+    }
+    let text = std::fs::read_to_string(path)?;
+    Ok(russh_keys::decode_secret_key(&text, None)?)
+}
+
+/// Loads the host keys from `dir`, generating the missing ones.
+/// New keys are stored in the OpenSSH format and are readable only by the owner.
+pub fn load_or_generate_host_keys(
+    dir: &Path,
+    algorithms: &[HostKeyAlgorithm],
+) -> AppResult<Vec<KeyPair>> {
+    let mut key_pairs = vec![];
+    for algorithm in algorithms {
+        let path = dir.join(algorithm.file_name());
+        if !path.exists() {
+            log::info!("Generating host key {}", path.display());
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)?;
+            algorithm
+                .generate()?
+                .write_openssh_file(&path, LineEnding::LF)?;
+        }
+        key_pairs.push(load_host_key(&path)?);
+    }
+    Ok(key_pairs)
+}
+
+pub fn fingerprint(key_pair: &KeyPair) -> AppResult<String> {
+    let public_key = key_pair.clone_public_key()?;
+    Ok(format!(
+        "{} SHA256:{}",
+        public_key.name(),
+        public_key.fingerprint()
+    ))
+}
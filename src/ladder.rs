@@ -0,0 +1,387 @@
+use crate::{
+    config::ServerConfig,
+    leaderboard::{sort_standings, MatchPlayer, PlayerRecord},
+    types::{AppResult, SystemTimeTick, Tick},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+
+const MILLISECONDS_PER_DAY: u128 = 24 * 60 * 60 * 1000;
+// 1970-01-05 was the first Monday after the epoch.
+const FIRST_MONDAY: i64 = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Champion {
+    pub season: String,
+    pub name: String,
+    pub points: u32,
+    pub games: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LadderData {
+    // Name of the season the records belong to.
+    season: Option<String>,
+    records: HashMap<String, PlayerRecord>,
+    // Most recent season first.
+    champions: Vec<Champion>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Season {
+    name: String,
+    // Days since the epoch, the end is excluded.
+    start: i64,
+    end: i64,
+}
+
+/// What the lobby shows of the ladder.
+#[derive(Debug, Clone, Default)]
+pub struct LadderView {
+    pub season: Option<String>,
+    pub days_left: i64,
+    pub standings: Vec<PlayerRecord>,
+    pub champions: Vec<Champion>,
+}
+
+/// Standings of the players with a key over the current season, which are archived
+/// with their champion when the season ends.
+#[derive(Debug, Default)]
+pub struct Ladder {
+    path: PathBuf,
+    seasons: Vec<Season>,
+    season_length_days: i64,
+    data: LadderData,
+}
+
+impl Ladder {
+    /// Loads the ladder, or returns an empty one if the file does not exist.
+    /// Seasons with invalid dates are skipped.
+    pub fn load(config: &ServerConfig) -> AppResult<Self> {
+        let data = if config.ladder.exists() {
+            let file = File::open(&config.ladder)?;
+            serde_json::from_reader(BufReader::new(file))?
+        } else {
+            LadderData::default()
+        };
+        let seasons = config
+            .seasons
+            .iter()
+            .filter_map(
+                |season| match (parse_date(&season.start), parse_date(&season.end)) {
+                    (Ok(start), Ok(end)) if start < end => Some(Season {
+                        name: season.name.clone(),
+                        start,
+                        end,
+                    }),
+                    _ => {
+                        log::error!("Skipping season {} with invalid dates", season.name);
+                        None
+                    }
+                },
+            )
+            .collect();
+        let mut ladder = Self {
+            path: config.ladder.clone(),
+            seasons,
+            season_length_days: config.season_length_days as i64,
+            data,
+        };
+        ladder.refresh();
+        Ok(ladder)
+    }
+
+    fn save(&self) -> AppResult<()> {
+        let file = File::create(&self.path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &self.data)?;
+        Ok(())
+    }
+
+    /// The configured season running on the given day, or else the recurring one.
+    fn season_on(&self, day: i64) -> Option<Season> {
+        if let Some(season) = self
+            .seasons
+            .iter()
+            .find(|season| season.start <= day && day < season.end)
+        {
+            return Some(season.clone());
+        }
+        if self.season_length_days <= 0 {
+            return None;
+        }
+        let start = day - (day - FIRST_MONDAY).rem_euclid(self.season_length_days);
+        Some(Season {
+            name: format!("Season of {}", format_date(start)),
+            start,
+            end: start + self.season_length_days,
+        })
+    }
+
+    /// Archives the champion of the season that ended, if any, and starts the current one.
+    pub fn refresh(&mut self) {
+        self.refresh_on(today());
+    }
+
+    fn refresh_on(&mut self, day: i64) {
+        let season = self.season_on(day).map(|season| season.name);
+        if season == self.data.season {
+            return;
+        }
+        if let Some(name) = self.data.season.take() {
+            let standings = sort_standings(self.data.records.values().cloned().collect());
+            if let Some(record) = standings.first() {
+                log::info!("{} won {name}", record.name);
+                self.data.champions.insert(
+                    0,
+                    Champion {
+                        season: name,
+                        name: record.name.clone(),
+                        points: record.points(),
+                        games: record.games(),
+                    },
+                );
+            }
+        }
+        self.data.season = season;
+        self.data.records.clear();
+        self.save()
+            .unwrap_or_else(|e| log::error!("Failed to save ladder: {e}"));
+    }
+
+    pub fn record_match(&mut self, red: &MatchPlayer, blue: &MatchPlayer) {
+        self.refresh();
+        if self.data.season.is_none() {
+            return;
+        }
+        self.data
+            .records
+            .entry(red.identity.clone())
+            .or_default()
+            .add_result(&red.name, red.score, blue.score);
+        self.data
+            .records
+            .entry(blue.identity.clone())
+            .or_default()
+            .add_result(&blue.name, blue.score, red.score);
+        self.save()
+            .unwrap_or_else(|e| log::error!("Failed to save ladder: {e}"));
+    }
+
+    pub fn view(&self) -> LadderView {
+        let today = today();
+        LadderView {
+            season: self.data.season.clone(),
+            days_left: self
+                .season_on(today)
+                .map(|season| season.end - today)
+                .unwrap_or_default(),
+            standings: sort_standings(self.data.records.values().cloned().collect()),
+            champions: self.data.champions.clone(),
+        }
+    }
+}
+
+fn today() -> i64 {
+    (Tick::now() / MILLISECONDS_PER_DAY) as i64
+}
+
+/// Parses a YYYY-MM-DD date into days since the epoch.
+fn parse_date(date: &str) -> AppResult<i64> {
+    let parts = date
+        .trim()
+        .split('-')
+        .map(|part| part.parse::<i64>())
+        .collect::<Result<Vec<i64>, _>>()?;
+    let [year, month, day] = parts[..] else {
+        return Err(format!("Invalid date {date}").into());
+    };
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return Err(format!("Invalid date {date}").into());
+    }
+    // Days from civil, counting years from March so that leap days come last.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Ok(era * 146_097 + day_of_era - 719_468)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn format_date(days: i64) -> String {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::SeasonConfig;
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(parse_date("1970-01-01").unwrap(), 0);
+        assert_eq!(parse_date("1970-01-05").unwrap(), FIRST_MONDAY);
+        assert_eq!(format_date(FIRST_MONDAY), "1970-01-05");
+        assert_eq!(parse_date("1969-12-31").unwrap(), -1);
+
+        // Every day around the leap years round trips, and the days follow each other.
+        for (start, end) in [
+            ("1899-12-01", "1900-04-01"),
+            ("1999-12-01", "2000-04-01"),
+            ("2023-12-01", "2025-04-01"),
+        ] {
+            let (start, end) = (parse_date(start).unwrap(), parse_date(end).unwrap());
+            for day in start..end {
+                let date = format_date(day);
+                assert_eq!(parse_date(&date).unwrap(), day, "{date}");
+            }
+        }
+        assert_eq!(
+            parse_date("2024-03-01").unwrap() - parse_date("2024-02-28").unwrap(),
+            2
+        );
+        assert_eq!(
+            parse_date("2023-03-01").unwrap() - parse_date("2023-02-28").unwrap(),
+            1
+        );
+
+        for date in ["2024-02-29", "2000-02-29", " 2024-12-31 "] {
+            assert!(parse_date(date).is_ok(), "{date}");
+        }
+        for date in [
+            "2024-02-31",
+            "2023-02-29",
+            "1900-02-29",
+            "2024-04-31",
+            "2024-13-01",
+            "2024-00-10",
+            "2024-01-00",
+            "2024-01",
+            "2024-01-01-01",
+            "first of may",
+        ] {
+            assert!(parse_date(date).is_err(), "{date}");
+        }
+    }
+
+    fn ladder(name: &str, seasons: Vec<SeasonConfig>) -> Ladder {
+        let path = std::env::temp_dir().join(format!(
+            "sshattrick_ladder_{name}_{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let config = ServerConfig {
+            ladder: path,
+            seasons,
+            season_length_days: 7,
+            ..Default::default()
+        };
+        Ladder::load(&config).unwrap()
+    }
+
+    // Records a game of the season running on the given day.
+    fn play(ladder: &mut Ladder, day: i64, winner: &str) {
+        ladder.refresh_on(day);
+        let loser = if winner == "alice" { "bob" } else { "alice" };
+        for (name, score, against) in [(winner, 2, 1), (loser, 1, 2)] {
+            ladder
+                .data
+                .records
+                .entry(name.to_string())
+                .or_default()
+                .add_result(name, score, against);
+        }
+    }
+
+    #[test]
+    fn test_weekly_seasons() {
+        let mut ladder = ladder("weekly", vec![]);
+        let monday = parse_date("2024-01-08").unwrap();
+
+        // A season starts on a Monday and the Sunday before still belongs to the previous one.
+        ladder.refresh_on(monday - 1);
+        assert_eq!(ladder.data.season.as_deref(), Some("Season of 2024-01-01"));
+        play(&mut ladder, monday - 1, "alice");
+        assert!(ladder.data.champions.is_empty());
+
+        ladder.refresh_on(monday);
+        assert_eq!(ladder.data.season.as_deref(), Some("Season of 2024-01-08"));
+        assert!(ladder.data.records.is_empty());
+        assert_eq!(ladder.data.champions.len(), 1);
+        assert_eq!(ladder.data.champions[0].name, "alice");
+        assert_eq!(ladder.data.champions[0].season, "Season of 2024-01-01");
+
+        // Refreshing during the same season keeps the records.
+        play(&mut ladder, monday + 6, "bob");
+        ladder.refresh_on(monday + 6);
+        assert_eq!(ladder.data.records.len(), 2);
+
+        // A season without games has no champion.
+        ladder.refresh_on(monday + 7);
+        ladder.refresh_on(monday + 14);
+        assert_eq!(ladder.data.champions.len(), 2);
+        assert_eq!(ladder.data.champions[0].name, "bob");
+        let _ = std::fs::remove_file(&ladder.path);
+    }
+
+    #[test]
+    fn test_configured_seasons() {
+        let season = |name: &str, start: &str, end: &str| SeasonConfig {
+            name: name.to_string(),
+            start: start.to_string(),
+            end: end.to_string(),
+        };
+        let mut ladder = ladder(
+            "configured",
+            vec![
+                season("Winter", "2024-01-03", "2024-02-29"),
+                season("Impossible", "2024-02-31", "2024-03-10"),
+                season("Backwards", "2024-05-01", "2024-04-01"),
+            ],
+        );
+        assert_eq!(ladder.seasons.len(), 1);
+
+        // The configured season runs from its start included to its end excluded,
+        // and the weekly seasons fill the rest.
+        let start = parse_date("2024-01-03").unwrap();
+        let end = parse_date("2024-02-29").unwrap();
+        ladder.refresh_on(start - 1);
+        assert_eq!(ladder.data.season.as_deref(), Some("Season of 2024-01-01"));
+        ladder.refresh_on(start);
+        assert_eq!(ladder.data.season.as_deref(), Some("Winter"));
+        play(&mut ladder, end - 1, "bob");
+        ladder.refresh_on(end);
+        assert_eq!(ladder.data.season.as_deref(), Some("Season of 2024-02-26"));
+        assert_eq!(ladder.data.champions[0].season, "Winter");
+        assert_eq!(ladder.data.champions[0].name, "bob");
+        let _ = std::fs::remove_file(&ladder.path);
+    }
+}
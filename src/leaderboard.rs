@@ -0,0 +1,204 @@
+use crate::{career::CareerStats, game::MatchStats, types::AppResult};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerRecord {
+    pub name: String,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub goals_for: u32,
+    pub goals_against: u32,
+}
+
+impl PlayerRecord {
+    pub fn points(&self) -> u32 {
+        3 * self.wins + self.draws
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn goal_difference(&self) -> i64 {
+        self.goals_for as i64 - self.goals_against as i64
+    }
+
+    pub fn add_result(&mut self, name: &str, goals_for: u8, goals_against: u8) {
+        self.name = name.to_string();
+        self.goals_for += goals_for as u32;
+        self.goals_against += goals_against as u32;
+        if goals_for > goals_against {
+            self.wins += 1;
+        } else if goals_for < goals_against {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+}
+
+/// A player taking part in a match, identified by the fingerprint of their key.
+#[derive(Debug, Clone)]
+pub struct MatchPlayer {
+    pub identity: String,
+    pub name: String,
+    pub score: u8,
+    pub stats: MatchStats,
+}
+
+/// Results and statistics of the completed matches between players with a known identity.
+#[derive(Debug, Default)]
+pub struct Leaderboard {
+    path: PathBuf,
+    records: HashMap<String, CareerStats>,
+}
+
+impl Leaderboard {
+    /// Loads the leaderboard, or returns an empty one if the file does not exist.
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let records = if path.exists() {
+            let file = File::open(path)?;
+            serde_json::from_reader(BufReader::new(file))?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            records,
+        })
+    }
+
+    fn save(&self) -> AppResult<()> {
+        let file = File::create(&self.path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &self.records)?;
+        Ok(())
+    }
+
+    pub fn record_match(&mut self, red: &MatchPlayer, blue: &MatchPlayer) {
+        self.records
+            .entry(red.identity.clone())
+            .or_default()
+            .add_match(&red.name, red.score, blue.score, &red.stats);
+        self.records
+            .entry(blue.identity.clone())
+            .or_default()
+            .add_match(&blue.name, blue.score, red.score, &blue.stats);
+        self.save()
+            .unwrap_or_else(|e| log::error!("Failed to save leaderboard: {e}"));
+    }
+
+    /// Records sorted by points, then goal difference.
+    pub fn standings(&self) -> Vec<PlayerRecord> {
+        sort_standings(
+            self.records
+                .values()
+                .map(|career| career.record.clone())
+                .collect(),
+        )
+    }
+
+    pub fn career(&self, identity: &str) -> Option<&CareerStats> {
+        self.records.get(identity)
+    }
+}
+
+pub fn sort_standings(mut records: Vec<PlayerRecord>) -> Vec<PlayerRecord> {
+    records.sort_by(|a, b| {
+        b.points()
+            .cmp(&a.points())
+            .then(b.goal_difference().cmp(&a.goal_difference()))
+            .then(a.name.cmp(&b.name))
+    });
+    records
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn player(identity: &str, score: u8, shots: u32) -> MatchPlayer {
+        MatchPlayer {
+            identity: identity.to_string(),
+            name: identity.to_uppercase(),
+            score,
+            stats: MatchStats {
+                shots,
+                shots_on_target: score as u32,
+                possession_milliseconds: 1500.0,
+                max_puck_speed: 100.0 * shots as f32,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_sort_standings() {
+        let record = |name: &str, wins, draws, goals_for, goals_against| PlayerRecord {
+            name: name.to_string(),
+            wins,
+            draws,
+            losses: 1,
+            goals_for,
+            goals_against,
+        };
+        let standings = sort_standings(vec![
+            record("d", 1, 0, 5, 5),
+            record("c", 0, 3, 9, 2),
+            record("b", 1, 0, 4, 2),
+            record("a", 1, 0, 4, 2),
+            record("e", 2, 0, 1, 8),
+        ]);
+        // By points, then goal difference, then name.
+        let names = standings
+            .iter()
+            .map(|record| record.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, ["e", "c", "a", "b", "d"]);
+        assert_eq!(standings[0].points(), 6);
+        assert_eq!(standings[0].goal_difference(), -7);
+        assert_eq!(standings[1].games(), 4);
+        assert!(sort_standings(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_leaderboard_careers() -> AppResult<()> {
+        let path = std::env::temp_dir().join(format!(
+            "sshattrick_leaderboard_{}.json",
+            std::process::id()
+        ));
+        // A leaderboard saved before the career statistics.
+        std::fs::write(
+            &path,
+            r#"{ "a": { "name": "A", "wins": 1, "goals_for": 2, "goals_against": 1 } }"#,
+        )?;
+
+        let mut leaderboard = Leaderboard::load(&path)?;
+        leaderboard.record_match(&player("a", 1, 4), &player("b", 1, 2));
+        leaderboard.record_match(&player("b", 3, 5), &player("a", 0, 1));
+
+        let leaderboard = Leaderboard::load(&path)?;
+        let _ = std::fs::remove_file(&path);
+        let a = leaderboard.career("a").expect("a has a career");
+        assert_eq!((a.record.wins, a.record.draws, a.record.losses), (1, 1, 1));
+        assert_eq!((a.record.goals_for, a.record.goals_against), (3, 5));
+        assert_eq!((a.shots, a.shots_on_target), (5, 1));
+        assert_eq!(a.possession_seconds, 3.0);
+        assert_eq!(a.max_puck_speed, 400.0);
+        assert_eq!(a.shot_accuracy(), 20.0);
+
+        let standings = leaderboard.standings();
+        assert_eq!(standings[0].name, "B");
+        assert_eq!(standings[0].points(), 4);
+        assert_eq!(standings[1].points(), 4);
+        assert!(leaderboard.career("c").is_none());
+        Ok(())
+    }
+}
@@ -1,5 +1,21 @@
+pub mod access;
+pub mod admin;
+pub mod ai;
+pub mod batch;
 pub mod big_text;
+pub mod bot;
+pub mod career;
+pub mod chat;
+pub mod config;
+pub mod events;
 pub mod game;
+pub mod keys;
+pub mod ladder;
+pub mod leaderboard;
+pub mod lobby;
+pub mod metrics;
+pub mod script;
 pub mod server;
+pub mod tournament;
 pub mod types;
 pub mod utils;
@@ -0,0 +1,1207 @@
+use crate::{
+    admin::{BotSummary, GameSummary, ServerSnapshot},
+    big_text::title,
+    career::CareerStats,
+    chat::{ChatMessage, MAX_MESSAGE_LENGTH},
+    game::Palette,
+    ladder::LadderView,
+    leaderboard::PlayerRecord,
+    tournament::{
+        Pairing, Tournament, TournamentFormat, TournamentState, MAX_TOURNAMENT_PLAYERS,
+        MIN_TOURNAMENT_PLAYERS,
+    },
+    types::{AppResult, SshTerminal},
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListState, Paragraph, Row, Table, TableState},
+};
+use std::{collections::VecDeque, time::Instant};
+
+const REDRAW_INTERVAL_MILLISECONDS: u128 = 200;
+const MAX_NAME_LENGTH: usize = 16;
+pub const ROOM_CODE_LENGTH: usize = 4;
+const LEADERBOARD_LENGTH: usize = 20;
+const CHAT_WIDTH: u16 = 48;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MenuItem {
+    QuickMatch,
+    PrivateRoom,
+    VersusAi,
+    Tournaments,
+    Spectate,
+    Leaderboard,
+    Profile,
+    Settings,
+    Quit,
+}
+
+const MENU_ITEMS: [MenuItem; 9] = [
+    MenuItem::QuickMatch,
+    MenuItem::PrivateRoom,
+    MenuItem::VersusAi,
+    MenuItem::Tournaments,
+    MenuItem::Spectate,
+    MenuItem::Leaderboard,
+    MenuItem::Profile,
+    MenuItem::Settings,
+    MenuItem::Quit,
+];
+
+impl MenuItem {
+    fn label(&self) -> &'static str {
+        match self {
+            MenuItem::QuickMatch => "Quick match",
+            MenuItem::PrivateRoom => "Private room",
+            MenuItem::VersusAi => "Play against the AI",
+            MenuItem::Tournaments => "Tournaments",
+            MenuItem::Spectate => "Spectate",
+            MenuItem::Leaderboard => "Leaderboard",
+            MenuItem::Profile => "Profile",
+            MenuItem::Settings => "Settings",
+            MenuItem::Quit => "Quit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LobbyAction {
+    QuickMatch,
+    CreateRoom,
+    JoinRoom(String),
+    PlayAi,
+    PlayScript(String),
+    // Challenges the waiting bot with the given id.
+    PlayBot(usize),
+    Spectate(uuid::Uuid),
+    CreateTournament {
+        format: TournamentFormat,
+        size: usize,
+    },
+    // Joins the tournament with the given id, or leaves it if already registered.
+    ToggleRegistration(usize),
+    ShowProfile,
+    CancelWaiting,
+    Chat(String),
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ranking {
+    AllTime,
+    Season,
+    Champions,
+}
+
+impl Ranking {
+    fn next(&self) -> Self {
+        match self {
+            Ranking::AllTime => Ranking::Season,
+            Ranking::Season => Ranking::Champions,
+            Ranking::Champions => Ranking::AllTime,
+        }
+    }
+
+    fn previous(&self) -> Self {
+        match self {
+            Ranking::AllTime => Ranking::Champions,
+            Ranking::Season => Ranking::AllTime,
+            Ranking::Champions => Ranking::Season,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SettingsField {
+    Name,
+    Palette,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Screen {
+    Menu,
+    // Waiting for an opponent, in the queue or in a private room.
+    Waiting {
+        since: Instant,
+        room: Option<String>,
+    },
+    PrivateRoom {
+        code: String,
+    },
+    // Picking between the built-in AI, the scripts and the connected bots.
+    Opponents,
+    Spectate,
+    Tournaments,
+    NewTournament {
+        format: TournamentFormat,
+        size: usize,
+    },
+    Leaderboard {
+        ranking: Ranking,
+    },
+    Profile,
+    Settings {
+        field: SettingsField,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub name: String,
+    pub palette: Palette,
+}
+
+pub struct Lobby {
+    terminal: SshTerminal,
+    screen: Screen,
+    menu_state: ListState,
+    games_state: TableState,
+    // Games listed in the spectate screen when it was last drawn.
+    games: Vec<GameSummary>,
+    opponents_state: TableState,
+    // Scripts and bots listed in the opponents screen when it was last drawn.
+    ai_scripts: Vec<String>,
+    bots: Vec<BotSummary>,
+    tournaments_state: TableState,
+    // Tournaments listed when the lobby was last drawn.
+    tournaments: Vec<Tournament>,
+    // Career statistics of the player, fetched when opening the profile.
+    profile: Option<CareerStats>,
+    settings: Settings,
+    // The lobby is hidden while playing or spectating.
+    is_active: bool,
+    is_dirty: bool,
+    // Clearing is left to the next draw, so that input handlers never write to the terminal.
+    needs_clear: bool,
+    status: String,
+    // Some while the player is typing a chat message.
+    chat_input: Option<String>,
+    // How many lines the chat is scrolled up from the latest message.
+    chat_scroll: usize,
+    last_draw: Instant,
+}
+
+impl Lobby {
+    pub fn new(terminal: SshTerminal, name: &str) -> Self {
+        let name = name
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(MAX_NAME_LENGTH)
+            .collect::<String>();
+        Self {
+            terminal,
+            screen: Screen::Menu,
+            menu_state: ListState::default().with_selected(Some(0)),
+            games_state: TableState::default().with_selected(Some(0)),
+            games: vec![],
+            opponents_state: TableState::default().with_selected(Some(0)),
+            ai_scripts: vec![],
+            bots: vec![],
+            tournaments_state: TableState::default().with_selected(Some(0)),
+            tournaments: vec![],
+            profile: None,
+            settings: Settings {
+                name: if name.is_empty() {
+                    "Player".to_string()
+                } else {
+                    name
+                },
+                palette: Palette::default(),
+            },
+            is_active: true,
+            is_dirty: true,
+            needs_clear: false,
+            status: String::new(),
+            chat_input: None,
+            chat_scroll: 0,
+            last_draw: Instant::now(),
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Hides the lobby when the client starts playing or spectating.
+    pub fn pause(&mut self) {
+        self.is_active = false;
+        self.screen = Screen::Menu;
+        self.status.clear();
+        self.chat_input = None;
+    }
+
+    /// Shows the lobby again, redrawing it from scratch since the game drew over it.
+    pub fn resume(&mut self, status: impl Into<String>) {
+        self.is_active = true;
+        self.screen = Screen::Menu;
+        self.status = status.into();
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.needs_clear = true;
+        self.is_dirty = true;
+    }
+
+    pub fn wait(&mut self, room: Option<String>) {
+        self.screen = Screen::Waiting {
+            since: Instant::now(),
+            room,
+        };
+        self.status.clear();
+        self.is_dirty = true;
+    }
+
+    pub fn stop_waiting(&mut self, status: impl Into<String>) {
+        self.screen = Screen::Menu;
+        self.set_status(status);
+    }
+
+    pub fn show_profile(&mut self, profile: Option<CareerStats>) {
+        self.profile = profile;
+        self.is_dirty = true;
+    }
+
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = status.into();
+        self.is_dirty = true;
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        self.is_active
+            && (self.is_dirty
+                || self.last_draw.elapsed().as_millis() >= REDRAW_INTERVAL_MILLISECONDS)
+    }
+
+    pub fn handle_input(&mut self, key_code: KeyCode) -> Option<LobbyAction> {
+        self.is_dirty = true;
+        if let Some(input) = self.chat_input.as_mut() {
+            match key_code {
+                KeyCode::Esc | KeyCode::Tab => self.chat_input = None,
+                KeyCode::Enter => {
+                    let text = std::mem::take(input);
+                    if !text.trim().is_empty() {
+                        self.chat_scroll = 0;
+                        return Some(LobbyAction::Chat(text.trim().to_string()));
+                    }
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c)
+                    if !c.is_control() && input.chars().count() < MAX_MESSAGE_LENGTH =>
+                {
+                    input.push(c)
+                }
+                _ => {}
+            }
+            return None;
+        }
+
+        // The settings and private room screens use the keyboard for their own input.
+        let is_typing = matches!(
+            self.screen,
+            Screen::PrivateRoom { .. } | Screen::NewTournament { .. } | Screen::Settings { .. }
+        );
+        match key_code {
+            KeyCode::Tab if !is_typing => {
+                self.chat_input = Some(String::new());
+                return None;
+            }
+            KeyCode::PageUp => {
+                self.chat_scroll += 1;
+                return None;
+            }
+            KeyCode::PageDown => {
+                self.chat_scroll = self.chat_scroll.saturating_sub(1);
+                return None;
+            }
+            _ => {}
+        }
+
+        match &mut self.screen {
+            Screen::Menu => match key_code {
+                KeyCode::Up => {
+                    let selected = self.menu_state.selected().unwrap_or(0);
+                    self.menu_state.select(Some(selected.saturating_sub(1)));
+                }
+                KeyCode::Down => {
+                    let selected = self.menu_state.selected().unwrap_or(0);
+                    self.menu_state
+                        .select(Some((selected + 1).min(MENU_ITEMS.len() - 1)));
+                }
+                KeyCode::Enter => {
+                    self.status.clear();
+                    let item = MENU_ITEMS[self.menu_state.selected().unwrap_or(0)];
+                    match item {
+                        MenuItem::QuickMatch => return Some(LobbyAction::QuickMatch),
+                        MenuItem::PrivateRoom => {
+                            self.screen = Screen::PrivateRoom {
+                                code: String::new(),
+                            }
+                        }
+                        MenuItem::VersusAi
+                            if self.ai_scripts.is_empty() && self.bots.is_empty() =>
+                        {
+                            return Some(LobbyAction::PlayAi)
+                        }
+                        MenuItem::VersusAi => self.screen = Screen::Opponents,
+                        MenuItem::Tournaments => self.screen = Screen::Tournaments,
+                        MenuItem::Spectate => self.screen = Screen::Spectate,
+                        MenuItem::Leaderboard => {
+                            self.screen = Screen::Leaderboard {
+                                ranking: Ranking::AllTime,
+                            }
+                        }
+                        MenuItem::Profile => {
+                            self.screen = Screen::Profile;
+                            return Some(LobbyAction::ShowProfile);
+                        }
+                        MenuItem::Settings => {
+                            self.screen = Screen::Settings {
+                                field: SettingsField::Name,
+                            }
+                        }
+                        MenuItem::Quit => return Some(LobbyAction::Quit),
+                    }
+                }
+                KeyCode::Esc => return Some(LobbyAction::Quit),
+                _ => {}
+            },
+            Screen::Waiting { .. } => {
+                if key_code == KeyCode::Esc {
+                    self.screen = Screen::Menu;
+                    return Some(LobbyAction::CancelWaiting);
+                }
+            }
+            Screen::PrivateRoom { code } => match key_code {
+                KeyCode::Esc => self.screen = Screen::Menu,
+                KeyCode::Enter => {
+                    if code.is_empty() {
+                        return Some(LobbyAction::CreateRoom);
+                    }
+                    if code.len() != ROOM_CODE_LENGTH {
+                        self.status = format!("Room codes have {ROOM_CODE_LENGTH} characters");
+                        return None;
+                    }
+                    return Some(LobbyAction::JoinRoom(code.clone()));
+                }
+                KeyCode::Backspace => {
+                    code.pop();
+                }
+                KeyCode::Char(c) if c.is_ascii_alphanumeric() && code.len() < ROOM_CODE_LENGTH => {
+                    code.push(c.to_ascii_uppercase());
+                }
+                _ => {}
+            },
+            Screen::Opponents => match key_code {
+                KeyCode::Esc => self.screen = Screen::Menu,
+                KeyCode::Up => {
+                    let selected = self.opponents_state.selected().unwrap_or(0);
+                    self.opponents_state
+                        .select(Some(selected.saturating_sub(1)));
+                }
+                KeyCode::Down => {
+                    let selected = self.opponents_state.selected().unwrap_or(0);
+                    // The built-in AI comes first, then the scripts and the bots.
+                    self.opponents_state.select(Some(
+                        (selected + 1).min(self.ai_scripts.len() + self.bots.len()),
+                    ));
+                }
+                KeyCode::Enter => {
+                    let selected = self.opponents_state.selected().unwrap_or(0);
+                    if selected == 0 {
+                        return Some(LobbyAction::PlayAi);
+                    }
+                    if let Some(name) = self.ai_scripts.get(selected - 1) {
+                        return Some(LobbyAction::PlayScript(name.clone()));
+                    }
+                    if let Some(bot) = self.bots.get(selected - 1 - self.ai_scripts.len()) {
+                        return Some(LobbyAction::PlayBot(bot.id));
+                    }
+                }
+                _ => {}
+            },
+            Screen::Spectate => match key_code {
+                KeyCode::Esc => self.screen = Screen::Menu,
+                KeyCode::Up => {
+                    let selected = self.games_state.selected().unwrap_or(0);
+                    self.games_state.select(Some(selected.saturating_sub(1)));
+                }
+                KeyCode::Down => {
+                    let selected = self.games_state.selected().unwrap_or(0);
+                    self.games_state
+                        .select(Some((selected + 1).min(self.games.len().saturating_sub(1))));
+                }
+                KeyCode::Enter => {
+                    if let Some(game) = self
+                        .games_state
+                        .selected()
+                        .and_then(|idx| self.games.get(idx))
+                    {
+                        return Some(LobbyAction::Spectate(game.id));
+                    }
+                }
+                _ => {}
+            },
+            Screen::Tournaments => match key_code {
+                KeyCode::Esc => self.screen = Screen::Menu,
+                KeyCode::Up => {
+                    let selected = self.tournaments_state.selected().unwrap_or(0);
+                    self.tournaments_state
+                        .select(Some(selected.saturating_sub(1)));
+                }
+                KeyCode::Down => {
+                    let selected = self.tournaments_state.selected().unwrap_or(0);
+                    self.tournaments_state.select(Some(
+                        (selected + 1).min(self.tournaments.len().saturating_sub(1)),
+                    ));
+                }
+                KeyCode::Enter => {
+                    if let Some(tournament) = self
+                        .tournaments_state
+                        .selected()
+                        .and_then(|idx| self.tournaments.get(idx))
+                    {
+                        return Some(LobbyAction::ToggleRegistration(tournament.id));
+                    }
+                }
+                KeyCode::Char('n') => {
+                    self.screen = Screen::NewTournament {
+                        format: TournamentFormat::SingleElimination,
+                        size: 4,
+                    }
+                }
+                _ => {}
+            },
+            Screen::NewTournament { format, size } => match key_code {
+                KeyCode::Esc => self.screen = Screen::Tournaments,
+                KeyCode::Up | KeyCode::Down | KeyCode::Tab => *format = format.next(),
+                KeyCode::Left => *size = size.saturating_sub(1).max(MIN_TOURNAMENT_PLAYERS),
+                KeyCode::Right => *size = (*size + 1).min(MAX_TOURNAMENT_PLAYERS),
+                KeyCode::Enter => {
+                    let action = LobbyAction::CreateTournament {
+                        format: *format,
+                        size: *size,
+                    };
+                    self.screen = Screen::Tournaments;
+                    return Some(action);
+                }
+                _ => {}
+            },
+            Screen::Leaderboard { ranking } => match key_code {
+                KeyCode::Esc | KeyCode::Enter => self.screen = Screen::Menu,
+                KeyCode::Left => *ranking = ranking.previous(),
+                KeyCode::Right => *ranking = ranking.next(),
+                _ => {}
+            },
+            Screen::Profile => {
+                if matches!(key_code, KeyCode::Esc | KeyCode::Enter) {
+                    self.screen = Screen::Menu;
+                }
+            }
+            Screen::Settings { field } => match (key_code, *field) {
+                (KeyCode::Esc | KeyCode::Enter, _) => self.screen = Screen::Menu,
+                (KeyCode::Up | KeyCode::Down | KeyCode::Tab, _) => {
+                    *field = match field {
+                        SettingsField::Name => SettingsField::Palette,
+                        SettingsField::Palette => SettingsField::Name,
+                    }
+                }
+                (KeyCode::Backspace, SettingsField::Name) => {
+                    self.settings.name.pop();
+                }
+                (KeyCode::Char(c), SettingsField::Name)
+                    if !c.is_control() && self.settings.name.chars().count() < MAX_NAME_LENGTH =>
+                {
+                    self.settings.name.push(c);
+                }
+                (KeyCode::Left, SettingsField::Palette) => {
+                    self.settings.palette = self.settings.palette.previous();
+                }
+                (KeyCode::Right, SettingsField::Palette) => {
+                    self.settings.palette = self.settings.palette.next();
+                }
+                _ => {}
+            },
+        }
+        None
+    }
+
+    pub fn draw(
+        &mut self,
+        snapshot: &ServerSnapshot,
+        standings: &[PlayerRecord],
+        chat: &VecDeque<ChatMessage>,
+        tournaments: &[Tournament],
+        ladder: &LadderView,
+    ) -> AppResult<()> {
+        self.last_draw = Instant::now();
+        self.is_dirty = false;
+        self.games = snapshot.games.clone();
+        self.ai_scripts = snapshot.ai_scripts.clone();
+        self.bots = snapshot.bots.clone();
+        self.tournaments = tournaments.to_vec();
+        if self.needs_clear {
+            // Overwrite every cell in a single flush, like the game does when clearing a client.
+            // A background the lobby never uses makes every cell differ from the last frame.
+            self.terminal.draw(|frame| {
+                let area = frame.size();
+                let lines = vec![Line::from(" ".repeat(area.width.into())); area.height.into()];
+                frame.render_widget(
+                    Paragraph::new(lines).style(Style::default().fg(Color::White).bg(Color::Black)),
+                    area,
+                );
+            })?;
+            self.needs_clear = false;
+        }
+        if self.settings.name.trim().is_empty() {
+            self.settings.name = "Player".to_string();
+        }
+
+        let screen = &self.screen;
+        let menu_state = &mut self.menu_state;
+        let games_state = &mut self.games_state;
+        let games = &self.games;
+        let opponents_state = &mut self.opponents_state;
+        let ai_scripts = &self.ai_scripts;
+        let bots = &self.bots;
+        let tournaments_state = &mut self.tournaments_state;
+        let tournaments = &self.tournaments;
+        let profile = &self.profile;
+        let settings = &self.settings;
+        let status = &self.status;
+        let chat_input = &self.chat_input;
+        let chat_scroll = &mut self.chat_scroll;
+
+        self.terminal.draw(|frame| {
+            let split = Layout::vertical([
+                Constraint::Length(2),
+                Constraint::Length(6),
+                Constraint::Length(1),
+                Constraint::Length(2),
+                Constraint::Min(10),
+                Constraint::Length(3),
+            ])
+            .split(frame.size());
+
+            frame.render_widget(title(Color::Red, Color::Blue), split[1]);
+            frame.render_widget(
+                Paragraph::new(format!(
+                    "Hockey in the terminal via ssh - {} online, {} games running",
+                    snapshot.clients.len(),
+                    snapshot.games.len()
+                ))
+                .centered(),
+                split[2],
+            );
+
+            let [main, chat_area] =
+                Layout::horizontal([Constraint::Min(1), Constraint::Length(CHAT_WIDTH)])
+                    .areas(split[4]);
+            let [content] = Layout::horizontal([Constraint::Length(80)])
+                .flex(Flex::Center)
+                .areas(main);
+            *chat_scroll = render_chat(frame, chat_area, chat, chat_input, *chat_scroll);
+
+            let help = match screen {
+                Screen::Menu => {
+                    let items = MENU_ITEMS
+                        .iter()
+                        .map(|item| Line::from(item.label()).centered())
+                        .collect::<Vec<Line>>();
+                    let [menu] = Layout::horizontal([Constraint::Length(30)])
+                        .flex(Flex::Center)
+                        .areas(content);
+                    let list = List::new(items)
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .title(format!("Welcome, {}", settings.name)),
+                        )
+                        .highlight_style(Style::default().bg(Color::DarkGray).bold());
+                    frame.render_stateful_widget(
+                        list,
+                        Rect {
+                            height: MENU_ITEMS.len() as u16 + 2,
+                            ..menu
+                        },
+                        menu_state,
+                    );
+                    "↑ ↓: select  Enter: confirm  Esc: quit"
+                }
+                Screen::Waiting { since, room } => {
+                    let mut lines = vec![Line::from(format!(
+                        "Waiting for an opponent... {}s",
+                        since.elapsed().as_secs()
+                    ))];
+                    if let Some(code) = room {
+                        lines.push(Line::from(""));
+                        lines.push(Line::from(format!("Room code: {code}")).bold().yellow());
+                        lines.push(Line::from("Share it with a friend to play together."));
+                    }
+                    frame.render_widget(
+                        Paragraph::new(lines)
+                            .centered()
+                            .block(Block::default().borders(Borders::ALL).title("Matchmaking")),
+                        Rect {
+                            height: 6,
+                            ..content
+                        },
+                    );
+                    "Esc: cancel"
+                }
+                Screen::PrivateRoom { code } => {
+                    frame.render_widget(
+                        Paragraph::new(vec![
+                            Line::from("Press Enter to create a room and get a code for a friend,"),
+                            Line::from("or type the code of a friend's room to join it."),
+                            Line::from(""),
+                            Line::from(format!("Room code: {code}_")).bold(),
+                        ])
+                        .centered()
+                        .block(Block::default().borders(Borders::ALL).title("Private room")),
+                        Rect {
+                            height: 6,
+                            ..content
+                        },
+                    );
+                    "Enter: create or join  Esc: back"
+                }
+                Screen::Opponents => {
+                    let rows = std::iter::once(Row::new(vec!["AI", "Built-in"]))
+                        .chain(
+                            ai_scripts
+                                .iter()
+                                .map(|name| Row::new(vec![name.as_str(), "Script"])),
+                        )
+                        .chain(
+                            bots.iter()
+                                .map(|bot| Row::new(vec![bot.name.as_str(), "Bot"])),
+                        );
+                    let table = Table::new(rows, [Constraint::Length(20), Constraint::Min(10)])
+                        .header(Row::new(vec!["Name", "Kind"]).bold())
+                        .highlight_style(Style::default().bg(Color::DarkGray))
+                        .block(Block::default().borders(Borders::ALL).title("Opponents"));
+                    frame.render_stateful_widget(table, content, opponents_state);
+                    "↑ ↓: select  Enter: play  Esc: back"
+                }
+                Screen::Spectate => {
+                    let rows = games.iter().map(|game| {
+                        Row::new(vec![
+                            game.names.0.clone(),
+                            format!("{} - {}", game.score.0, game.score.1),
+                            game.names.1.clone(),
+                            format!(
+                                "{}:{:02}",
+                                game.remaining_seconds / 60,
+                                game.remaining_seconds % 60
+                            ),
+                        ])
+                    });
+                    let table = Table::new(
+                        rows,
+                        [
+                            Constraint::Length(20),
+                            Constraint::Length(9),
+                            Constraint::Length(20),
+                            Constraint::Min(6),
+                        ],
+                    )
+                    .header(Row::new(vec!["Red", "Score", "Blue", "Time"]).bold())
+                    .highlight_style(Style::default().bg(Color::DarkGray))
+                    .block(Block::default().borders(Borders::ALL).title("Games"));
+                    if games.is_empty() {
+                        frame.render_widget(
+                            Paragraph::new("No games are being played right now.")
+                                .centered()
+                                .block(Block::default().borders(Borders::ALL).title("Games")),
+                            Rect {
+                                height: 3,
+                                ..content
+                            },
+                        );
+                    } else {
+                        frame.render_stateful_widget(table, content, games_state);
+                    }
+                    "↑ ↓: select  Enter: watch  Esc: back"
+                }
+                Screen::Tournaments => {
+                    if tournaments.is_empty() {
+                        frame.render_widget(
+                            Paragraph::new("No tournaments yet, press n to create one.")
+                                .centered()
+                                .block(Block::default().borders(Borders::ALL).title("Tournaments")),
+                            Rect {
+                                height: 3,
+                                ..content
+                            },
+                        );
+                    } else {
+                        let [list_area, bracket_area] = Layout::vertical([
+                            Constraint::Length(tournaments.len().min(4) as u16 + 3),
+                            Constraint::Min(1),
+                        ])
+                        .areas(content);
+                        let rows = tournaments.iter().map(|tournament| {
+                            let state = match tournament.state() {
+                                TournamentState::Registering => "registering".to_string(),
+                                TournamentState::Running => "running".to_string(),
+                                TournamentState::Finished { .. } => format!(
+                                    "won by {}",
+                                    tournament
+                                        .champion()
+                                        .map(|entrant| entrant.name.as_str())
+                                        .unwrap_or_default()
+                                ),
+                            };
+                            Row::new(vec![
+                                tournament.name(),
+                                tournament.format.name().to_string(),
+                                format!("{}/{}", tournament.entrants().len(), tournament.size),
+                                state,
+                            ])
+                        });
+                        let table = Table::new(
+                            rows,
+                            [
+                                Constraint::Length(16),
+                                Constraint::Length(20),
+                                Constraint::Length(9),
+                                Constraint::Min(10),
+                            ],
+                        )
+                        .header(Row::new(vec!["Name", "Format", "Players", "State"]).bold())
+                        .highlight_style(Style::default().bg(Color::DarkGray))
+                        .block(Block::default().borders(Borders::ALL).title("Tournaments"));
+                        frame.render_stateful_widget(table, list_area, tournaments_state);
+
+                        if let Some(tournament) = tournaments_state
+                            .selected()
+                            .and_then(|idx| tournaments.get(idx))
+                        {
+                            render_tournament(frame, bracket_area, tournament, games);
+                        }
+                    }
+                    "↑ ↓: select  Enter: join or leave  n: new tournament  Esc: back"
+                }
+                Screen::NewTournament { format, size } => {
+                    frame.render_widget(
+                        Paragraph::new(vec![
+                            Line::from(format!("Format: {}", format.name())),
+                            Line::from(format!("Players: ← {size} →")),
+                            Line::from(""),
+                            Line::from("The tournament starts when all the players have joined."),
+                            Line::from("Only players with an ssh key can take part."),
+                        ])
+                        .centered()
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .title("New tournament"),
+                        ),
+                        Rect {
+                            height: 7,
+                            ..content
+                        },
+                    );
+                    "↑ ↓: change format  ← →: change players  Enter: create  Esc: back"
+                }
+                Screen::Leaderboard { ranking } => {
+                    match ranking {
+                        Ranking::AllTime => frame.render_widget(
+                            standings_table(standings, "All time (players with an ssh key)"),
+                            content,
+                        ),
+                        Ranking::Season => {
+                            let title = match &ladder.season {
+                                Some(season) => format!(
+                                    "{season} ({} day{} left)",
+                                    ladder.days_left,
+                                    if ladder.days_left == 1 { "" } else { "s" }
+                                ),
+                                None => "No season is running".to_string(),
+                            };
+                            frame
+                                .render_widget(standings_table(&ladder.standings, &title), content);
+                        }
+                        Ranking::Champions => {
+                            let rows = ladder.champions.iter().map(|champion| {
+                                Row::new(vec![
+                                    champion.season.clone(),
+                                    champion.name.clone(),
+                                    champion.points.to_string(),
+                                    champion.games.to_string(),
+                                ])
+                            });
+                            let table = Table::new(
+                                rows,
+                                [
+                                    Constraint::Length(30),
+                                    Constraint::Length(18),
+                                    Constraint::Length(7),
+                                    Constraint::Min(5),
+                                ],
+                            )
+                            .header(Row::new(vec!["Season", "Champion", "Points", "Games"]).bold())
+                            .block(
+                                Block::default()
+                                    .borders(Borders::ALL)
+                                    .title("Past season champions"),
+                            );
+                            frame.render_widget(table, content);
+                        }
+                    }
+                    "← →: all time, season or champions  Esc: back"
+                }
+                Screen::Profile => {
+                    let lines = match profile {
+                        Some(stats) => {
+                            let games = stats.record.games().max(1) as f64;
+                            vec![
+                                Line::from(stats.record.name.clone()).bold(),
+                                Line::from(""),
+                                Line::from(format!(
+                                    "Games: {}  ({} won, {} drawn, {} lost)",
+                                    stats.record.games(),
+                                    stats.record.wins,
+                                    stats.record.draws,
+                                    stats.record.losses
+                                )),
+                                Line::from(format!(
+                                    "Goals: {} scored, {} conceded",
+                                    stats.record.goals_for, stats.record.goals_against
+                                )),
+                                Line::from(format!(
+                                    "Shots: {}  ({} on target, {:.0}%)",
+                                    stats.shots,
+                                    stats.shots_on_target,
+                                    stats.shot_accuracy()
+                                )),
+                                Line::from(format!(
+                                    "Possession: {:.0}s per game",
+                                    stats.possession_seconds / games
+                                )),
+                                Line::from(format!("Steals: {}", stats.steals)),
+                                Line::from(format!("Saves: {}", stats.saves)),
+                                Line::from(format!(
+                                    "Fastest shot: {:.0} px/s",
+                                    stats.max_puck_speed
+                                )),
+                            ]
+                        }
+                        None => vec![
+                            Line::from(""),
+                            Line::from("No statistics yet."),
+                            Line::from("Complete a match against another player with your ssh key"),
+                            Line::from("to start your career."),
+                        ],
+                    };
+                    frame.render_widget(
+                        Paragraph::new(lines)
+                            .centered()
+                            .block(Block::default().borders(Borders::ALL).title("Profile")),
+                        Rect {
+                            height: 13,
+                            ..content
+                        },
+                    );
+                    "Esc: back"
+                }
+                Screen::Settings { field } => {
+                    let highlight = |selected: bool| {
+                        if selected {
+                            Style::default().bg(Color::DarkGray)
+                        } else {
+                            Style::default()
+                        }
+                    };
+                    frame.render_widget(
+                        Paragraph::new(vec![
+                            Line::from(format!("Name: {}_", settings.name))
+                                .style(highlight(*field == SettingsField::Name)),
+                            Line::from(format!("Palette: ← {} →", settings.palette.name()))
+                                .style(highlight(*field == SettingsField::Palette)),
+                        ])
+                        .centered()
+                        .block(Block::default().borders(Borders::ALL).title("Settings")),
+                        Rect {
+                            height: 4,
+                            ..content
+                        },
+                    );
+                    "↑ ↓: select  type: edit name  ← →: change palette  Enter: done"
+                }
+            };
+
+            frame.render_widget(
+                Paragraph::new(vec![Line::from(help), Line::from(status.as_str()).yellow()])
+                    .centered()
+                    .block(Block::default().borders(Borders::TOP)),
+                split[5],
+            );
+        })?;
+        Ok(())
+    }
+}
+
+fn standings_table<'a>(standings: &'a [PlayerRecord], title: &'a str) -> Table<'a> {
+    let rows = standings
+        .iter()
+        .take(LEADERBOARD_LENGTH)
+        .enumerate()
+        .map(|(idx, record)| {
+            Row::new(vec![
+                format!("{}", idx + 1),
+                record.name.clone(),
+                record.points().to_string(),
+                record.games().to_string(),
+                record.wins.to_string(),
+                record.draws.to_string(),
+                record.losses.to_string(),
+                format!("{}:{}", record.goals_for, record.goals_against),
+            ])
+        });
+    Table::new(
+        rows,
+        [
+            Constraint::Length(4),
+            Constraint::Length(18),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Min(7),
+        ],
+    )
+    .header(Row::new(vec!["#", "Name", "Points", "Games", "W", "D", "L", "Goals"]).bold())
+    .block(Block::default().borders(Borders::ALL).title(title))
+}
+
+/// Renders the chat panel and returns the scroll offset, clamped to the available messages.
+fn render_chat(
+    frame: &mut ratatui::Frame,
+    area: Rect,
+    chat: &VecDeque<ChatMessage>,
+    chat_input: &Option<String>,
+    chat_scroll: usize,
+) -> usize {
+    let width = area.width.saturating_sub(2).max(1) as usize;
+    let height = area.height.saturating_sub(4) as usize;
+
+    // Messages are wrapped by hand, so that the panel can show the latest lines.
+    let mut lines = vec![];
+    for message in chat {
+        let text = format!("{}: {}", message.name, message.text)
+            .chars()
+            .collect::<Vec<char>>();
+        let name_length = message.name.chars().count() + 1;
+        for (idx, chunk) in text.chunks(width).enumerate() {
+            let chunk = chunk.iter().collect::<String>();
+            if idx == 0 {
+                let split = chunk
+                    .char_indices()
+                    .nth(name_length)
+                    .map(|(idx, _)| idx)
+                    .unwrap_or(chunk.len());
+                lines.push(Line::from(vec![
+                    Span::from(chunk[..split].to_string()).cyan().bold(),
+                    Span::from(chunk[split..].to_string()),
+                ]));
+            } else {
+                lines.push(Line::from(chunk));
+            }
+        }
+    }
+    let chat_scroll = chat_scroll.min(lines.len().saturating_sub(height));
+    let end = lines.len() - chat_scroll;
+    let start = end.saturating_sub(height);
+    let mut visible = lines[start..end].to_vec();
+    while visible.len() < height {
+        visible.insert(0, Line::from(""));
+    }
+
+    let input = match chat_input {
+        Some(text) => {
+            // Keep the end of long messages in view while typing.
+            let text = format!("> {text}_");
+            let skip = text.chars().count().saturating_sub(width);
+            Line::from(text.chars().skip(skip).collect::<String>()).yellow()
+        }
+        None => Line::from("Tab: write  PgUp PgDn: scroll").dark_gray(),
+    };
+    visible.push(Line::from("─".repeat(width)).dark_gray());
+    visible.push(input);
+
+    frame.render_widget(
+        Paragraph::new(visible).block(Block::default().borders(Borders::ALL).title("Chat")),
+        area,
+    );
+    chat_scroll
+}
+
+/// Renders the bracket of an elimination tournament, or the table and pairings of a round robin.
+fn render_tournament(
+    frame: &mut ratatui::Frame,
+    area: Rect,
+    tournament: &Tournament,
+    games: &[GameSummary],
+) {
+    let block = Block::default().borders(Borders::ALL).title(format!(
+        "{} - {}",
+        tournament.name(),
+        tournament.format.name()
+    ));
+    if *tournament.state() == TournamentState::Registering {
+        let mut lines = vec![Line::from(format!(
+            "Waiting for {} more players.",
+            tournament.size - tournament.entrants().len()
+        ))];
+        lines.extend(
+            tournament
+                .entrants()
+                .iter()
+                .map(|entrant| Line::from(entrant.name.clone())),
+        );
+        frame.render_widget(Paragraph::new(lines).centered().block(block), area);
+        return;
+    }
+
+    let name = |player: Option<usize>| {
+        player
+            .and_then(|idx| tournament.entrants().get(idx))
+            .map(|entrant| entrant.name.clone())
+            .unwrap_or_else(|| "?".to_string())
+    };
+    // Running games show their live score.
+    let score = |pairing: &Pairing| {
+        if let Some(game) = pairing
+            .game_id
+            .and_then(|game_id| games.iter().find(|game| game.id == game_id))
+        {
+            (
+                game.score.0.to_string(),
+                game.score.1.to_string(),
+                Style::default().yellow(),
+            )
+        } else if let Some((red, blue)) = pairing.score {
+            (red.to_string(), blue.to_string(), Style::default())
+        } else {
+            (String::new(), String::new(), Style::default().dark_gray())
+        }
+    };
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    match tournament.format {
+        TournamentFormat::SingleElimination => {
+            let rounds = tournament.rounds();
+            let columns = Layout::horizontal(vec![
+                Constraint::Ratio(1, rounds.len() as u32);
+                rounds.len()
+            ])
+            .split(inner);
+            for (round, pairings) in rounds.iter().enumerate() {
+                let column = columns[round];
+                let width = column.width.saturating_sub(2) as usize;
+                // Each pairing is centered on the two pairings it comes from.
+                let block_height = 3 << round;
+                let mut lines = vec![Line::from(tournament.round_name(round)).bold()];
+                for pairing in pairings {
+                    let (red_score, blue_score, style) = score(pairing);
+                    let padding = (block_height - 2) / 2;
+                    lines.extend((0..padding).map(|_| Line::from("")));
+                    for (player, score) in [
+                        (pairing.players[0], red_score),
+                        (pairing.players[1], blue_score),
+                    ] {
+                        let is_winner = player.is_some() && pairing.winner() == player;
+                        let text = if pairing.is_bye && player.is_none() {
+                            "bye".to_string()
+                        } else {
+                            name(player)
+                        };
+                        let line = Line::from(format!(
+                            "{:<width$}{:>3}",
+                            text.chars()
+                                .take(width.saturating_sub(3))
+                                .collect::<String>(),
+                            score,
+                            width = width.saturating_sub(3)
+                        ))
+                        .style(style);
+                        lines.push(if is_winner { line.bold() } else { line });
+                    }
+                    lines.extend((0..block_height - 2 - padding).map(|_| Line::from("")));
+                }
+                frame.render_widget(Paragraph::new(lines), column);
+            }
+        }
+        TournamentFormat::RoundRobin => {
+            let [table_area, pairings_area] =
+                Layout::horizontal([Constraint::Length(36), Constraint::Min(1)]).areas(inner);
+            let rows = tournament
+                .standings()
+                .into_iter()
+                .enumerate()
+                .map(|(idx, (_, record))| {
+                    Row::new(vec![
+                        format!("{}", idx + 1),
+                        record.name.clone(),
+                        record.points().to_string(),
+                        record.games().to_string(),
+                        format!("{}:{}", record.goals_for, record.goals_against),
+                    ])
+                });
+            frame.render_widget(
+                Table::new(
+                    rows,
+                    [
+                        Constraint::Length(3),
+                        Constraint::Length(16),
+                        Constraint::Length(4),
+                        Constraint::Length(3),
+                        Constraint::Min(5),
+                    ],
+                )
+                .header(Row::new(vec!["#", "Name", "Pts", "G", "Goals"]).bold()),
+                table_area,
+            );
+
+            // Live games first, then the pairings still to play, then the results.
+            let mut pairings = tournament
+                .rounds()
+                .iter()
+                .enumerate()
+                .flat_map(|(round, pairings)| pairings.iter().map(move |pairing| (round, pairing)))
+                .collect::<Vec<_>>();
+            pairings
+                .sort_by_key(|(_, pairing)| (pairing.game_id.is_none(), pairing.score.is_some()));
+            let lines = pairings
+                .into_iter()
+                .map(|(round, pairing)| {
+                    let (red_score, blue_score, style) = score(pairing);
+                    let result = if red_score.is_empty() {
+                        "vs".to_string()
+                    } else {
+                        format!("{red_score} - {blue_score}")
+                    };
+                    Line::from(format!(
+                        "{:<9}{:>16} {:^7} {}",
+                        tournament.round_name(round),
+                        name(pairing.players[0]),
+                        result,
+                        name(pairing.players[1])
+                    ))
+                    .style(style)
+                })
+                .collect::<Vec<Line>>();
+            frame.render_widget(Paragraph::new(lines), pairings_area);
+        }
+    }
+}
@@ -1,11 +1,135 @@
-use clap::{ArgAction, Parser};
-use sshattrick::server::GameServer;
+use clap::{ArgAction, Parser, Subcommand};
+use sshattrick::{
+    access::BanList,
+    batch::{run_batch, Contender, BUILT_IN_AI_NAME},
+    config::ServerConfig,
+    keys::{fingerprint, load_or_generate_host_keys},
+    script::load_scripts,
+    server::GameServer,
+};
+use std::path::PathBuf;
+use tokio::signal::unix::{signal, SignalKind};
 
 #[derive(Parser, Debug)]
 #[clap(name="ssHattrick", about = "Hockey in the terminal via ssh", author, version, long_about = None)]
 struct Args {
     #[clap(long, short = 'p', action=ArgAction::Set, help = "Set port to listen on")]
     port: Option<u16>,
+    #[clap(long, short = 'c', action=ArgAction::Set, help = "Path to the server configuration file")]
+    config: Option<PathBuf>,
+    #[clap(long, action=ArgAction::Set, help = "Serve Prometheus metrics on this local port")]
+    metrics_port: Option<u16>,
+    #[clap(long, action=ArgAction::Set, help = "Let bots play on this local port")]
+    bot_port: Option<u16>,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[clap(about = "Ban an IP address or a key fingerprint")]
+    Ban { entry: String },
+    #[clap(about = "Remove an IP address or a key fingerprint from the ban list")]
+    Unban { entry: String },
+    #[clap(about = "List banned IP addresses and key fingerprints")]
+    Bans,
+    #[clap(about = "Print the fingerprints of the host keys, generating them if missing")]
+    Fingerprint,
+    #[clap(about = "Play matches between two AIs without rendering and print the results")]
+    Batch {
+        #[clap(help = "The built-in ai or the name of an AI script")]
+        first: String,
+        #[clap(help = "The built-in ai or the name of an AI script")]
+        second: String,
+        #[clap(long, short = 'n', default_value_t = 1000, help = "Number of matches")]
+        matches: u32,
+        #[clap(long, help = "Seed of the first match, random by default")]
+        seed: Option<u64>,
+    },
+}
+
+fn run_command(config: &ServerConfig, command: Command) {
+    let path = config.ban_list.as_path();
+    match command {
+        Command::Ban { entry } => {
+            let mut ban_list = BanList::load(path).expect("Failed loading ban list");
+            if ban_list.add(&entry) {
+                ban_list.save(path).expect("Failed saving ban list");
+                println!("Banned {entry}");
+            } else {
+                println!("{entry} is already banned");
+            }
+        }
+        Command::Unban { entry } => {
+            let mut ban_list = BanList::load(path).expect("Failed loading ban list");
+            if ban_list.remove(&entry) {
+                ban_list.save(path).expect("Failed saving ban list");
+                println!("Unbanned {entry}");
+            } else {
+                println!("{entry} is not banned");
+            }
+        }
+        Command::Bans => {
+            let ban_list = BanList::load(path).expect("Failed loading ban list");
+            for ip in ban_list.ips.iter() {
+                println!("{ip}");
+            }
+            for key in ban_list.keys.iter() {
+                println!("SHA256:{key}");
+            }
+        }
+        Command::Fingerprint => {
+            let keys =
+                load_or_generate_host_keys(&config.host_key_dir, &config.host_key_algorithms)
+                    .expect("Failed loading host keys");
+            for key_pair in keys.iter() {
+                println!(
+                    "{}",
+                    fingerprint(key_pair).expect("Failed computing fingerprint")
+                );
+            }
+        }
+        Command::Batch {
+            first,
+            second,
+            matches,
+            seed,
+        } => {
+            let scripts = config
+                .ai_scripts
+                .as_ref()
+                .map(|dir| load_scripts(dir, config.ai_script_max_operations))
+                .unwrap_or_default();
+            let find = |name: &str| {
+                Contender::find(name, &scripts).unwrap_or_else(|| {
+                    let names = std::iter::once(BUILT_IN_AI_NAME)
+                        .chain(scripts.iter().map(|script| script.name.as_str()))
+                        .collect::<Vec<_>>();
+                    eprintln!("Unknown AI {name}, choose among: {}", names.join(", "));
+                    std::process::exit(1);
+                })
+            };
+            let (first, second) = (find(&first), find(&second));
+            println!(
+                "{}",
+                run_batch(
+                    &first,
+                    &second,
+                    matches,
+                    config.restitution,
+                    seed.unwrap_or_else(rand::random)
+                )
+            );
+        }
+    }
+}
+
+async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed installing SIGTERM handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
 }
 
 #[tokio::main]
@@ -14,9 +138,49 @@ async fn main() {
         .filter_level(log::LevelFilter::Info)
         .init();
 
-    let mut game_server = GameServer::new();
+    let args = Args::parse();
+
+    let config = match args.config {
+        Some(path) => ServerConfig::load(&path).expect("Failed loading configuration"),
+        None => ServerConfig::default(),
+    };
+
+    if let Some(command) = args.command {
+        run_command(&config, command);
+        return;
+    }
+
+    let mut game_server = GameServer::new(config);
+
+    if let Some(metrics_port) = args.metrics_port {
+        let metrics_server = game_server.clone();
+        tokio::spawn(async move {
+            metrics_server
+                .serve_metrics(metrics_port)
+                .await
+                .unwrap_or_else(|e| log::error!("Failed serving metrics: {e}"));
+        });
+    }
+
+    if let Some(bot_port) = args.bot_port {
+        let bot_server = game_server.clone();
+        tokio::spawn(async move {
+            bot_server
+                .serve_bots(bot_port)
+                .await
+                .unwrap_or_else(|e| log::error!("Failed serving bots: {e}"));
+        });
+    }
 
-    let port = Args::parse().port.unwrap_or(2020);
+    let port = args.port.unwrap_or(2020);
 
-    game_server.run(port).await.expect("Failed running server");
+    let shutdown_server = game_server.clone();
+    // Dropping the server future stops accepting connections, while open sessions keep running.
+    tokio::select! {
+        result = game_server.run(port) => result.expect("Failed running server"),
+        _ = shutdown_signal() => {
+            log::info!("Shutting down server");
+            shutdown_server.shutdown().await;
+        }
+    }
 }
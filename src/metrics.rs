@@ -0,0 +1,147 @@
+use crate::admin::ServerSnapshot;
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+// Weight of the last tick in the tick duration moving average.
+const TICK_DURATION_SMOOTHING: f64 = 0.05;
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    matches_completed: AtomicU64,
+    ticks: AtomicU64,
+    tick_duration_seconds_sum: AtomicU64,
+    tick_duration_seconds_average: AtomicU64,
+}
+
+// Atomic floats are stored as their bit representation.
+fn add_f64(atomic: &AtomicU64, value: f64) {
+    let _ = atomic.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+        Some((f64::from_bits(bits) + value).to_bits())
+    });
+}
+
+fn load_f64(atomic: &AtomicU64) -> f64 {
+    f64::from_bits(atomic.load(Ordering::Relaxed))
+}
+
+impl Metrics {
+    pub fn record_tick(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let ticks = self.ticks.fetch_add(1, Ordering::Relaxed);
+        add_f64(&self.tick_duration_seconds_sum, seconds);
+        let _ = self.tick_duration_seconds_average.fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |bits| {
+                let average = if ticks == 0 {
+                    seconds
+                } else {
+                    f64::from_bits(bits) * (1.0 - TICK_DURATION_SMOOTHING)
+                        + seconds * TICK_DURATION_SMOOTHING
+                };
+                Some(average.to_bits())
+            },
+        );
+    }
+
+    pub fn record_match_completed(&self) {
+        self.matches_completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self, snapshot: &ServerSnapshot) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            let _ = writeln!(text, "# HELP sshattrick_{name} {help}");
+            let _ = writeln!(text, "# TYPE sshattrick_{name} {kind}");
+            for (labels, value) in samples {
+                let _ = writeln!(text, "sshattrick_{name}{labels} {value}");
+            }
+        };
+
+        metric(
+            "connected_clients",
+            "gauge",
+            "Number of connected clients.",
+            vec![(String::new(), snapshot.clients.len().to_string())],
+        );
+        metric(
+            "games_in_progress",
+            "gauge",
+            "Number of games being played.",
+            vec![(String::new(), snapshot.games.len().to_string())],
+        );
+        metric(
+            "queue_length",
+            "gauge",
+            "Number of clients waiting for an opponent.",
+            vec![(
+                String::new(),
+                (snapshot.pending_client.is_some() as u8).to_string(),
+            )],
+        );
+        metric(
+            "matches_completed_total",
+            "counter",
+            "Number of matches played until the end.",
+            vec![(
+                String::new(),
+                self.matches_completed.load(Ordering::Relaxed).to_string(),
+            )],
+        );
+        metric(
+            "tick_duration_seconds",
+            "summary",
+            "Duration of the game loop ticks.",
+            vec![
+                (
+                    "_sum".to_string(),
+                    load_f64(&self.tick_duration_seconds_sum).to_string(),
+                ),
+                (
+                    "_count".to_string(),
+                    self.ticks.load(Ordering::Relaxed).to_string(),
+                ),
+            ],
+        );
+        metric(
+            "tick_duration_seconds_average",
+            "gauge",
+            "Moving average of the duration of the game loop ticks.",
+            vec![(
+                String::new(),
+                load_f64(&self.tick_duration_seconds_average).to_string(),
+            )],
+        );
+        metric(
+            "game_fps",
+            "gauge",
+            "Frames per second of each game.",
+            snapshot
+                .games
+                .iter()
+                .map(|game| (format!("{{game_id=\"{}\"}}", game.id), game.fps.to_string()))
+                .collect(),
+        );
+        metric(
+            "client_bytes_flushed_total",
+            "counter",
+            "Bytes sent to each client terminal.",
+            snapshot
+                .clients
+                .iter()
+                .map(|client| {
+                    (
+                        format!("{{client_id=\"{}\"}}", client.id),
+                        client.bytes_flushed.to_string(),
+                    )
+                })
+                .collect(),
+        );
+
+        text
+    }
+}
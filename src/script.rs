@@ -0,0 +1,363 @@
+use crate::{
+    ai::INPUT_INTERVAL_MILLISECONDS,
+    game::{PlayerSnapshot, PuckSnapshot, Snapshot},
+    types::GameSide,
+};
+use crossterm::event::KeyCode;
+use rhai::{
+    module_resolvers::DummyModuleResolver, CallFnOptions, Dynamic, Engine, Map, Scope, AST,
+};
+use serde::Serialize;
+use std::{fs, path::Path, sync::Arc};
+
+const DECIDE_FUNCTION: &str = "decide";
+const MAX_CALL_LEVELS: usize = 16;
+const MAX_EXPRESSION_DEPTH: usize = 64;
+const MAX_STRING_SIZE: usize = 1024;
+const MAX_ARRAY_SIZE: usize = 1024;
+const MAX_MAP_SIZE: usize = 256;
+
+/// What a script sees of the game, from the point of view of its side.
+#[derive(Debug, Serialize)]
+struct ScriptState<'a> {
+    side: GameSide,
+    me: &'a PlayerSnapshot,
+    opponent: &'a PlayerSnapshot,
+    puck: &'a PuckSnapshot,
+    has_puck: bool,
+    my_goalie: (f32, f32),
+    opponent_goalie: (f32, f32),
+    // Own goals first.
+    score: (u8, u8),
+    remaining_seconds: u128,
+}
+
+/// A compiled opponent script, shared by all the games using it.
+#[derive(Clone)]
+pub struct AiScript {
+    pub name: String,
+    engine: Arc<Engine>,
+    ast: Arc<AST>,
+}
+
+impl std::fmt::Debug for AiScript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AiScript")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+/// Compiles the `.rhai` scripts in the directory. Scripts that fail to compile
+/// or do not define a `decide` function are skipped.
+pub fn load_scripts(dir: &Path, max_operations: u64) -> Vec<AiScript> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::error!("Failed to read AI scripts in {}: {e}", dir.display());
+            return vec![];
+        }
+    };
+
+    let mut engine = Engine::new();
+    // Scripts cannot import other files from the disk.
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine
+        .set_max_operations(max_operations)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_expr_depths(MAX_EXPRESSION_DEPTH, MAX_EXPRESSION_DEPTH)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_ARRAY_SIZE)
+        .set_max_map_size(MAX_MAP_SIZE);
+    engine.on_print(|text| log::debug!("AI script: {text}"));
+    engine.on_debug(|text, _, _| log::debug!("AI script: {text}"));
+    let engine = Arc::new(engine);
+
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "rhai")
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut scripts = vec![];
+    for path in paths {
+        let Some(name) = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+        else {
+            continue;
+        };
+        let ast = match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| engine.compile(source).map_err(|e| e.to_string()))
+        {
+            Ok(ast) => ast,
+            Err(e) => {
+                log::error!("Skipping AI script {}: {e}", path.display());
+                continue;
+            }
+        };
+        if !ast
+            .iter_functions()
+            .any(|function| function.name == DECIDE_FUNCTION && function.params.len() == 1)
+        {
+            log::error!(
+                "Skipping AI script {}: it does not define fn {DECIDE_FUNCTION}(state)",
+                path.display()
+            );
+            continue;
+        }
+        log::info!("Loaded AI script {name}");
+        scripts.push(AiScript {
+            name,
+            engine: engine.clone(),
+            ast: Arc::new(ast),
+        });
+    }
+    scripts
+}
+
+/// An opponent playing the keys returned by a script. A script that fails,
+/// for instance by running for too long, stops playing until the end of the game.
+#[derive(Debug, Clone)]
+pub struct ScriptedAi {
+    script: AiScript,
+    // Bound to `this` in the script, kept between calls.
+    memory: Dynamic,
+    elapsed: f32,
+    has_failed: bool,
+}
+
+impl ScriptedAi {
+    pub fn new(script: AiScript) -> Self {
+        Self {
+            script,
+            memory: Dynamic::from_map(Map::new()),
+            elapsed: 0.0,
+            has_failed: false,
+        }
+    }
+
+    pub fn next_input(
+        &mut self,
+        side: GameSide,
+        snapshot: &Snapshot,
+        deltatime: f32,
+    ) -> Option<KeyCode> {
+        self.elapsed += deltatime;
+        if self.has_failed || self.elapsed < INPUT_INTERVAL_MILLISECONDS {
+            return None;
+        }
+        self.elapsed = 0.0;
+
+        let (me, opponent, my_goalie, opponent_goalie, score) = match side {
+            GameSide::Red => (
+                &snapshot.red_player,
+                &snapshot.blue_player,
+                snapshot.red_goalie,
+                snapshot.blue_goalie,
+                snapshot.score,
+            ),
+            GameSide::Blue => (
+                &snapshot.blue_player,
+                &snapshot.red_player,
+                snapshot.blue_goalie,
+                snapshot.red_goalie,
+                (snapshot.score.1, snapshot.score.0),
+            ),
+        };
+        let state = ScriptState {
+            side,
+            me,
+            opponent,
+            puck: &snapshot.puck,
+            has_puck: snapshot.puck.possession == Some(side),
+            my_goalie,
+            opponent_goalie,
+            score,
+            remaining_seconds: snapshot.remaining_seconds,
+        };
+
+        let result = rhai::serde::to_dynamic(&state).and_then(|state| {
+            self.script.engine.call_fn_with_options::<Dynamic>(
+                CallFnOptions::new()
+                    .eval_ast(false)
+                    .bind_this_ptr(&mut self.memory),
+                &mut Scope::new(),
+                &self.script.ast,
+                DECIDE_FUNCTION,
+                (state,),
+            )
+        });
+        let key = match result {
+            Ok(key) => key,
+            Err(e) => {
+                log::error!("AI script {} failed: {e}", self.script.name);
+                self.has_failed = true;
+                return None;
+            }
+        };
+        if key.is_unit() {
+            return None;
+        }
+        match key.into_string().as_deref() {
+            Ok("up") => Some(KeyCode::Up),
+            Ok("down") => Some(KeyCode::Down),
+            Ok("left") => Some(KeyCode::Left),
+            Ok("right") => Some(KeyCode::Right),
+            Ok("shoot") => Some(KeyCode::Char(' ')),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::{Simulation, MINIMUM_DELTATIME_MILLISECONDS};
+    use std::path::PathBuf;
+
+    const MAX_OPERATIONS: u64 = 10_000;
+
+    fn script_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("sshattrick_scripts_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn scripted_ai(name: &str, source: &str) -> ScriptedAi {
+        let dir = script_dir(name);
+        fs::write(dir.join(format!("{name}.rhai")), source).unwrap();
+        let mut scripts = load_scripts(&dir, MAX_OPERATIONS);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(scripts.len(), 1);
+        ScriptedAi::new(scripts.remove(0))
+    }
+
+    // Calls the script once, as the game does every input interval.
+    fn decide(ai: &mut ScriptedAi, simulation: &Simulation) -> Option<KeyCode> {
+        ai.next_input(
+            GameSide::Red,
+            &simulation.snapshot(),
+            INPUT_INTERVAL_MILLISECONDS,
+        )
+    }
+
+    #[test]
+    fn test_script_keys() {
+        let mut ai = scripted_ai(
+            "keys",
+            r#"
+                fn decide(state) {
+                    if state.has_puck { "shoot" } else if state.side == "red" { "right" }
+                }
+            "#,
+        );
+        let simulation = Simulation::new();
+        // The script is only called every input interval.
+        assert_eq!(
+            ai.next_input(
+                GameSide::Red,
+                &simulation.snapshot(),
+                MINIMUM_DELTATIME_MILLISECONDS
+            ),
+            None
+        );
+        assert_eq!(decide(&mut ai, &simulation), Some(KeyCode::Right));
+
+        let mut ai = scripted_ai(
+            "all_keys",
+            r#"
+                fn decide(state) {
+                    let keys = ["up", "down", "left", "right", "shoot", "jump", 42, ()];
+                    if this.index == () { this.index = 0; }
+                    this.index += 1;
+                    keys[this.index - 1]
+                }
+            "#,
+        );
+        let keys = (0..8)
+            .map(|_| decide(&mut ai, &simulation))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                Some(KeyCode::Up),
+                Some(KeyCode::Down),
+                Some(KeyCode::Left),
+                Some(KeyCode::Right),
+                Some(KeyCode::Char(' ')),
+                None,
+                None,
+                None
+            ]
+        );
+        assert!(!ai.has_failed);
+    }
+
+    #[test]
+    fn test_script_memory() {
+        let mut ai = scripted_ai(
+            "memory",
+            r#"
+                fn decide(state) {
+                    if this.calls == () { this.calls = 0; }
+                    this.calls += 1;
+                    if this.calls == 3 { "shoot" }
+                }
+            "#,
+        );
+        let simulation = Simulation::new();
+        let keys = (0..4)
+            .map(|_| decide(&mut ai, &simulation))
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec![None, None, Some(KeyCode::Char(' ')), None]);
+    }
+
+    #[test]
+    fn test_script_limits() {
+        let mut ai = scripted_ai("forever", "fn decide(state) { loop { } }");
+        let simulation = Simulation::new();
+        assert_eq!(decide(&mut ai, &simulation), None);
+        assert!(ai.has_failed);
+        assert_eq!(decide(&mut ai, &simulation), None);
+
+        // Scripts cannot read other files.
+        let dir = script_dir("module");
+        let module = dir.join("secret");
+        fs::write(dir.join("secret.rhai"), "export const KEY = \"shoot\";").unwrap();
+        let mut ai = scripted_ai(
+            "import",
+            &format!(
+                "fn decide(state) {{ import {:?} as secret; secret::KEY }}",
+                module.display().to_string()
+            ),
+        );
+        assert_eq!(decide(&mut ai, &simulation), None);
+        assert!(ai.has_failed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_scripts() {
+        let dir = script_dir("invalid");
+        fs::write(dir.join("broken.rhai"), "fn decide(state) {").unwrap();
+        fs::write(dir.join("idle.rhai"), "fn wait() { }").unwrap();
+        fs::write(dir.join("notes.txt"), "fn decide(state) { }").unwrap();
+        fs::write(dir.join("valid.rhai"), "fn decide(state) { }").unwrap();
+        let scripts = load_scripts(&dir, MAX_OPERATIONS);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            scripts
+                .iter()
+                .map(|script| script.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["valid"]
+        );
+    }
+}
@@ -1,40 +1,67 @@
 use crate::{
-    game::Game,
-    types::{AppResult, TerminalHandle},
+    access::{AccessControl, Rejection},
+    admin::{AdminAction, AdminConsole, BotSummary, ClientSummary, GameSummary, ServerSnapshot},
+    bot::{BotLink, BotMessage, FIRST_BOT_CLIENT_ID},
+    chat::{Chat, ChatRejection},
+    config::{AuthPolicy, ServerConfig},
+    events::{Event, EventLog},
+    game::{Client, Game, GameEvent},
+    keys::{fingerprint, load_or_generate_host_keys},
+    ladder::Ladder,
+    leaderboard::{Leaderboard, MatchPlayer},
+    lobby::{Lobby, LobbyAction, ROOM_CODE_LENGTH},
+    metrics::Metrics,
+    script::{load_scripts, AiScript},
+    tournament::{Tournament, TournamentFormat},
+    types::{GameSide, SshTerminal, TerminalHandle},
 };
 use async_trait::async_trait;
 use crossterm::event::KeyCode;
+use rand::Rng;
 use ratatui::{backend::CrosstermBackend, layout::Rect, Terminal};
-use russh::{server::*, Channel, ChannelId};
-use russh_keys::key::{KeyPair, PublicKey};
+use russh::{server::*, Channel, ChannelId, MethodSet};
+use russh_keys::key::PublicKey;
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{Read, Write},
-    sync::Arc,
-    time::Instant,
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use subtle::ConstantTimeEq;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::Mutex,
 };
-use tokio::sync::Mutex;
 
 const GAME_NAME: &str = "ssHattrick";
 const TERMINAL_WIDTH: u16 = 160;
 const TERMINAL_HEIGHT: u16 = 50;
-const INACTIVITY_TIMEOUT: u64 = 10;
-
-pub fn save_keys(signing_key: &ed25519_dalek::SigningKey) -> AppResult<()> {
-    let file = File::create::<&str>("./keys".into())?;
-    assert!(file.metadata()?.is_file());
-    let mut buffer = std::io::BufWriter::new(file);
-    buffer.write(&signing_key.to_bytes())?;
-    Ok(())
-}
+// Players can idle in the lobby, so this is only meant to clean up dead connections.
+const INACTIVITY_TIMEOUT: u64 = 300;
+// How long a player waits in the quick match queue before going back to the menu.
+const QUEUE_TIMEOUT: u64 = 60;
+// Room codes avoid characters that are easily confused, like 0 and O.
+const ROOM_CODE_CHARACTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+// Finished tournaments are kept in the lobby for a while to show their brackets.
+const MAX_FINISHED_TOURNAMENTS: usize = 5;
 
-pub fn load_keys() -> AppResult<ed25519_dalek::SigningKey> {
-    let file = File::open::<&str>("./keys".into())?;
-    let mut buffer = std::io::BufReader::new(file);
-    let mut buf: [u8; 32] = [0; 32];
-    buffer.read(&mut buf)?;
-    Ok(ed25519_dalek::SigningKey::from_bytes(&buf))
+fn new_terminal(terminal_handle: TerminalHandle) -> std::io::Result<SshTerminal> {
+    let backend = CrosstermBackend::new(terminal_handle);
+    Terminal::with_options(
+        backend,
+        ratatui::TerminalOptions {
+            viewport: ratatui::Viewport::Fixed(Rect {
+                x: 0,
+                y: 0,
+                width: TERMINAL_WIDTH,
+                height: TERMINAL_HEIGHT,
+            }),
+        },
+    )
 }
 
 fn convert_data_to_key_code(data: &[u8]) -> crossterm::event::KeyCode {
@@ -50,108 +77,245 @@ fn convert_data_to_key_code(data: &[u8]) -> crossterm::event::KeyCode {
         b"\x7f" => crossterm::event::KeyCode::Backspace,
         b"\x1b[3~" => crossterm::event::KeyCode::Delete,
         b"\x09" => crossterm::event::KeyCode::Tab,
+        b"\x1b[5~" => crossterm::event::KeyCode::PageUp,
+        b"\x1b[6~" => crossterm::event::KeyCode::PageDown,
         _ => crossterm::event::KeyCode::Char(data[0] as char),
     }
 }
 
+/// Splits typed or pasted text into keys, so that the lobby also gets non ASCII characters.
+fn convert_data_to_key_codes(data: &[u8]) -> Vec<crossterm::event::KeyCode> {
+    if data.first() == Some(&0x1b) {
+        return vec![convert_data_to_key_code(data)];
+    }
+    String::from_utf8_lossy(data)
+        .chars()
+        .map(|c| match c {
+            '\x03' => crossterm::event::KeyCode::Esc,
+            '\x0d' => crossterm::event::KeyCode::Enter,
+            '\x7f' => crossterm::event::KeyCode::Backspace,
+            '\x09' => crossterm::event::KeyCode::Tab,
+            c => crossterm::event::KeyCode::Char(c),
+        })
+        .collect()
+}
+
+/// Ends a client session when the client handler is dropped.
+#[derive(Debug)]
+struct Connection {
+    client_id: usize,
+    peer: Option<SocketAddr>,
+    opened_at: Instant,
+    event_log: Arc<EventLog>,
+    access_control: Arc<std::sync::Mutex<AccessControl>>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Ok(mut access_control) = self.access_control.lock() {
+            access_control.close_session(self.peer.map(|peer| peer.ip()));
+        }
+        self.event_log.log(Event::SessionClosed {
+            client_id: self.client_id,
+            duration_seconds: self.opened_at.elapsed().as_secs(),
+        });
+    }
+}
+
 #[derive(Clone)]
 pub struct GameServer {
     clients: Arc<Mutex<HashMap<usize, TerminalHandle>>>,
     clients_to_game: Arc<Mutex<HashMap<usize, uuid::Uuid>>>,
     client_id: usize,
+    // Fingerprint of the public key used to authenticate, if any.
+    identity: Option<String>,
+    // Name sent by the ssh client, used as the default player name.
+    username: String,
+    // Maps identities to the last client id they played with, used to rejoin a game.
+    identities: Arc<Mutex<HashMap<String, usize>>>,
     games: Arc<Mutex<HashMap<uuid::Uuid, Game>>>,
+    // Clients that are not playing or spectating see the lobby.
+    lobbies: Arc<Mutex<HashMap<usize, Lobby>>>,
+    spectators: Arc<Mutex<HashMap<usize, uuid::Uuid>>>,
+    // Maps the codes of the private rooms to the client waiting in them.
+    rooms: Arc<Mutex<HashMap<String, usize>>>,
     pending_client: Arc<Mutex<Option<(usize, Instant)>>>,
+    leaderboard: Arc<Mutex<Leaderboard>>,
+    ladder: Arc<Mutex<Ladder>>,
+    chat: Arc<Mutex<Chat>>,
+    tournaments: Arc<Mutex<Vec<Tournament>>>,
+    // Bots connected to the bot port and waiting for an opponent.
+    bots: Arc<Mutex<Vec<BotLink>>>,
+    next_bot_id: Arc<AtomicUsize>,
+    ai_scripts: Arc<Vec<AiScript>>,
+    admins: Arc<Mutex<HashMap<usize, AdminConsole>>>,
+    config: Arc<ServerConfig>,
+    metrics: Arc<Metrics>,
+    event_log: Arc<EventLog>,
+    peer: Option<SocketAddr>,
+    // Only set for client handlers, so that dropping it marks the end of the session.
+    connection: Option<Arc<Connection>>,
+    // The access control is also used from sync code, hence the std mutex.
+    access_control: Arc<std::sync::Mutex<AccessControl>>,
+    rejection: Option<Rejection>,
+    shutting_down: Arc<AtomicBool>,
 }
 
 impl GameServer {
-    pub fn new() -> Self {
+    pub fn new(config: ServerConfig) -> Self {
         log::info!("Creating new server");
+        let event_log = match &config.event_log {
+            Some(path) => EventLog::open(path).unwrap_or_else(|e| {
+                log::error!("Failed to open event log {}: {e}", path.display());
+                EventLog::default()
+            }),
+            None => EventLog::default(),
+        };
+        let access_control = AccessControl::new(&config);
+        let leaderboard = Leaderboard::load(&config.leaderboard).unwrap_or_else(|e| {
+            log::error!(
+                "Failed to load leaderboard {}: {e}",
+                config.leaderboard.display()
+            );
+            Leaderboard::default()
+        });
+        let ladder = Ladder::load(&config).unwrap_or_else(|e| {
+            log::error!("Failed to load ladder {}: {e}", config.ladder.display());
+            Ladder::default()
+        });
+        let ai_scripts = config
+            .ai_scripts
+            .as_ref()
+            .map(|dir| load_scripts(dir, config.ai_script_max_operations))
+            .unwrap_or_default();
         Self {
             clients: Arc::new(Mutex::new(HashMap::new())),
             clients_to_game: Arc::new(Mutex::new(HashMap::new())),
             client_id: 0,
+            identity: None,
+            username: String::new(),
+            identities: Arc::new(Mutex::new(HashMap::new())),
             games: Arc::new(Mutex::new(HashMap::new())),
+            lobbies: Arc::new(Mutex::new(HashMap::new())),
+            spectators: Arc::new(Mutex::new(HashMap::new())),
+            rooms: Arc::new(Mutex::new(HashMap::new())),
             pending_client: Arc::new(Mutex::new(None)),
+            leaderboard: Arc::new(Mutex::new(leaderboard)),
+            ladder: Arc::new(Mutex::new(ladder)),
+            chat: Arc::new(Mutex::new(Chat::new(&config))),
+            tournaments: Arc::new(Mutex::new(vec![])),
+            bots: Arc::new(Mutex::new(vec![])),
+            next_bot_id: Arc::new(AtomicUsize::new(FIRST_BOT_CLIENT_ID)),
+            ai_scripts: Arc::new(ai_scripts),
+            admins: Arc::new(Mutex::new(HashMap::new())),
+            config: Arc::new(config),
+            metrics: Arc::new(Metrics::default()),
+            event_log: Arc::new(event_log),
+            peer: None,
+            connection: None,
+            access_control: Arc::new(std::sync::Mutex::new(access_control)),
+            rejection: None,
+            shutting_down: Arc::new(AtomicBool::new(false)),
         }
     }
 
     pub async fn run(&mut self, port: u16) -> Result<(), anyhow::Error> {
+        let keys =
+            load_or_generate_host_keys(&self.config.host_key_dir, &self.config.host_key_algorithms)
+                .map_err(|e| anyhow::anyhow!("Failed loading host keys: {e}"))?;
+        for key_pair in keys.iter() {
+            if let Ok(fingerprint) = fingerprint(key_pair) {
+                log::info!("Host key {fingerprint}");
+            }
+        }
+
         let games = self.games.clone();
         let clients = self.clients.clone();
         let pending_client = self.pending_client.clone();
+        let server = self.clone();
         log::info!("Starting game loop");
         // TODO (maybe): spawn a new loop for each game. Not sure it's a good idea actually
         // To close the loop, check if both are disconnected or the game is over.
         tokio::spawn(async move {
             loop {
                 tokio::time::sleep(tokio::time::Duration::from_millis(2)).await;
+                let tick_start = Instant::now();
                 let mut to_remove = vec![];
                 for (_, game) in games.lock().await.iter_mut() {
-                    let (red_client_id, blue_client_id) = game.client_ids();
-                    if clients.lock().await.get(&red_client_id).is_none() {
-                        game.disconnect(red_client_id);
-                    }
-                    if clients.lock().await.get(&blue_client_id).is_none() {
-                        game.disconnect(blue_client_id);
+                    for client_id in game.human_client_ids() {
+                        if clients.lock().await.get(&client_id).is_none() {
+                            game.disconnect(client_id);
+                        }
                     }
 
-                    log::info!("Connections state: {:?}", game.connections_state());
+                    log::debug!("Connections state: {:?}", game.connections_state());
 
-                    if game.connections_state() == (false, false) {
-                        log::info!("Both players disconnected, removing game {}", game.id);
-                        to_remove.push(game.id);
+                    if !game.has_connected_players() {
+                        if !game.is_waiting_for_reconnection() {
+                            log::info!("All players disconnected, removing game {}", game.id);
+                            to_remove.push(game.id);
+                        }
                     } else {
                         game.update().unwrap_or_else(|e| {
                             log::error!("Failed to update game: {:?}", e);
                             to_remove.push(game.id);
                         });
 
+                        for event in game.drain_events() {
+                            server.log_game_event(game, event);
+                        }
+
                         game.draw().unwrap_or_else(|e| {
                             log::error!("Failed to draw game: {:?}", e);
                             to_remove.push(game.id);
                         });
                     }
 
-                    if game.is_over() {
+                    // Keep the final result on screen for a while before going back to the lobby.
+                    if game.is_finished() {
                         to_remove.push(game.id);
                     }
                 }
 
                 for game_id in to_remove {
-                    log::info!("Removing game {game_id}");
-                    games.lock().await.remove(&game_id);
+                    let game = games.lock().await.remove(&game_id);
+                    if let Some(game) = game {
+                        log::info!("Removing game {game_id}");
+                        server.end_game(game).await;
+                    }
                 }
 
-                // Remove pending client if it's been waiting for too long
+                // Send the pending client back to the menu if it's been waiting for too long
                 let mut pending_client = pending_client.lock().await;
                 log::debug!("Pending client: {:?}", pending_client);
-                if pending_client.is_some() {
-                    let (pending_id, instant) = pending_client.as_ref().unwrap().clone();
-                    if instant.elapsed().as_secs() > INACTIVITY_TIMEOUT {
-                        log::info!("Pending client connection timed out");
-                        clients.lock().await.remove(&pending_id);
+                let timed_out = match *pending_client {
+                    Some((pending_id, instant)) if instant.elapsed().as_secs() > QUEUE_TIMEOUT => {
                         *pending_client = None;
+                        Some(pending_id)
+                    }
+                    _ => None,
+                };
+                drop(pending_client);
+                if let Some(pending_id) = timed_out {
+                    log::info!("Pending client {pending_id} timed out");
+                    if let Some(lobby) = server.lobbies.lock().await.get_mut(&pending_id) {
+                        lobby.stop_waiting("No opponent found, please try again later");
                     }
                 }
-            }
-        });
 
-        let signing_key = load_keys().unwrap_or_else(|_| {
-            let key_pair = russh_keys::key::KeyPair::generate_ed25519().unwrap();
-            let signing_key = match key_pair {
-                KeyPair::Ed25519(key) => key,
-            };
-            let _ = save_keys(&signing_key);
-            signing_key
+                server.start_tournament_games().await;
+                server.start_bot_games().await;
+                server.draw_lobbies().await;
+                server.draw_admin_consoles().await;
+                server.metrics.record_tick(tick_start.elapsed());
+            }
         });
 
-        let key_pair = KeyPair::Ed25519(signing_key);
-
         let config = Config {
             inactivity_timeout: Some(std::time::Duration::from_secs(INACTIVITY_TIMEOUT)),
             auth_rejection_time: std::time::Duration::from_secs(3),
             auth_rejection_time_initial: Some(std::time::Duration::from_secs(0)),
-            keys: vec![key_pair],
+            keys,
             ..Default::default()
         };
 
@@ -162,31 +326,1016 @@ impl GameServer {
         Ok(())
     }
 
+    fn log_game_event(&self, game: &Game, event: GameEvent) {
+        let game_time_seconds = game.game_time_seconds();
+        let (red_client_id, blue_client_id) = game.client_ids();
+        let client_id = |side| match side {
+            GameSide::Red => red_client_id,
+            GameSide::Blue => blue_client_id,
+        };
+        let event = match event {
+            GameEvent::Goal { side } => Event::GoalScored {
+                game_id: game.id,
+                side,
+                scorer_client_id: client_id(side),
+                game_time_seconds,
+            },
+            GameEvent::Save { side } => Event::Save {
+                game_id: game.id,
+                side,
+                goalie_client_id: client_id(side),
+                game_time_seconds,
+            },
+        };
+        self.event_log.log(event);
+    }
+
+    async fn snapshot(&self) -> ServerSnapshot {
+        let identities = self.identities.lock().await.clone();
+        let clients_to_game = self.clients_to_game.lock().await.clone();
+        let chat = self.chat.lock().await;
+        let mut clients = self
+            .clients
+            .lock()
+            .await
+            .iter()
+            .map(|(&id, terminal_handle)| {
+                let identity = identities
+                    .iter()
+                    .find(|(_, &client_id)| client_id == id)
+                    .map(|(identity, _)| identity.clone());
+                ClientSummary {
+                    id,
+                    is_muted: chat.is_muted(id, identity.as_deref()),
+                    identity,
+                    game_id: clients_to_game.get(&id).copied(),
+                    bytes_flushed: terminal_handle.bytes_flushed(),
+                }
+            })
+            .collect::<Vec<ClientSummary>>();
+        drop(chat);
+        clients.sort_by_key(|client| client.id);
+
+        let pending_client = self
+            .pending_client
+            .lock()
+            .await
+            .map(|(id, instant)| (id, instant.elapsed().as_secs()));
+
+        let mut games = self
+            .games
+            .lock()
+            .await
+            .values()
+            .map(|game| GameSummary {
+                id: game.id,
+                client_ids: game.client_ids(),
+                connections_state: game.connections_state(),
+                names: (game.names().0.to_string(), game.names().1.to_string()),
+                score: game.score(),
+                remaining_seconds: game.remaining_seconds(),
+                fps: game.fps(),
+            })
+            .collect::<Vec<GameSummary>>();
+        games.sort_by_key(|game| game.client_ids);
+
+        let bots = self
+            .bots
+            .lock()
+            .await
+            .iter()
+            .filter(|bot| bot.is_connected())
+            .map(|bot| BotSummary {
+                id: bot.id,
+                name: bot.name.clone(),
+            })
+            .collect::<Vec<BotSummary>>();
+
+        ServerSnapshot {
+            clients,
+            pending_client,
+            games,
+            bots,
+            ai_scripts: self
+                .ai_scripts
+                .iter()
+                .map(|script| script.name.clone())
+                .collect(),
+        }
+    }
+
+    async fn draw_admin_consoles(&self) {
+        let mut admins = self.admins.lock().await;
+        if !admins.values().any(|admin| admin.needs_redraw()) {
+            return;
+        }
+        let snapshot = self.snapshot().await;
+        for admin in admins.values_mut() {
+            if admin.needs_redraw() {
+                admin.draw(snapshot.clone()).unwrap_or_else(|e| {
+                    log::error!("Failed to draw admin console: {e}");
+                });
+            }
+        }
+    }
+
+    async fn draw_lobbies(&self) {
+        if !self
+            .lobbies
+            .lock()
+            .await
+            .values()
+            .any(|lobby| lobby.needs_redraw())
+        {
+            return;
+        }
+        let snapshot = self.snapshot().await;
+        let standings = self.leaderboard.lock().await.standings();
+        let chat = self.chat.lock().await.messages().clone();
+        let tournaments = self.tournaments.lock().await.clone();
+        let ladder = {
+            let mut ladder = self.ladder.lock().await;
+            ladder.refresh();
+            ladder.view()
+        };
+        for lobby in self.lobbies.lock().await.values_mut() {
+            if lobby.needs_redraw() {
+                lobby
+                    .draw(&snapshot, &standings, &chat, &tournaments, &ladder)
+                    .unwrap_or_else(|e| {
+                        log::error!("Failed to draw lobby: {e}");
+                    });
+            }
+        }
+    }
+
+    async fn identity_of(&self, client_id: usize) -> Option<String> {
+        self.identities
+            .lock()
+            .await
+            .iter()
+            .find(|(_, &id)| id == client_id)
+            .map(|(identity, _)| identity.clone())
+    }
+
+    /// Records the result of a removed game and sends its players and spectators back to the lobby.
+    async fn end_game(&self, game: Game) {
+        let (red_score, blue_score) = game.score();
+        let completed = game.is_over();
+        self.event_log.log(Event::GameEnded {
+            game_id: game.id,
+            red_score,
+            blue_score,
+            completed,
+        });
+
+        let status = if completed {
+            self.metrics.record_match_completed();
+            let (red_name, blue_name) = game.names();
+            if !game.is_against_ai() {
+                let (red_client_id, blue_client_id) = game.client_ids();
+                let red_identity = self.identity_of(red_client_id).await;
+                let blue_identity = self.identity_of(blue_client_id).await;
+                let (red_stats, blue_stats) = game.stats();
+
+                // Only players with a key can be told apart between sessions.
+                if let (Some(red_identity), Some(blue_identity)) = (red_identity, blue_identity) {
+                    let red = MatchPlayer {
+                        identity: red_identity,
+                        name: red_name.to_string(),
+                        score: red_score,
+                        stats: red_stats,
+                    };
+                    let blue = MatchPlayer {
+                        identity: blue_identity,
+                        name: blue_name.to_string(),
+                        score: blue_score,
+                        stats: blue_stats,
+                    };
+                    self.leaderboard.lock().await.record_match(&red, &blue);
+                    self.ladder.lock().await.record_match(&red, &blue);
+                }
+            }
+            format!("Last game: {red_name} {red_score} - {blue_score} {blue_name}")
+        } else {
+            "The game was interrupted".to_string()
+        };
+        self.record_tournament_result(game.id, completed.then_some((red_score, blue_score)))
+            .await;
+
+        // Bots still connected wait for their next match.
+        for (_, bot) in game.bots() {
+            if bot.is_connected() {
+                bot.send(&BotMessage::End {
+                    score: (red_score, blue_score),
+                    completed,
+                });
+                bot.send(&BotMessage::Waiting);
+                self.bots.lock().await.push(bot);
+            }
+        }
+
+        // Players who already left are back in the lobby and may be doing something else.
+        let mut returning = vec![];
+        {
+            let mut clients_to_game = self.clients_to_game.lock().await;
+            for client_id in game.human_client_ids() {
+                if clients_to_game.get(&client_id) == Some(&game.id) {
+                    clients_to_game.remove(&client_id);
+                    returning.push(client_id);
+                }
+            }
+        }
+        {
+            let mut spectators = self.spectators.lock().await;
+            for client_id in game.spectator_ids() {
+                if spectators.remove(&client_id).is_some() {
+                    returning.push(client_id);
+                }
+            }
+        }
+        let mut lobbies = self.lobbies.lock().await;
+        for client_id in returning {
+            if let Some(lobby) = lobbies.get_mut(&client_id) {
+                lobby.resume(status.as_str());
+            }
+        }
+    }
+
+    /// Creates the game client for a player, with the settings chosen in the lobby.
+    async fn game_client(&self, client_id: usize) -> Result<Client, anyhow::Error> {
+        let terminal_handle = self
+            .clients
+            .lock()
+            .await
+            .get(&client_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Client {client_id} is not connected"))?;
+        let lobbies = self.lobbies.lock().await;
+        let settings = lobbies
+            .get(&client_id)
+            .ok_or_else(|| anyhow::anyhow!("Client {client_id} has no lobby"))?
+            .settings();
+        Ok(Client::new(
+            client_id,
+            settings.name.clone(),
+            settings.palette,
+            new_terminal(terminal_handle)?,
+        ))
+    }
+
+    /// Starts a game between two players, or against the AI if there is no blue player.
+    async fn start_game(
+        &self,
+        red_client_id: usize,
+        blue_client_id: Option<usize>,
+    ) -> Result<uuid::Uuid, anyhow::Error> {
+        let red_client = self.game_client(red_client_id).await?;
+        let blue_client = match blue_client_id {
+            Some(client_id) => self.game_client(client_id).await?,
+            None => Client::ai(),
+        };
+        Ok(self
+            .add_game(Game::new(red_client, blue_client, self.config.restitution))
+            .await)
+    }
+
+    /// Registers a new game, taking its players out of the lobby.
+    async fn add_game(&self, game: Game) -> uuid::Uuid {
+        let game_id = game.id;
+        let (red_client_id, blue_client_id) = game.client_ids();
+
+        self.event_log.log(Event::GameCreated {
+            game_id: game.id,
+            red_client_id,
+            blue_client_id,
+        });
+        let (red_name, blue_name) = game.names();
+        for (side, bot) in game.bots() {
+            let opponent = match side {
+                GameSide::Red => blue_name,
+                GameSide::Blue => red_name,
+            };
+            bot.send(&BotMessage::Start { side, opponent });
+        }
+        {
+            let mut lobbies = self.lobbies.lock().await;
+            let mut clients_to_game = self.clients_to_game.lock().await;
+            for client_id in game.human_client_ids() {
+                if let Some(lobby) = lobbies.get_mut(&client_id) {
+                    lobby.pause();
+                }
+                clients_to_game.insert(client_id, game.id);
+            }
+        }
+        let mut games = self.games.lock().await;
+        games.insert(game.id, game);
+        let number_of_games = games.len();
+        log::info!(
+            "Started new game. There {} now {} game{} running",
+            if number_of_games == 1 { "is" } else { "are" },
+            number_of_games,
+            if number_of_games == 1 { "" } else { "s" }
+        );
+        game_id
+    }
+
+    /// Pairs the waiting bots that asked to play against other bots.
+    async fn start_bot_games(&self) {
+        let pairing = {
+            let mut bots = self.bots.lock().await;
+            bots.retain(|bot| bot.is_connected());
+            let willing = bots
+                .iter()
+                .enumerate()
+                .filter(|(_, bot)| bot.versus_bots)
+                .map(|(idx, _)| idx)
+                .take(2)
+                .collect::<Vec<usize>>();
+            match willing[..] {
+                [red, blue] => {
+                    let blue = bots.remove(blue);
+                    let red = bots.remove(red);
+                    Some((red, blue))
+                }
+                _ => None,
+            }
+        };
+        if let Some((red, blue)) = pairing {
+            log::info!(
+                "Starting a game between bots {} and {}",
+                red.name,
+                blue.name
+            );
+            self.add_game(Game::new(
+                Client::bot(red),
+                Client::bot(blue),
+                self.config.restitution,
+            ))
+            .await;
+        }
+    }
+
+    async fn create_tournament(&self, format: TournamentFormat, size: usize) -> usize {
+        let mut tournaments = self.tournaments.lock().await;
+        let id = tournaments
+            .last()
+            .map(|tournament| tournament.id + 1)
+            .unwrap_or(1);
+        tournaments.push(Tournament::new(id, format, size));
+        log::info!(
+            "Created tournament {id}: {} for {size} players",
+            format.name()
+        );
+        id
+    }
+
+    /// Starts the tournament games whose players are both idle in the lobby.
+    async fn start_tournament_games(&self) {
+        let ready = self
+            .tournaments
+            .lock()
+            .await
+            .iter()
+            .flat_map(|tournament| tournament.ready_pairings())
+            .collect::<Vec<_>>();
+        if ready.is_empty() {
+            return;
+        }
+
+        let identities = self.identities.lock().await.clone();
+        let mut busy = self
+            .clients_to_game
+            .lock()
+            .await
+            .keys()
+            .chain(self.spectators.lock().await.keys())
+            .copied()
+            .collect::<HashSet<usize>>();
+        busy.extend(
+            self.pending_client
+                .lock()
+                .await
+                .map(|(client_id, _)| client_id),
+        );
+        busy.extend(self.rooms.lock().await.values().copied());
+        let connected = self
+            .lobbies
+            .lock()
+            .await
+            .keys()
+            .copied()
+            .collect::<HashSet<usize>>();
+
+        for pairing in ready {
+            let (Some(&red_client_id), Some(&blue_client_id)) = (
+                identities.get(&pairing.red.identity),
+                identities.get(&pairing.blue.identity),
+            ) else {
+                continue;
+            };
+            if ![red_client_id, blue_client_id]
+                .iter()
+                .all(|client_id| connected.contains(client_id) && !busy.contains(client_id))
+            {
+                continue;
+            }
+            match self.start_game(red_client_id, Some(blue_client_id)).await {
+                Ok(game_id) => {
+                    busy.extend([red_client_id, blue_client_id]);
+                    if let Some(tournament) = self
+                        .tournaments
+                        .lock()
+                        .await
+                        .iter_mut()
+                        .find(|tournament| tournament.id == pairing.tournament_id)
+                    {
+                        tournament.set_game(pairing.round, pairing.index, game_id);
+                    }
+                    if let Some(game) = self.games.lock().await.get_mut(&game_id) {
+                        game.set_banner(&pairing.label);
+                    }
+                    log::info!(
+                        "Started {}: {} vs {}",
+                        pairing.label,
+                        pairing.red.name,
+                        pairing.blue.name
+                    );
+                }
+                Err(e) => log::error!("Failed to start tournament game: {e}"),
+            }
+        }
+    }
+
+    async fn record_tournament_result(&self, game_id: uuid::Uuid, score: Option<(u8, u8)>) {
+        let mut tournaments = self.tournaments.lock().await;
+        let Some(tournament) = tournaments
+            .iter_mut()
+            .find(|tournament| tournament.has_game(game_id))
+        else {
+            return;
+        };
+        tournament.record_result(game_id, score);
+        let announcement = tournament
+            .champion()
+            .map(|champion| format!("{} won {}!", champion.name, tournament.name()));
+
+        let finished = tournaments
+            .iter()
+            .filter(|tournament| tournament.is_finished())
+            .count();
+        if finished > MAX_FINISHED_TOURNAMENTS {
+            if let Some(idx) = tournaments
+                .iter()
+                .position(|tournament| tournament.is_finished())
+            {
+                tournaments.remove(idx);
+            }
+        }
+        drop(tournaments);
+
+        if let Some(announcement) = announcement {
+            log::info!("{announcement}");
+            for lobby in self.lobbies.lock().await.values_mut() {
+                lobby.set_status(announcement.as_str());
+            }
+        }
+    }
+
+    async fn create_room(&self) -> String {
+        let mut rooms = self.rooms.lock().await;
+        let mut rng = rand::thread_rng();
+        loop {
+            let code = (0..ROOM_CODE_LENGTH)
+                .map(|_| ROOM_CODE_CHARACTERS[rng.gen_range(0..ROOM_CODE_CHARACTERS.len())] as char)
+                .collect::<String>();
+            if !rooms.contains_key(&code) {
+                rooms.insert(code.clone(), self.client_id);
+                return code;
+            }
+        }
+    }
+
+    /// Takes the client out of the quick match queue and closes its private rooms.
+    async fn stop_waiting(&self) {
+        let mut pending_client = self.pending_client.lock().await;
+        if pending_client.is_some_and(|(client_id, _)| client_id == self.client_id) {
+            *pending_client = None;
+        }
+        drop(pending_client);
+        self.rooms
+            .lock()
+            .await
+            .retain(|_, client_id| *client_id != self.client_id);
+    }
+
+    async fn set_lobby_status(&self, status: &str) {
+        if let Some(lobby) = self.lobbies.lock().await.get_mut(&self.client_id) {
+            lobby.set_status(status);
+        }
+    }
+
+    async fn handle_lobby_action(
+        &mut self,
+        action: LobbyAction,
+        session: &mut Session,
+        channel: ChannelId,
+    ) -> Result<(), anyhow::Error> {
+        match action {
+            LobbyAction::QuickMatch => {
+                let mut pending_client = self.pending_client.lock().await;
+                match *pending_client {
+                    Some((client_id, _)) if client_id != self.client_id => {
+                        *pending_client = None;
+                        drop(pending_client);
+                        self.start_game(client_id, Some(self.client_id)).await?;
+                    }
+                    _ => {
+                        *pending_client = Some((self.client_id, Instant::now()));
+                        drop(pending_client);
+                        if let Some(lobby) = self.lobbies.lock().await.get_mut(&self.client_id) {
+                            lobby.wait(None);
+                        }
+                        log::info!("Added player to pending list");
+                    }
+                }
+            }
+            LobbyAction::CreateRoom => {
+                let code = self.create_room().await;
+                if let Some(lobby) = self.lobbies.lock().await.get_mut(&self.client_id) {
+                    lobby.wait(Some(code.clone()));
+                }
+                log::info!("Client {} created room {code}", self.client_id);
+            }
+            LobbyAction::JoinRoom(code) => {
+                let host_id = self.rooms.lock().await.get(&code).copied();
+                match host_id {
+                    Some(host_id) if host_id != self.client_id => {
+                        self.rooms.lock().await.remove(&code);
+                        self.start_game(host_id, Some(self.client_id)).await?;
+                    }
+                    _ => {
+                        self.set_lobby_status(&format!("There is no room with code {code}"))
+                            .await
+                    }
+                }
+            }
+            LobbyAction::PlayAi => {
+                self.start_game(self.client_id, None).await?;
+            }
+            LobbyAction::PlayScript(name) => {
+                let Some(script) = self
+                    .ai_scripts
+                    .iter()
+                    .find(|script| script.name == name)
+                    .cloned()
+                else {
+                    return Ok(());
+                };
+                let red_client = self.game_client(self.client_id).await?;
+                self.add_game(Game::new(
+                    red_client,
+                    Client::script(script),
+                    self.config.restitution,
+                ))
+                .await;
+            }
+            LobbyAction::PlayBot(bot_id) => {
+                let red_client = self.game_client(self.client_id).await?;
+                let bot = {
+                    let mut bots = self.bots.lock().await;
+                    bots.iter()
+                        .position(|bot| bot.id == bot_id && bot.is_connected())
+                        .map(|idx| bots.remove(idx))
+                };
+                match bot {
+                    Some(bot) => {
+                        self.add_game(Game::new(
+                            red_client,
+                            Client::bot(bot),
+                            self.config.restitution,
+                        ))
+                        .await;
+                    }
+                    None => {
+                        self.set_lobby_status("The bot is not available anymore")
+                            .await
+                    }
+                }
+            }
+            LobbyAction::Spectate(game_id) => {
+                let spectator = self.game_client(self.client_id).await?;
+                let is_spectating = match self.games.lock().await.get_mut(&game_id) {
+                    Some(game) => {
+                        game.add_spectator(spectator);
+                        true
+                    }
+                    None => false,
+                };
+                if is_spectating {
+                    self.spectators.lock().await.insert(self.client_id, game_id);
+                    if let Some(lobby) = self.lobbies.lock().await.get_mut(&self.client_id) {
+                        lobby.pause();
+                    }
+                } else {
+                    self.set_lobby_status("The game has already ended").await;
+                }
+            }
+            LobbyAction::CreateTournament { format, size } => {
+                let Some(identity) = self.identity.clone() else {
+                    self.set_lobby_status("Tournaments are only open to players with an ssh key")
+                        .await;
+                    return Ok(());
+                };
+                let id = self.create_tournament(format, size).await;
+                let name = self.player_name().await;
+                if let Some(tournament) = self
+                    .tournaments
+                    .lock()
+                    .await
+                    .iter_mut()
+                    .find(|tournament| tournament.id == id)
+                {
+                    tournament.toggle_registration(&identity, &name);
+                }
+                self.set_lobby_status(&format!("Created tournament {id}, you are registered"))
+                    .await;
+            }
+            LobbyAction::ToggleRegistration(id) => {
+                let Some(identity) = self.identity.clone() else {
+                    self.set_lobby_status("Tournaments are only open to players with an ssh key")
+                        .await;
+                    return Ok(());
+                };
+                let name = self.player_name().await;
+                let registered = self
+                    .tournaments
+                    .lock()
+                    .await
+                    .iter_mut()
+                    .find(|tournament| tournament.id == id)
+                    .and_then(|tournament| tournament.toggle_registration(&identity, &name));
+                let status = match registered {
+                    Some(true) => format!("You joined tournament {id}"),
+                    Some(false) => format!("You left tournament {id}"),
+                    None => format!("Tournament {id} is closed"),
+                };
+                self.set_lobby_status(&status).await;
+            }
+            LobbyAction::ShowProfile => {
+                let profile = match &self.identity {
+                    Some(identity) => self.leaderboard.lock().await.career(identity).cloned(),
+                    None => None,
+                };
+                if let Some(lobby) = self.lobbies.lock().await.get_mut(&self.client_id) {
+                    lobby.show_profile(profile);
+                }
+            }
+            LobbyAction::CancelWaiting => self.stop_waiting().await,
+            LobbyAction::Chat(text) => {
+                let name = self.player_name().await;
+                let result = self.chat.lock().await.post(
+                    self.client_id,
+                    self.identity.as_deref(),
+                    &name,
+                    &text,
+                );
+                match result {
+                    Ok(text) => log::info!("Client {} said: {text}", self.client_id),
+                    Err(ChatRejection::Muted) => {
+                        self.set_lobby_status("You have been muted by an admin")
+                            .await
+                    }
+                    Err(ChatRejection::TooManyMessages) => {
+                        self.set_lobby_status("You are sending messages too fast, slow down")
+                            .await
+                    }
+                }
+            }
+            LobbyAction::Quit => self.close_session(session, channel).await?,
+        }
+        Ok(())
+    }
+
+    async fn player_name(&self) -> String {
+        self.lobbies
+            .lock()
+            .await
+            .get(&self.client_id)
+            .map(|lobby| lobby.settings().name.clone())
+            .unwrap_or_default()
+    }
+
+    async fn resume_lobby(&self, status: &str) {
+        if let Some(lobby) = self.lobbies.lock().await.get_mut(&self.client_id) {
+            lobby.resume(status);
+        }
+    }
+
+    async fn kick_client(&self, client_id: usize) -> bool {
+        let Some(terminal_handle) = self.clients.lock().await.remove(&client_id) else {
+            return false;
+        };
+        self.remove_client(client_id).await;
+        let _ = terminal_handle.close().await;
+        log::info!("Client {client_id} was kicked");
+        true
+    }
+
+    /// Removes the client from its lobby, game, queue and rooms.
+    async fn remove_client(&self, client_id: usize) {
+        self.lobbies.lock().await.remove(&client_id);
+        if let Some(game_id) = self.clients_to_game.lock().await.remove(&client_id) {
+            if let Some(game) = self.games.lock().await.get_mut(&game_id) {
+                game.leave(client_id);
+            }
+        }
+        if let Some(game_id) = self.spectators.lock().await.remove(&client_id) {
+            if let Some(game) = self.games.lock().await.get_mut(&game_id) {
+                game.remove_spectator(client_id);
+            }
+        }
+        let mut pending_client = self.pending_client.lock().await;
+        if pending_client.is_some_and(|(id, _)| id == client_id) {
+            *pending_client = None;
+            log::info!("Removed player from pending list");
+        }
+        drop(pending_client);
+        self.rooms.lock().await.retain(|_, id| *id != client_id);
+        self.chat.lock().await.remove_client(client_id);
+    }
+
+    async fn broadcast(&self, text: &str) {
+        for lobby in self.lobbies.lock().await.values_mut() {
+            lobby.set_status(text);
+        }
+        for game in self.games.lock().await.values_mut() {
+            game.set_banner(text);
+        }
+        log::info!("Broadcasted message: {text}");
+    }
+
+    async fn handle_admin_action(&mut self, action: AdminAction) -> String {
+        match action {
+            AdminAction::Kick(client_id) => {
+                if self.kick_client(client_id).await {
+                    format!("Kicked client {client_id}")
+                } else {
+                    format!("Client {client_id} is not connected")
+                }
+            }
+            AdminAction::EndGame(game_id) => {
+                if let Some(game) = self.games.lock().await.get_mut(&game_id) {
+                    game.end();
+                    format!("Ended game {game_id}")
+                } else {
+                    format!("Game {game_id} is not running")
+                }
+            }
+            AdminAction::Mute(client_id) => {
+                let identity = self.identity_of(client_id).await;
+                if self
+                    .chat
+                    .lock()
+                    .await
+                    .toggle_mute(client_id, identity.as_deref())
+                {
+                    format!("Muted client {client_id}")
+                } else {
+                    format!("Unmuted client {client_id}")
+                }
+            }
+            AdminAction::CreateTournament { format, size } => {
+                let id = self.create_tournament(format, size).await;
+                format!("Created tournament {id}, players can join it from the lobby")
+            }
+            AdminAction::Broadcast(text) => {
+                self.broadcast(&text).await;
+                "Message broadcasted".to_string()
+            }
+            AdminAction::Quit => String::new(),
+        }
+    }
+
+    /// Warns the players that the server is going down, optionally lets the running games finish,
+    /// then closes every channel. New connections must be stopped by the caller.
+    pub async fn shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+        let notice = Duration::from_secs(self.config.shutdown_notice_seconds);
+        let wait_for_games = self.config.shutdown_wait_for_games;
+        let deadline = Instant::now()
+            + if wait_for_games {
+                notice.max(Duration::from_secs(self.config.shutdown_deadline_seconds))
+            } else {
+                notice
+            };
+
+        for lobby in self.lobbies.lock().await.values_mut() {
+            lobby.set_status(format!(
+                "The {GAME_NAME} server is restarting, see you soon!"
+            ));
+        }
+
+        loop {
+            let remaining_seconds = deadline
+                .saturating_duration_since(Instant::now())
+                .as_secs_f64()
+                .ceil() as u64;
+            let mut games = self.games.lock().await;
+            if remaining_seconds == 0 || (wait_for_games && games.is_empty()) {
+                break;
+            }
+            for game in games.values_mut() {
+                if wait_for_games && (game.remaining_seconds() as u64) < remaining_seconds {
+                    game.set_banner("Server restarting after this match");
+                } else {
+                    game.set_banner(&format!("Server restarting in {remaining_seconds} seconds"));
+                }
+            }
+            drop(games);
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        for (game_id, game) in self.games.lock().await.drain() {
+            let (red_score, blue_score) = game.score();
+            self.event_log.log(Event::GameEnded {
+                game_id,
+                red_score,
+                blue_score,
+                completed: game.is_over(),
+            });
+        }
+
+        let mut terminal_handles = self
+            .clients
+            .lock()
+            .await
+            .values()
+            .cloned()
+            .collect::<Vec<TerminalHandle>>();
+        terminal_handles.extend(
+            self.admins
+                .lock()
+                .await
+                .values()
+                .map(|admin| admin.terminal_handle().clone()),
+        );
+        log::info!("Closing {} channels", terminal_handles.len());
+        for terminal_handle in terminal_handles {
+            let _ = terminal_handle.eof().await;
+            let _ = terminal_handle.close().await;
+        }
+        // Give the sessions some time to send the close messages.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    /// Serves the server metrics in the Prometheus text format on a local port.
+    pub async fn serve_metrics(&self, port: u16) -> Result<(), anyhow::Error> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+        log::info!("Serving metrics on port {}", port);
+        loop {
+            let (mut stream, _) = listener.accept().await?;
+            // Metrics are served on any path, so the request is not parsed.
+            let mut buffer = [0; 1024];
+            if stream.read(&mut buffer).await.is_err() {
+                continue;
+            }
+            let body = self.metrics.render(&self.snapshot().await);
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream
+                .write_all(response.as_bytes())
+                .await
+                .unwrap_or_else(|e| log::error!("Failed to send metrics: {e}"));
+        }
+    }
+
+    /// Lets bots play over newline delimited JSON on a local port.
+    pub async fn serve_bots(&self, port: u16) -> Result<(), anyhow::Error> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+        log::info!("Serving bots on port {}", port);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let id = self.next_bot_id.fetch_add(1, Ordering::Relaxed);
+            let bots = self.bots.clone();
+            tokio::spawn(async move {
+                match BotLink::connect(stream, id).await {
+                    Ok(bot) => {
+                        log::info!("Bot {} connected with id {id}", bot.name);
+                        bot.send(&BotMessage::Waiting);
+                        bots.lock().await.push(bot);
+                    }
+                    Err(e) => log::warn!("Failed to connect bot: {e}"),
+                }
+            });
+        }
+    }
+
+    /// Banned and flooding clients are rejected at authentication,
+    /// while a full server still lets clients in to tell them so.
+    fn is_rejected(&self) -> bool {
+        matches!(
+            self.rejection,
+            Some(Rejection::Banned) | Some(Rejection::TooManyConnections)
+        )
+    }
+
     async fn close_session(
         &mut self,
         session: &mut Session,
         channel: ChannelId,
     ) -> Result<(), anyhow::Error> {
+        self.admins.lock().await.remove(&self.client_id);
         self.clients.lock().await.remove(&self.client_id);
-        self.clients_to_game.lock().await.remove(&self.client_id);
+        self.remove_client(self.client_id).await;
+
+        // The player quit on purpose, so there is no game to rejoin.
+        if let Some(identity) = &self.identity {
+            let mut identities = self.identities.lock().await;
+            if identities.get(identity) == Some(&self.client_id) {
+                identities.remove(identity);
+            }
+        }
 
         session.eof(channel);
         session.disconnect(russh::Disconnect::ByApplication, "Quit", "");
         session.close(channel);
+        Ok(())
+    }
 
-        let mut pending_client = self.pending_client.lock().await;
-        if pending_client.is_some() && pending_client.unwrap().0 == self.client_id {
-            *pending_client = None;
-            log::info!("Removed player from pending list");
+    /// Rebinds the terminal to the game the identity was playing in before its connection dropped.
+    /// Returns false if there is no game to rejoin.
+    async fn try_reconnect(
+        &mut self,
+        terminal_handle: &TerminalHandle,
+    ) -> Result<bool, anyhow::Error> {
+        let Some(identity) = self.identity.clone() else {
+            return Ok(false);
+        };
+        let Some(old_client_id) = self.identities.lock().await.get(&identity).copied() else {
+            return Ok(false);
+        };
+        let Some(game_id) = self
+            .clients_to_game
+            .lock()
+            .await
+            .get(&old_client_id)
+            .copied()
+        else {
+            return Ok(false);
+        };
+
+        if let Some(game) = self.games.lock().await.get_mut(&game_id) {
+            if !game.can_reconnect(old_client_id) {
+                return Ok(false);
+            }
+            game.reconnect(
+                old_client_id,
+                self.client_id,
+                new_terminal(terminal_handle.clone())?,
+            );
+        } else {
+            return Ok(false);
         }
-        Ok(())
+
+        let mut clients_to_game = self.clients_to_game.lock().await;
+        clients_to_game.remove(&old_client_id);
+        clients_to_game.insert(self.client_id, game_id);
+        self.identities
+            .lock()
+            .await
+            .insert(identity, self.client_id);
+        log::info!(
+            "Client {} reconnected to game {} as client {}",
+            old_client_id,
+            game_id,
+            self.client_id
+        );
+        Ok(true)
     }
 }
 
 impl Server for GameServer {
     type Handler = Self;
-    fn new_client(&mut self, _: Option<std::net::SocketAddr>) -> Self {
-        let s = self.clone();
+    fn new_client(&mut self, peer: Option<SocketAddr>) -> Self {
+        let mut s = self.clone();
+        s.peer = peer;
+        if let Ok(mut access_control) = self.access_control.lock() {
+            s.rejection = access_control.open_session(peer.map(|peer| peer.ip()));
+        }
+        if let Some(rejection) = s.rejection {
+            log::warn!("Rejecting connection from {:?}: {:?}", peer, rejection);
+        }
+        s.connection = Some(Arc::new(Connection {
+            client_id: s.client_id,
+            peer,
+            opened_at: Instant::now(),
+            event_log: self.event_log.clone(),
+            access_control: self.access_control.clone(),
+        }));
         self.client_id += 1;
         s
     }
@@ -219,92 +1368,167 @@ impl Handler for GameServer {
     ) -> Result<bool, Self::Error> {
         {
             log::info!("Opening new session");
+            self.event_log.log(Event::SessionOpened {
+                client_id: self.client_id,
+                peer: self.peer.map(|peer| peer.to_string()),
+                identity: self.identity.clone(),
+            });
             let mut terminal_handle = TerminalHandle::new(session.handle(), channel.id());
-            let backend = CrosstermBackend::new(terminal_handle.clone());
-            let terminal = Terminal::with_options(
-                backend,
-                ratatui::TerminalOptions {
-                    viewport: ratatui::Viewport::Fixed(Rect {
-                        x: 0,
-                        y: 0,
-                        width: TERMINAL_WIDTH,
-                        height: TERMINAL_HEIGHT,
-                    }),
-                },
-            )?;
-
-            let mut clients = self.clients.lock().await;
-            let mut pending_client_id = self.pending_client.lock().await;
-
-            if pending_client_id.is_some() {
-                let client_id = pending_client_id.as_ref().unwrap().0.clone();
-                let pending_handle = clients.get(&client_id).unwrap();
-                let backend = CrosstermBackend::new(pending_handle.clone());
-                let pending_terminal = Terminal::with_options(
-                    backend,
-                    ratatui::TerminalOptions {
-                        viewport: ratatui::Viewport::Fixed(Rect {
-                            x: 0,
-                            y: 0,
-                            width: TERMINAL_WIDTH,
-                            height: TERMINAL_HEIGHT,
-                        }),
-                    },
+
+            if self.rejection == Some(Rejection::ServerFull) {
+                terminal_handle.message(
+                    format!("Sorry, the {GAME_NAME} server is full! Please try again later.\r\n")
+                        .as_str(),
                 )?;
-                clients.insert(self.client_id, terminal_handle);
-                let game = Game::new(
-                    (client_id.clone(), pending_terminal),
-                    (self.client_id, terminal),
-                );
+                session.eof(channel.id());
+                session.disconnect(russh::Disconnect::ByApplication, "Server full", "");
+                session.close(channel.id());
+                return Ok(true);
+            }
 
-                self.games.lock().await.insert(game.id, game.clone());
-                let number_of_games = self.games.lock().await.len();
-                self.clients_to_game.lock().await.insert(client_id, game.id);
-                self.clients_to_game
-                    .lock()
-                    .await
-                    .insert(self.client_id, game.id);
-                log::info!(
-                    "Added player to new game. There {} now {} game{} running",
-                    if number_of_games == 1 { "is" } else { "are" },
-                    number_of_games,
-                    if number_of_games == 1 { "" } else { "s" }
-                );
-                *pending_client_id = None;
-            } else {
-                *pending_client_id = Some((self.client_id, Instant::now()));
-                log::info!("Added player to pending list");
+            if self.shutting_down.load(Ordering::Relaxed) {
                 terminal_handle.message(
-                    format!(
-                        "Welcome to the {GAME_NAME}! Waiting for another player to join...\r\nIn the meanwhile, remember to set your terminal to a minimum of {TERMINAL_WIDTH}x{TERMINAL_HEIGHT} characters.\r\n\r\nPress Esc to close the game. Your connection will be closed after {INACTIVITY_TIMEOUT} seconds of inactivity.\r\n",
-                    )
-                    .as_str(),
+                    format!("Sorry, the {GAME_NAME} server is restarting! Please try again in a while.\r\n")
+                        .as_str(),
                 )?;
-                clients.insert(self.client_id, terminal_handle);
+                session.eof(channel.id());
+                session.disconnect(russh::Disconnect::ByApplication, "Server restarting", "");
+                session.close(channel.id());
+                return Ok(true);
+            }
+
+            if let Some(identity) = &self.identity {
+                if self.config.is_admin(identity) {
+                    log::info!("Admin {identity} opened the admin console");
+                    let admin =
+                        AdminConsole::new(new_terminal(terminal_handle.clone())?, terminal_handle);
+                    self.admins.lock().await.insert(self.client_id, admin);
+                    return Ok(true);
+                }
+            }
+
+            // Register the handle before rejoining so that the game loop does not
+            // consider the new client id as disconnected.
+            self.clients
+                .lock()
+                .await
+                .insert(self.client_id, terminal_handle.clone());
+            let mut lobby = Lobby::new(new_terminal(terminal_handle.clone())?, &self.username);
+            if self.try_reconnect(&terminal_handle).await? {
+                lobby.pause();
+            } else if let Some(identity) = &self.identity {
+                self.identities
+                    .lock()
+                    .await
+                    .insert(identity.clone(), self.client_id);
             }
+            self.lobbies.lock().await.insert(self.client_id, lobby);
         }
 
         Ok(true)
     }
 
-    async fn auth_none(&mut self, _: &str) -> Result<Auth, Self::Error> {
-        Ok(Auth::Accept)
+    async fn auth_none(&mut self, user: &str) -> Result<Auth, Self::Error> {
+        self.username = user.to_string();
+        if self.is_rejected() {
+            return Ok(Auth::Reject {
+                proceed_with_methods: None,
+            });
+        }
+        // Ask for a public key first so that we can recognize players who reconnect.
+        // Clients without keys can still join with the other methods allowed by the policy.
+        let proceed_with_methods = match self.config.auth {
+            AuthPolicy::Open => MethodSet::PUBLICKEY | MethodSet::KEYBOARD_INTERACTIVE,
+            AuthPolicy::Password { .. } => MethodSet::PUBLICKEY | MethodSet::PASSWORD,
+            AuthPolicy::Allowlist { .. } => MethodSet::PUBLICKEY,
+        };
+        Ok(Auth::Reject {
+            proceed_with_methods: Some(proceed_with_methods),
+        })
     }
 
-    async fn auth_password(&mut self, _: &str, _: &str) -> Result<Auth, Self::Error> {
+    async fn auth_password(&mut self, user: &str, password: &str) -> Result<Auth, Self::Error> {
+        self.username = user.to_string();
+        if self.is_rejected() {
+            return Ok(Auth::Reject {
+                proceed_with_methods: None,
+            });
+        }
+        let is_accepted = match &self.config.auth {
+            AuthPolicy::Open => true,
+            // Compared in constant time, so timing does not give away the password.
+            AuthPolicy::Password {
+                password: server_password,
+            } => password.as_bytes().ct_eq(server_password.as_bytes()).into(),
+            AuthPolicy::Allowlist { .. } => false,
+        };
+        if !is_accepted {
+            log::warn!("Rejecting wrong password from {:?}", self.peer);
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(MethodSet::PASSWORD),
+            });
+        }
         Ok(Auth::Accept)
     }
 
-    async fn auth_publickey(&mut self, _: &str, _: &PublicKey) -> Result<Auth, Self::Error> {
+    async fn auth_publickey(&mut self, user: &str, key: &PublicKey) -> Result<Auth, Self::Error> {
+        self.username = user.to_string();
+        let fingerprint = key.fingerprint();
+        let is_allowed = match self.access_control.lock() {
+            Ok(mut access_control) => {
+                if access_control.is_key_banned(&fingerprint) {
+                    log::warn!("Rejecting banned key {fingerprint}");
+                    self.rejection = Some(Rejection::Banned);
+                }
+                // Admins can always log in with their key.
+                match self.config.auth {
+                    AuthPolicy::Open => true,
+                    AuthPolicy::Password { .. } => self.config.is_admin(&fingerprint),
+                    AuthPolicy::Allowlist { .. } => {
+                        self.config.is_admin(&fingerprint)
+                            || access_control.is_key_allowed(&fingerprint)
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to check key {fingerprint}: {e}");
+                false
+            }
+        };
+        if self.is_rejected() {
+            return Ok(Auth::Reject {
+                proceed_with_methods: None,
+            });
+        }
+        if !is_allowed {
+            return Ok(Auth::Reject {
+                proceed_with_methods: match self.config.auth {
+                    // The key is verified, so it stays the identity of the player
+                    // once the password is accepted.
+                    AuthPolicy::Password { .. } => {
+                        self.identity = Some(fingerprint);
+                        Some(MethodSet::PASSWORD)
+                    }
+                    _ => Some(MethodSet::PUBLICKEY),
+                },
+            });
+        }
+        self.identity = Some(fingerprint);
         Ok(Auth::Accept)
     }
 
     async fn auth_keyboard_interactive(
         &mut self,
-        _: &str,
+        user: &str,
         _: &str,
         _: Option<Response<'async_trait>>,
     ) -> Result<Auth, Self::Error> {
+        self.username = user.to_string();
+        if self.is_rejected() || self.config.auth != AuthPolicy::Open {
+            return Ok(Auth::Reject {
+                proceed_with_methods: None,
+            });
+        }
         Ok(Auth::Accept)
     }
 
@@ -317,10 +1541,16 @@ impl Handler for GameServer {
         _: u32,
         _: &mut Session,
     ) -> Result<(), Self::Error> {
-        if let Some(game_id) = &mut self.clients_to_game.lock().await.get_mut(&self.client_id) {
-            if let Some(game) = self.games.lock().await.get_mut(game_id) {
+        let game_id = match self.clients_to_game.lock().await.get(&self.client_id) {
+            Some(game_id) => Some(*game_id),
+            None => self.spectators.lock().await.get(&self.client_id).copied(),
+        };
+        if let Some(game_id) = game_id {
+            if let Some(game) = self.games.lock().await.get_mut(&game_id) {
                 game.clear_client(self.client_id);
             }
+        } else if let Some(lobby) = self.lobbies.lock().await.get_mut(&self.client_id) {
+            lobby.clear();
         }
         Ok(())
     }
@@ -333,30 +1563,76 @@ impl Handler for GameServer {
     ) -> Result<(), Self::Error> {
         let key_code = convert_data_to_key_code(data);
 
-        if key_code == KeyCode::Esc {
-            self.close_session(session, channel)
-                .await
-                .unwrap_or_else(|e| log::error!("Failed to close session: {:?}", e));
+        let admin_action = self
+            .admins
+            .lock()
+            .await
+            .get_mut(&self.client_id)
+            .map(|admin| admin.handle_input(key_code));
+        if let Some(action) = admin_action {
+            match action {
+                Some(AdminAction::Quit) => {
+                    self.close_session(session, channel)
+                        .await
+                        .unwrap_or_else(|e| log::error!("Failed to close session: {:?}", e));
+                }
+                Some(action) => {
+                    let status = self.handle_admin_action(action).await;
+                    if let Some(admin) = self.admins.lock().await.get_mut(&self.client_id) {
+                        admin.set_status(status);
+                    }
+                }
+                None => {}
+            }
             return Ok(());
         }
 
-        let pending_client = self.pending_client.lock().await;
-        if pending_client.is_some() && pending_client.unwrap().0 == self.client_id {
+        let spectated_game_id = self.spectators.lock().await.get(&self.client_id).copied();
+        if let Some(game_id) = spectated_game_id {
+            if key_code == KeyCode::Esc {
+                self.spectators.lock().await.remove(&self.client_id);
+                if let Some(game) = self.games.lock().await.get_mut(&game_id) {
+                    game.remove_spectator(self.client_id);
+                }
+                self.resume_lobby("").await;
+            } else if let Some(game) = self.games.lock().await.get_mut(&game_id) {
+                game.handle_spectator_input(self.client_id, key_code);
+            }
             return Ok(());
         }
 
-        if let Some(game_id) = &mut self.clients_to_game.lock().await.get_mut(&self.client_id) {
-            if let Some(game) = self.games.lock().await.get_mut(game_id) {
+        let game_id = self
+            .clients_to_game
+            .lock()
+            .await
+            .get(&self.client_id)
+            .copied();
+        if let Some(game_id) = game_id {
+            if key_code == KeyCode::Esc {
+                self.clients_to_game.lock().await.remove(&self.client_id);
+                if let Some(game) = self.games.lock().await.get_mut(&game_id) {
+                    game.leave(self.client_id);
+                }
+                self.resume_lobby("You left the game").await;
+            } else if let Some(game) = self.games.lock().await.get_mut(&game_id) {
                 game.handle_input(self.client_id, key_code);
-                return Ok(());
             }
+            return Ok(());
         }
 
-        self.clients.lock().await.remove(&self.client_id);
-        self.clients_to_game.lock().await.remove(&self.client_id);
-        session.eof(channel);
-        session.disconnect(russh::Disconnect::ByApplication, "Quit", "");
-        session.close(channel);
+        for key_code in convert_data_to_key_codes(data) {
+            let lobby_action = self
+                .lobbies
+                .lock()
+                .await
+                .get_mut(&self.client_id)
+                .and_then(|lobby| lobby.handle_input(key_code));
+            if let Some(action) = lobby_action {
+                self.handle_lobby_action(action, session, channel)
+                    .await
+                    .unwrap_or_else(|e| log::error!("Failed to handle lobby action: {:?}", e));
+            }
+        }
 
         Ok(())
     }
@@ -0,0 +1,534 @@
+use crate::leaderboard::PlayerRecord;
+use rand::seq::SliceRandom;
+
+pub const MIN_TOURNAMENT_PLAYERS: usize = 2;
+pub const MAX_TOURNAMENT_PLAYERS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TournamentFormat {
+    SingleElimination,
+    RoundRobin,
+}
+
+impl TournamentFormat {
+    pub fn next(&self) -> Self {
+        match self {
+            TournamentFormat::SingleElimination => TournamentFormat::RoundRobin,
+            TournamentFormat::RoundRobin => TournamentFormat::SingleElimination,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TournamentFormat::SingleElimination => "Single elimination",
+            TournamentFormat::RoundRobin => "Round robin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TournamentState {
+    Registering,
+    Running,
+    // The champion is an index into the entrants.
+    Finished { champion: usize },
+}
+
+/// A registered player, identified by the fingerprint of their key.
+#[derive(Debug, Clone)]
+pub struct Entrant {
+    pub identity: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Pairing {
+    // Indexes into the entrants, None while waiting for the winner of a previous round.
+    pub players: [Option<usize>; 2],
+    pub game_id: Option<uuid::Uuid>,
+    pub score: Option<(u8, u8)>,
+    // A first round pairing without an opponent, the player goes through.
+    pub is_bye: bool,
+}
+
+impl Pairing {
+    fn new(red: Option<usize>, blue: Option<usize>) -> Self {
+        Self {
+            players: [red, blue],
+            ..Default::default()
+        }
+    }
+
+    pub fn winner(&self) -> Option<usize> {
+        if self.is_bye {
+            return self.players[0].or(self.players[1]);
+        }
+        match self.score? {
+            (red, blue) if red > blue => self.players[0],
+            (red, blue) if blue > red => self.players[1],
+            _ => None,
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        !self.is_bye
+            && self.players.iter().all(|player| player.is_some())
+            && self.game_id.is_none()
+            && self.score.is_none()
+    }
+}
+
+/// A pairing whose players can be sent on the ice.
+#[derive(Debug, Clone)]
+pub struct ReadyPairing {
+    pub tournament_id: usize,
+    pub round: usize,
+    pub index: usize,
+    pub red: Entrant,
+    pub blue: Entrant,
+    pub label: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tournament {
+    pub id: usize,
+    pub format: TournamentFormat,
+    // Number of players, the tournament starts as soon as they are all registered.
+    pub size: usize,
+    entrants: Vec<Entrant>,
+    rounds: Vec<Vec<Pairing>>,
+    state: TournamentState,
+}
+
+impl Tournament {
+    pub fn new(id: usize, format: TournamentFormat, size: usize) -> Self {
+        Self {
+            id,
+            format,
+            size: size.clamp(MIN_TOURNAMENT_PLAYERS, MAX_TOURNAMENT_PLAYERS),
+            entrants: vec![],
+            rounds: vec![],
+            state: TournamentState::Registering,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        format!("Tournament {}", self.id)
+    }
+
+    pub fn entrants(&self) -> &[Entrant] {
+        &self.entrants
+    }
+
+    pub fn rounds(&self) -> &[Vec<Pairing>] {
+        &self.rounds
+    }
+
+    pub fn state(&self) -> &TournamentState {
+        &self.state
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, TournamentState::Finished { .. })
+    }
+
+    pub fn is_registered(&self, identity: &str) -> bool {
+        self.entrants
+            .iter()
+            .any(|entrant| entrant.identity == identity)
+    }
+
+    /// Registers the player, or withdraws them if they were registered.
+    /// Returns whether the player is now registered. Registration is closed once the tournament starts.
+    pub fn toggle_registration(&mut self, identity: &str, name: &str) -> Option<bool> {
+        if self.state != TournamentState::Registering {
+            return None;
+        }
+        if self.is_registered(identity) {
+            self.entrants.retain(|entrant| entrant.identity != identity);
+            return Some(false);
+        }
+        self.entrants.push(Entrant {
+            identity: identity.to_string(),
+            name: name.to_string(),
+        });
+        if self.entrants.len() >= self.size {
+            self.start();
+        }
+        Some(true)
+    }
+
+    fn start(&mut self) {
+        self.entrants.shuffle(&mut rand::thread_rng());
+        self.rounds = match self.format {
+            TournamentFormat::SingleElimination => self.elimination_rounds(),
+            TournamentFormat::RoundRobin => self.round_robin_rounds(),
+        };
+        self.state = TournamentState::Running;
+        self.advance();
+    }
+
+    fn elimination_rounds(&self) -> Vec<Vec<Pairing>> {
+        let bracket_size = self.entrants.len().next_power_of_two();
+        let half = bracket_size / 2;
+        // Fill the red side of every first round pairing first, so that byes never meet.
+        let first_round = (0..half)
+            .map(|idx| {
+                let blue = (half + idx < self.entrants.len()).then_some(half + idx);
+                Pairing {
+                    is_bye: blue.is_none(),
+                    ..Pairing::new(Some(idx), blue)
+                }
+            })
+            .collect::<Vec<Pairing>>();
+
+        let mut rounds = vec![first_round];
+        let mut length = half / 2;
+        while length > 0 {
+            rounds.push(vec![Pairing::default(); length]);
+            length /= 2;
+        }
+        rounds
+    }
+
+    /// Schedules every pairing with the circle method, so that nobody plays twice in a round.
+    fn round_robin_rounds(&self) -> Vec<Vec<Pairing>> {
+        let mut slots = (0..self.entrants.len()).map(Some).collect::<Vec<_>>();
+        if slots.len() % 2 == 1 {
+            slots.push(None);
+        }
+        let length = slots.len();
+        let mut rounds = vec![];
+        for _ in 0..length - 1 {
+            let round = (0..length / 2)
+                .filter_map(|idx| match (slots[idx], slots[length - 1 - idx]) {
+                    (Some(red), Some(blue)) => Some(Pairing::new(Some(red), Some(blue))),
+                    _ => None,
+                })
+                .collect();
+            rounds.push(round);
+            slots[1..].rotate_right(1);
+        }
+        rounds
+    }
+
+    /// Moves the winners on in the bracket and crowns the champion once every pairing is decided.
+    fn advance(&mut self) {
+        match self.format {
+            TournamentFormat::SingleElimination => {
+                for round in 0..self.rounds.len() - 1 {
+                    for index in 0..self.rounds[round].len() {
+                        if let Some(winner) = self.rounds[round][index].winner() {
+                            self.rounds[round + 1][index / 2].players[index % 2] = Some(winner);
+                        }
+                    }
+                }
+                if let Some(champion) = self.rounds.last().and_then(|round| round[0].winner()) {
+                    self.state = TournamentState::Finished { champion };
+                }
+            }
+            TournamentFormat::RoundRobin => {
+                let is_complete = self
+                    .rounds
+                    .iter()
+                    .flatten()
+                    .all(|pairing| pairing.score.is_some());
+                if let Some((champion, _)) = self.standings().first().filter(|_| is_complete) {
+                    self.state = TournamentState::Finished {
+                        champion: *champion,
+                    };
+                }
+            }
+        }
+    }
+
+    pub fn round_name(&self, round: usize) -> String {
+        if self.format == TournamentFormat::RoundRobin {
+            return format!("Round {}", round + 1);
+        }
+        match self.rounds.len() - round {
+            1 => "Final".to_string(),
+            2 => "Semifinals".to_string(),
+            3 => "Quarterfinals".to_string(),
+            _ => format!("Round {}", round + 1),
+        }
+    }
+
+    /// Pairings with both players known that have not been played yet.
+    pub fn ready_pairings(&self) -> Vec<ReadyPairing> {
+        if self.state != TournamentState::Running {
+            return vec![];
+        }
+        let mut ready = vec![];
+        for (round, pairings) in self.rounds.iter().enumerate() {
+            for (index, pairing) in pairings.iter().enumerate() {
+                if let [Some(red), Some(blue)] = pairing.players {
+                    if pairing.is_ready() {
+                        ready.push(ReadyPairing {
+                            tournament_id: self.id,
+                            round,
+                            index,
+                            red: self.entrants[red].clone(),
+                            blue: self.entrants[blue].clone(),
+                            label: format!("{} - {}", self.name(), self.round_name(round)),
+                        });
+                    }
+                }
+            }
+        }
+        ready
+    }
+
+    pub fn set_game(&mut self, round: usize, index: usize, game_id: uuid::Uuid) {
+        if let Some(pairing) = self
+            .rounds
+            .get_mut(round)
+            .and_then(|pairings| pairings.get_mut(index))
+        {
+            pairing.game_id = Some(game_id);
+        }
+    }
+
+    pub fn has_game(&self, game_id: uuid::Uuid) -> bool {
+        self.rounds
+            .iter()
+            .flatten()
+            .any(|pairing| pairing.game_id == Some(game_id))
+    }
+
+    /// Records the score of a tournament game. Interrupted games, and draws in an elimination
+    /// tournament, are played again.
+    pub fn record_result(&mut self, game_id: uuid::Uuid, score: Option<(u8, u8)>) {
+        let format = self.format;
+        let Some(pairing) = self
+            .rounds
+            .iter_mut()
+            .flatten()
+            .find(|pairing| pairing.game_id == Some(game_id))
+        else {
+            return;
+        };
+        pairing.game_id = None;
+        match score {
+            Some((red, blue)) if red == blue && format == TournamentFormat::SingleElimination => {}
+            Some(score) => pairing.score = Some(score),
+            None => {}
+        }
+        self.advance();
+    }
+
+    /// Round robin table, as entrant indexes with their record, sorted by points then goal difference.
+    pub fn standings(&self) -> Vec<(usize, PlayerRecord)> {
+        let mut records = self
+            .entrants
+            .iter()
+            .map(|entrant| PlayerRecord {
+                name: entrant.name.clone(),
+                ..Default::default()
+            })
+            .collect::<Vec<PlayerRecord>>();
+        for pairing in self.rounds.iter().flatten() {
+            if let ([Some(red), Some(blue)], Some((red_score, blue_score))) =
+                (pairing.players, pairing.score)
+            {
+                let red_name = self.entrants[red].name.clone();
+                let blue_name = self.entrants[blue].name.clone();
+                records[red].add_result(&red_name, red_score, blue_score);
+                records[blue].add_result(&blue_name, blue_score, red_score);
+            }
+        }
+        let mut standings = records.into_iter().enumerate().collect::<Vec<_>>();
+        standings.sort_by(|(_, a), (_, b)| {
+            b.points()
+                .cmp(&a.points())
+                .then(b.goal_difference().cmp(&a.goal_difference()))
+                .then(b.goals_for.cmp(&a.goals_for))
+        });
+        standings
+    }
+
+    pub fn champion(&self) -> Option<&Entrant> {
+        match self.state {
+            TournamentState::Finished { champion } => self.entrants.get(champion),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn full_tournament(format: TournamentFormat, size: usize) -> Tournament {
+        let mut tournament = Tournament::new(1, format, size);
+        for idx in 0..size {
+            assert_eq!(
+                tournament.toggle_registration(&format!("key{idx}"), &format!("player{idx}")),
+                Some(true)
+            );
+        }
+        assert_eq!(tournament.state(), &TournamentState::Running);
+        tournament
+    }
+
+    // Plays every ready pairing, the red player winning, until the tournament is over.
+    fn play_out(tournament: &mut Tournament) -> Vec<(usize, usize)> {
+        let index_of = |tournament: &Tournament, entrant: &Entrant| {
+            tournament
+                .entrants()
+                .iter()
+                .position(|other| other.identity == entrant.identity)
+                .unwrap()
+        };
+        let mut played = vec![];
+        while !tournament.is_finished() {
+            let ready = tournament.ready_pairings();
+            assert!(!ready.is_empty(), "The tournament is stuck");
+            for pairing in ready {
+                let game_id = uuid::Uuid::new_v4();
+                tournament.set_game(pairing.round, pairing.index, game_id);
+                assert!(tournament.has_game(game_id));
+                tournament.record_result(game_id, Some((2, 1)));
+                played.push((
+                    index_of(tournament, &pairing.red),
+                    index_of(tournament, &pairing.blue),
+                ));
+            }
+        }
+        played
+    }
+
+    #[test]
+    fn test_round_robin() {
+        for size in [2, 3, 5, 8] {
+            let mut tournament = full_tournament(TournamentFormat::RoundRobin, size);
+            let rounds = if size % 2 == 0 { size - 1 } else { size };
+            assert_eq!(tournament.rounds().len(), rounds);
+            for round in tournament.rounds() {
+                let players = round
+                    .iter()
+                    .flat_map(|pairing| pairing.players.iter().flatten())
+                    .collect::<Vec<_>>();
+                // Nobody plays twice in a round, and with an odd number of players one rests.
+                assert_eq!(players.iter().collect::<HashSet<_>>().len(), players.len());
+                assert_eq!(players.len(), size - size % 2);
+            }
+
+            let played = play_out(&mut tournament);
+            let pairs = played
+                .iter()
+                .map(|&(red, blue)| (red.min(blue), red.max(blue)))
+                .collect::<HashSet<_>>();
+            assert_eq!(played.len(), size * (size - 1) / 2);
+            assert_eq!(pairs.len(), played.len());
+
+            let standings = tournament.standings();
+            assert_eq!(
+                tournament.state(),
+                &TournamentState::Finished {
+                    champion: standings[0].0
+                }
+            );
+            assert!(tournament.champion().is_some());
+        }
+    }
+
+    #[test]
+    fn test_single_elimination() {
+        for size in [2, 3, 5, 8] {
+            let mut tournament = full_tournament(TournamentFormat::SingleElimination, size);
+            let bracket_size = size.next_power_of_two();
+            assert_eq!(1 << tournament.rounds().len(), bracket_size);
+            let byes = tournament.rounds()[0]
+                .iter()
+                .filter(|pairing| pairing.is_bye)
+                .collect::<Vec<_>>();
+            assert_eq!(byes.len(), bracket_size - size);
+            // Players with a bye are already in the second round.
+            for (index, pairing) in tournament.rounds()[0].iter().enumerate() {
+                if pairing.is_bye {
+                    assert_eq!(
+                        tournament.rounds()[1][index / 2].players[index % 2],
+                        pairing.players[0]
+                    );
+                }
+            }
+
+            let played = play_out(&mut tournament);
+            assert_eq!(played.len(), size - 1);
+            let TournamentState::Finished { champion } = *tournament.state() else {
+                panic!("The tournament is not finished");
+            };
+            assert_eq!(
+                tournament.rounds().last().unwrap()[0].winner(),
+                Some(champion)
+            );
+            assert_eq!(
+                tournament
+                    .champion()
+                    .map(|entrant| entrant.identity.clone()),
+                Some(tournament.entrants()[champion].identity.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_draws_and_interrupted_games() {
+        let mut tournament = full_tournament(TournamentFormat::SingleElimination, 2);
+        let final_pairing = tournament.ready_pairings().remove(0);
+
+        // A draw in elimination is played again, and so is an interrupted game.
+        for score in [Some((1, 1)), None] {
+            let game_id = uuid::Uuid::new_v4();
+            tournament.set_game(final_pairing.round, final_pairing.index, game_id);
+            assert!(tournament.ready_pairings().is_empty());
+            tournament.record_result(game_id, score);
+            assert!(!tournament.has_game(game_id));
+            assert_eq!(tournament.ready_pairings().len(), 1);
+            assert!(!tournament.is_finished());
+        }
+
+        let game_id = uuid::Uuid::new_v4();
+        tournament.set_game(final_pairing.round, final_pairing.index, game_id);
+        tournament.record_result(game_id, Some((0, 3)));
+        assert_eq!(
+            tournament
+                .champion()
+                .map(|entrant| entrant.identity.clone()),
+            Some(final_pairing.blue.identity)
+        );
+
+        // A draw counts in a round robin.
+        let mut tournament = full_tournament(TournamentFormat::RoundRobin, 2);
+        let pairing = tournament.ready_pairings().remove(0);
+        let game_id = uuid::Uuid::new_v4();
+        tournament.set_game(pairing.round, pairing.index, game_id);
+        tournament.record_result(game_id, Some((1, 1)));
+        assert!(tournament.is_finished());
+        assert!(tournament
+            .standings()
+            .iter()
+            .all(|(_, record)| record.draws == 1));
+    }
+
+    #[test]
+    fn test_registration() {
+        let mut tournament = Tournament::new(1, TournamentFormat::RoundRobin, 3);
+        assert_eq!(
+            tournament.toggle_registration("key0", "player0"),
+            Some(true)
+        );
+        assert_eq!(
+            tournament.toggle_registration("key0", "player0"),
+            Some(false)
+        );
+        assert!(tournament.entrants().is_empty());
+        for idx in 0..3 {
+            tournament.toggle_registration(&format!("key{idx}"), &format!("player{idx}"));
+        }
+        // Registration closes once the tournament starts.
+        assert_eq!(tournament.toggle_registration("key3", "player3"), None);
+        assert_eq!(tournament.entrants().len(), 3);
+    }
+}
@@ -4,9 +4,14 @@ use ratatui::{
     Terminal,
 };
 use russh::{server::Handle, ChannelId, CryptoVec};
+use serde::Serialize;
 use std::{
     fmt::{Debug, Formatter},
     io::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -37,7 +42,8 @@ impl SystemTimeTick for Tick {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum GameSide {
     Red,
     Blue,
@@ -50,6 +56,13 @@ impl GameSide {
             GameSide::Blue => Style::new().blue(),
         }
     }
+
+    pub fn opponent(&self) -> GameSide {
+        match self {
+            GameSide::Red => GameSide::Blue,
+            GameSide::Blue => GameSide::Red,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -58,6 +71,8 @@ pub struct TerminalHandle {
     // The sink collects the data which is finally flushed to the handle.
     sink: Vec<u8>,
     channel_id: ChannelId,
+    // Shared between clones, so that it counts every byte sent to the channel.
+    bytes_flushed: Arc<AtomicU64>,
 }
 
 impl Debug for TerminalHandle {
@@ -74,9 +89,14 @@ impl TerminalHandle {
             handle,
             sink: Vec::new(),
             channel_id,
+            bytes_flushed: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    pub fn bytes_flushed(&self) -> u64 {
+        self.bytes_flushed.load(Ordering::Relaxed)
+    }
+
     pub async fn close(&self) -> Result<(), ()> {
         self.handle.close(self.channel_id).await?;
         Ok(())
@@ -107,6 +127,8 @@ impl TerminalHandle {
                 "Failed to send data",
             ));
         }
+        self.bytes_flushed
+            .fetch_add(data_length as u64, Ordering::Relaxed);
         log::debug!(
             "Sent {} bytes of data to channel {}",
             data_length,
@@ -135,6 +157,8 @@ pub trait Vector2D {
     fn dot(&self, other: &Self) -> f32;
     fn magnitude(&self) -> f32;
     fn mul(self, rhs: f32) -> Self;
+    fn add(self, other: Self) -> Self;
+    fn sub(self, other: Self) -> Self;
 }
 
 impl Vector2D for (f32, f32) {
@@ -161,4 +185,12 @@ impl Vector2D for (f32, f32) {
         let (x, y) = self;
         (x * rhs, y * rhs)
     }
+
+    fn add(self, other: Self) -> Self {
+        (self.0 + other.0, self.1 + other.1)
+    }
+
+    fn sub(self, other: Self) -> Self {
+        (self.0 - other.0, self.1 - other.1)
+    }
 }